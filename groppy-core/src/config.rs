@@ -0,0 +1,754 @@
+//! User configuration loaded from `config.toml`.
+//!
+//! Looked up at `--config <path>`, else `$XDG_CONFIG_HOME/groppy/config.toml`,
+//! else `~/.config/groppy/config.toml`. A missing default file is not an error;
+//! every setting is optional and CLI flags fill in anything left unset.
+//!
+//! ```toml
+//! remote = "origin"              # Remote fetched when a repo has no entry below
+//! signing_keys = ["SHA256:..."]  # Keys trusted by signature verification
+//! only_branches = ["main", "master"]
+//! hooks = "hooks.rhai"           # Script deciding per repo; relative to this file
+//! strategy = "ff-only"           # Default; groups and repo entries override it
+//! fetch_notes = true             # Also fetch refs/notes/*; repo entries can opt out
+//! clean_check = "auto"           # How local changes are found; repo entries override it
+//! user_agent = "groppy (build-farm)"  # HTTP User-Agent for fetches
+//! roots = ["~/src", { path = "~/work", depth = 3 }, { path = "~/dotfiles", depth = 1 }]
+//!
+//! [token_files]                  # Access tokens by host, e.g. systemd credentials
+//! "github.com" = "/run/credentials/groppy.service/github"
+//!
+//! [rewrites]                     # Fetch from the new prefix instead of the old one
+//! "git@git.old-corp.example:" = "git@git.example.com:"
+//!
+//! [groups.mirrors]
+//! paths = ["~/mirrors"]          # Repos at or below these directories
+//! strategy = "reset"
+//!
+//! [repos."~/src/forked-tool"]
+//! remote = "upstream"
+//! strategy = "rebase"
+//! refspecs = ["+refs/pull/*/head:refs/remotes/upstream/pr/*"]
+//! verify_signatures = true
+//!
+//! [repos."/srv/deploy/site"]
+//! tag = "v2.4.1"                 # Or `branch = "release"`, or `track = "latest-tag"`
+//!
+//! [repos."~/src/dotfiles"]
+//! url = "git@github.com:me/dotfiles.git"   # Cloned by a default run when missing
+//! clone_depth = 1
+//! ```
+
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::forge::CloneSpec;
+
+/// Fetches every notes ref as is. Not forced: notes with local commits the
+/// remote lacks are left alone rather than overwritten.
+pub const NOTES_REFSPEC: &str = "refs/notes/*:refs/notes/*";
+
+/// Top-level configuration file contents.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Remote to fetch from when neither the repo entry nor `--remote` names one
+    pub remote: Option<String>,
+
+    /// Signing key fingerprints accepted by signature verification in every repo
+    pub signing_keys: Vec<String>,
+
+    /// Branches repos must be on to be updated when `--only-branch` is not given
+    pub only_branches: Vec<String>,
+
+    /// Rhai script with `should_update`/`after_update` hooks (see [`crate::hooks`])
+    pub hooks: Option<PathBuf>,
+
+    /// How repos without a group or repo strategy are updated
+    pub strategy: Option<Strategy>,
+
+    /// Fetch `refs/notes/*` in every repo whose entry doesn't say otherwise
+    pub fetch_notes: bool,
+
+    /// How repos without their own `clean_check` are checked for local changes
+    pub clean_check: Option<CleanCheck>,
+
+    /// Directories scanned along with any given on the command line (unless
+    /// `--only-args`), each with an optional depth of its own
+    #[serde(deserialize_with = "scan_roots")]
+    pub roots: Vec<ScanRoot>,
+
+    /// Files holding access tokens, by host (e.g. `github.com`), for forge APIs
+    /// and HTTPS fetches; relative to the config file
+    pub token_files: BTreeMap<String, PathBuf>,
+
+    /// HTTP `User-Agent` sent by fetches when `--user-agent` is not given
+    pub user_agent: Option<String>,
+
+    /// Remote URL prefixes replaced at fetch time (old → new), e.g. a renamed
+    /// server or a caching mirror; the repos' own config is left alone
+    pub rewrites: BTreeMap<String, String>,
+
+    /// Named sets of repos, by directory, sharing settings
+    pub groups: BTreeMap<String, GroupConfig>,
+
+    /// Per-repository overrides keyed by path (`~` expanded, symlinks resolved)
+    pub repos: BTreeMap<PathBuf, RepoConfig>,
+}
+
+/// Settings for a single repository.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Remote to fetch from and whose tracking ref HEAD is fast-forwarded to
+    pub remote: Option<String>,
+
+    /// Where to clone the repo from when its directory is missing
+    pub url: Option<String>,
+
+    /// Commits to fetch when `url` clones the repo (shallow); full history when unset
+    pub clone_depth: Option<NonZeroU32>,
+
+    /// Extra fetch refspecs, added to the remote's configured ones and `--refspec`
+    pub refspecs: Vec<String>,
+
+    /// Fetch `refs/notes/*` too (or not), whatever `--fetch-notes` and the top level say
+    pub fetch_notes: Option<bool>,
+
+    /// Verify upstream signatures for this repo even without `--verify-signatures`
+    pub verify_signatures: bool,
+
+    /// Signing key fingerprints accepted for this repo, on top of the top-level ones
+    pub signing_keys: Vec<String>,
+
+    /// Branches this repo must be on to be updated; replaces every other list
+    pub only_branches: Vec<String>,
+
+    /// Branch to keep checked out and updated, whatever HEAD is on
+    pub branch: Option<String>,
+
+    /// Tag to keep checked out (detached), whatever HEAD is on
+    pub tag: Option<String>,
+
+    /// Follow something other than a branch, e.g. the latest release tag
+    pub track: Option<Track>,
+
+    /// How this repo is updated; replaces its group's and the top-level strategy
+    pub strategy: Option<Strategy>,
+
+    /// How this repo is checked for local changes; replaces the top-level one
+    pub clean_check: Option<CleanCheck>,
+}
+
+/// A directory to scan, written as a path or `{ path, depth, recursive }`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScanRoot {
+    /// Directory to scan (`~` expanded, symlinks resolved)
+    pub path: PathBuf,
+
+    /// Levels below the directory to search, replacing `--max-depth` for it
+    pub depth: Option<usize>,
+
+    /// Search at any depth
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+/// Accepts each `roots` entry as a bare path or a table.
+fn scan_roots<'de, D>(deserializer: D) -> Result<Vec<ScanRoot>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Path(PathBuf),
+        Root(ScanRoot),
+    }
+    let entries = Vec::<Entry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Path(path) => ScanRoot {
+                path,
+                depth: None,
+                recursive: false,
+            },
+            Entry::Root(root) => root,
+        })
+        .collect())
+}
+
+/// Settings shared by every repository under a group's paths.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupConfig {
+    /// Directories whose repos (at any depth) belong to the group (`~` expanded)
+    pub paths: Vec<PathBuf>,
+
+    /// How the group's repos are updated; replaces the top-level strategy
+    pub strategy: Option<Strategy>,
+}
+
+/// How a repository is brought up to date with its upstream.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Fast-forward only; ahead or diverged branches are left alone
+    #[default]
+    FfOnly,
+    /// Fast-forward, or rebase local commits onto a diverged upstream
+    Rebase,
+    /// Fast-forward, or merge a diverged upstream into the branch
+    Merge,
+    /// Make the branch match upstream, dropping local commits
+    Reset,
+    /// Fetch and update the tracking ref; leave HEAD and the work tree alone
+    FetchOnly,
+    /// Leave the repo untouched, without fetching
+    Skip,
+}
+
+impl Strategy {
+    /// Config spelling, used in messages.
+    pub fn label(self) -> &'static str {
+        match self {
+            Strategy::FfOnly => "ff-only",
+            Strategy::Rebase => "rebase",
+            Strategy::Merge => "merge",
+            Strategy::Reset => "reset",
+            Strategy::FetchOnly => "fetch-only",
+            Strategy::Skip => "skip",
+        }
+    }
+}
+
+/// How a repository is checked for local changes before it is updated.
+/// Untracked files never count.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanCheck {
+    /// `git` when the repo has `core.fsmonitor` set, else `gix`
+    #[default]
+    Auto,
+    /// gix compares the index with the work tree itself, stat-ing every file
+    Gix,
+    /// `git status`, which can ask fsmonitor what changed instead
+    Git,
+}
+
+/// Non-branch targets a repository can follow.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Track {
+    /// Highest semver release tag on the remote (`v` prefix allowed, pre-releases skipped)
+    LatestTag,
+}
+
+/// A ref a repository is pinned to by its config entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pin {
+    Branch(String),
+    Tag(String),
+    LatestTag,
+}
+
+impl Config {
+    /// Loads the config from `explicit` (which must exist) or the default location
+    /// (which may be absent).
+    pub fn load(explicit: Option<&Path>) -> Result<Config> {
+        let path = match explicit {
+            Some(p) => p.to_path_buf(),
+            None => match default_path() {
+                Some(p) if p.exists() => p,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("cannot read config {}", path.display()))?;
+        let mut config =
+            Config::parse(&text).with_context(|| format!("invalid config {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let next_to_config = |file: &mut PathBuf| {
+            *file = match file.starts_with("~") {
+                true => resolve_path(file),
+                false => dir.join(&*file),
+            };
+        };
+        config.hooks.iter_mut().for_each(&next_to_config);
+        config.token_files.values_mut().for_each(&next_to_config);
+        Ok(config)
+    }
+
+    /// Reads every `token_files` entry, by host.
+    pub fn tokens(&self) -> Result<BTreeMap<String, String>> {
+        self.token_files
+            .iter()
+            .map(|(host, path)| Ok((host.clone(), read_token(path)?)))
+            .collect()
+    }
+
+    /// Parses config text and normalizes repo keys to canonical paths.
+    pub fn parse(text: &str) -> Result<Config> {
+        let mut config: Config = toml::from_str(text)?;
+        config.repos = std::mem::take(&mut config.repos)
+            .into_iter()
+            .map(|(path, repo)| (resolve_path(&path), repo))
+            .collect();
+        for root in &mut config.roots {
+            root.path = resolve_path(&root.path);
+            if root.recursive && root.depth.is_some() {
+                anyhow::bail!("root {}: set only one of depth, recursive", root.path.display());
+            }
+        }
+        for group in config.groups.values_mut() {
+            group.paths = group.paths.iter().map(|path| resolve_path(path)).collect();
+        }
+        if config.rewrites.keys().any(String::is_empty) {
+            anyhow::bail!("rewrites: empty URL prefix");
+        }
+        for key in &config.signing_keys {
+            check_signing_key(key)?;
+        }
+        for (path, repo) in &config.repos {
+            for key in &repo.signing_keys {
+                check_signing_key(key).with_context(|| format!("repo {}", path.display()))?;
+            }
+            let pins = [repo.branch.is_some(), repo.tag.is_some(), repo.track.is_some()];
+            if pins.into_iter().filter(|&set| set).count() > 1 {
+                anyhow::bail!("repo {}: set only one of branch, tag, track", path.display());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Repos with a `url`, to clone where their directory is missing.
+    pub fn clone_specs(&self) -> Vec<CloneSpec> {
+        self.repos
+            .iter()
+            .filter_map(|(path, repo)| {
+                Some(CloneSpec {
+                    path: path.clone(),
+                    url: repo.url.clone()?,
+                    branch: repo.branch.clone(),
+                    depth: repo.clone_depth,
+                    alt_url: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the entry for a canonical repository path, or an empty one.
+    pub fn repo(&self, path: &Path) -> RepoConfig {
+        self.repos.get(path).cloned().unwrap_or_default()
+    }
+
+    /// Resolves the remote to fetch for a repo: its own entry wins, then the
+    /// `--remote` flag, then the top-level `remote`. `None` means use the
+    /// repository's default fetch remote.
+    pub fn remote_for(&self, path: &Path, cli_remote: Option<&str>) -> Option<String> {
+        self.repo(path)
+            .remote
+            .or_else(|| cli_remote.map(str::to_string))
+            .or_else(|| self.remote.clone())
+    }
+
+    /// Extra fetch refspecs for a repo: `--refspec` values followed by the repo's
+    /// own, then the notes refspec when the repo's `fetch_notes`, else
+    /// `--fetch-notes` or the top-level `fetch_notes`, asks for notes.
+    pub fn refspecs_for(
+        &self,
+        path: &Path,
+        cli_refspecs: &[String],
+        cli_notes: bool,
+    ) -> Vec<String> {
+        let repo = self.repo(path);
+        let mut specs = cli_refspecs.to_vec();
+        specs.extend(repo.refspecs);
+        let notes = repo.fetch_notes.unwrap_or(cli_notes || self.fetch_notes);
+        if notes && !specs.iter().any(|spec| spec == NOTES_REFSPEC) {
+            specs.push(NOTES_REFSPEC.to_string());
+        }
+        specs
+    }
+
+    /// The branch or tag a repo's entry pins it to, if any.
+    pub fn pin_for(&self, path: &Path) -> Option<Pin> {
+        let repo = self.repo(path);
+        let track = repo.track.map(|Track::LatestTag| Pin::LatestTag);
+        repo.branch.map(Pin::Branch).or(repo.tag.map(Pin::Tag)).or(track)
+    }
+
+    /// Scan depth overrides by root path (`None`: unlimited), for roots whose
+    /// entry sets `depth` or `recursive`.
+    pub fn root_depths(&self) -> BTreeMap<PathBuf, Option<usize>> {
+        self.roots
+            .iter()
+            .filter(|root| root.recursive || root.depth.is_some())
+            .map(|root| (root.path.clone(), root.depth.filter(|_| !root.recursive)))
+            .collect()
+    }
+
+    /// Update strategy for a repo: its own entry wins, then the group with the
+    /// most specific path containing it, then the top-level `strategy`.
+    pub fn strategy_for(&self, path: &Path) -> Strategy {
+        let group = self
+            .groups
+            .values()
+            .filter(|group| group.strategy.is_some())
+            .flat_map(|group| group.paths.iter().map(move |dir| (dir, group)))
+            .filter(|(dir, _)| path.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .and_then(|(_, group)| group.strategy);
+        self.repo(path).strategy.or(group).or(self.strategy).unwrap_or_default()
+    }
+
+    /// Clean check for a repo: its own entry wins, then the top-level `clean_check`.
+    pub fn clean_check_for(&self, path: &Path) -> CleanCheck {
+        self.repo(path).clean_check.or(self.clean_check).unwrap_or_default()
+    }
+
+    /// Branches a repo may be updated on: its own list wins, then `--only-branch`,
+    /// then the top-level list. Empty means any branch.
+    pub fn only_branches_for(&self, path: &Path, cli_branches: &[String]) -> Vec<String> {
+        [self.repo(path).only_branches, cli_branches.to_vec(), self.only_branches.clone()]
+            .into_iter()
+            .find(|list| !list.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Allowed signing keys for a repo when signatures must be verified (by
+    /// `--verify-signatures` or the repo's `verify_signatures`), else `None`.
+    /// An empty list means any signature git itself accepts.
+    pub fn signing_keys_for(&self, path: &Path, cli_verify: bool) -> Option<Vec<String>> {
+        let repo = self.repo(path);
+        if !cli_verify && !repo.verify_signatures {
+            return None;
+        }
+        let mut keys = self.signing_keys.clone();
+        keys.extend(repo.signing_keys);
+        Some(keys)
+    }
+}
+
+/// Reads an access token from a file, e.g. a systemd credential or a mounted
+/// Kubernetes secret. Surrounding whitespace (the trailing newline) is dropped.
+pub fn read_token(path: &Path) -> Result<String> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read token file {}", path.display()))?;
+    let token = text.trim();
+    if token.is_empty() {
+        anyhow::bail!("token file {} is empty", path.display());
+    }
+    Ok(token.to_string())
+}
+
+/// Default config file location following the XDG base directory spec.
+fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".config")))?;
+    Some(base.join("groppy").join("config.toml"))
+}
+
+/// Returns `$HOME`, if set.
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Rejects a `signing_keys` entry that is neither an SSH fingerprint
+/// (`SHA256:...`) nor a GPG long key ID (16 hex digits) or full fingerprint
+/// (40 or 64): shorter IDs are easy to collide with a key of one's own.
+fn check_signing_key(key: &str) -> Result<()> {
+    if key.starts_with("SHA256:") {
+        return Ok(());
+    }
+    let hex: String = key.split_whitespace().collect();
+    if !matches!(hex.len(), 16 | 40 | 64) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!(
+            "signing_keys: {key:?} is not a GPG long key ID (16 hex digits), a full \
+             fingerprint (40 or 64), or an SSH SHA256: fingerprint"
+        );
+    }
+    Ok(())
+}
+
+/// Expands a leading `~` and canonicalizes the path when it exists, so config
+/// keys compare equal to the canonical paths produced by discovery.
+pub fn resolve_path(path: &Path) -> PathBuf {
+    let expanded = match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    };
+    std::fs::canonicalize(&expanded).unwrap_or(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty() {
+        let config = Config::parse("").unwrap();
+        assert!(config.remote.is_none());
+        assert!(config.repos.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        assert!(Config::parse("remtoe = \"origin\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_canonicalizes_repo_keys() {
+        let tmp = tempfile::tempdir().unwrap();
+        let text = format!("[repos.\"{}/.\"]\nremote = \"upstream\"\n", tmp.path().display());
+        let config = Config::parse(&text).unwrap();
+        let canonical = tmp.path().canonicalize().unwrap();
+        assert_eq!(config.repo(&canonical).remote.as_deref(), Some("upstream"));
+    }
+
+    #[test]
+    fn test_clone_specs() {
+        let config = Config::parse(
+            "[repos.\"/srv/app\"]\nurl = \"https://git.example.com/app.git\"\n\
+             branch = \"main\"\nclone_depth = 1\n[repos.\"/srv/local\"]\nremote = \"up\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.clone_specs(),
+            vec![CloneSpec {
+                path: PathBuf::from("/srv/app"),
+                url: "https://git.example.com/app.git".to_string(),
+                branch: Some("main".to_string()),
+                depth: NonZeroU32::new(1),
+                alt_url: None,
+            }]
+        );
+        assert!(Config::parse("[repos.\"/srv/app\"]\nclone_depth = 0\n").is_err());
+    }
+
+    #[test]
+    fn test_token_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.toml");
+        let text = r#"
+[token_files]
+"github.com" = "github.token"
+"gitea.example" = "none"
+"#;
+        std::fs::write(&path, text).unwrap();
+        let mut config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.token_files["github.com"], tmp.path().join("github.token"));
+        std::fs::write(tmp.path().join("github.token"), "ghp_abc\n").unwrap();
+        assert!(config.tokens().is_err(), "missing file must be an error");
+        config.token_files.remove("gitea.example");
+        assert_eq!(config.tokens().unwrap()["github.com"], "ghp_abc");
+        std::fs::write(tmp.path().join("empty"), " \n").unwrap();
+        assert!(read_token(&tmp.path().join("empty")).is_err());
+    }
+
+    #[test]
+    fn test_parse_rewrites() {
+        let text = r#"
+[rewrites]
+"git@git.old.example:" = "git@git.example.com:"
+"https://github.com/" = "https://mirror.example.com/github/"
+"#;
+        let config = Config::parse(text).unwrap();
+        assert_eq!(config.rewrites["git@git.old.example:"], "git@git.example.com:");
+        assert_eq!(config.rewrites.len(), 2);
+        assert!(Config::parse(r#"[rewrites]
+"" = "https://x/""#).is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_hooks_next_to_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, "hooks = \"hooks.rhai\"\n").unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.hooks, Some(tmp.path().join("hooks.rhai")));
+
+        std::fs::write(&path, "hooks = \"/etc/groppy.rhai\"\n").unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+        assert_eq!(config.hooks.as_deref(), Some(Path::new("/etc/groppy.rhai")));
+    }
+
+    #[test]
+    fn test_remote_for_precedence() {
+        let config = Config::parse(
+            "remote = \"global\"\n[repos.\"/srv/fork\"]\nremote = \"upstream\"\n",
+        )
+        .unwrap();
+        let fork = Path::new("/srv/fork");
+        let other = Path::new("/srv/other");
+        assert_eq!(config.remote_for(fork, Some("cli")).as_deref(), Some("upstream"));
+        assert_eq!(config.remote_for(other, Some("cli")).as_deref(), Some("cli"));
+        assert_eq!(config.remote_for(other, None).as_deref(), Some("global"));
+        assert!(Config::default().remote_for(other, None).is_none());
+    }
+
+    #[test]
+    fn test_refspecs_for_merges_cli_and_repo() {
+        let config = Config::parse(
+            "[repos.\"/srv/fork\"]\nrefspecs = [\"+refs/notes/*:refs/notes/*\"]\n",
+        )
+        .unwrap();
+        let cli = vec!["+refs/pull/*/head:refs/pull/*".to_string()];
+        assert_eq!(
+            config.refspecs_for(Path::new("/srv/fork"), &cli, false),
+            vec!["+refs/pull/*/head:refs/pull/*", "+refs/notes/*:refs/notes/*"]
+        );
+        assert_eq!(config.refspecs_for(Path::new("/srv/other"), &cli, false), cli);
+    }
+
+    #[test]
+    fn test_refspecs_for_fetch_notes_precedence() {
+        let config = Config::parse(
+            "fetch_notes = true\n[repos.\"/srv/big\"]\nfetch_notes = false\n\
+             [repos.\"/srv/app\"]\nrefspecs = [\"refs/notes/*:refs/notes/*\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.refspecs_for(Path::new("/srv/other"), &[], false), [NOTES_REFSPEC]);
+        assert_eq!(config.refspecs_for(Path::new("/srv/app"), &[], false), [NOTES_REFSPEC]);
+        assert!(config.refspecs_for(Path::new("/srv/big"), &[], true).is_empty());
+        let plain = Config::default();
+        assert!(plain.refspecs_for(Path::new("/srv/other"), &[], false).is_empty());
+        assert_eq!(plain.refspecs_for(Path::new("/srv/other"), &[], true), [NOTES_REFSPEC]);
+    }
+
+    #[test]
+    fn test_clean_check_for_precedence() {
+        let config = Config::parse(
+            "clean_check = \"gix\"\n[repos.\"/srv/mono\"]\nclean_check = \"git\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.clean_check_for(Path::new("/srv/mono")), CleanCheck::Git);
+        assert_eq!(config.clean_check_for(Path::new("/srv/app")), CleanCheck::Gix);
+        assert_eq!(Config::default().clean_check_for(Path::new("/srv/app")), CleanCheck::Auto);
+        assert!(Config::parse("clean_check = \"fast\"\n").is_err());
+    }
+
+    #[test]
+    fn test_only_branches_for_precedence() {
+        let config = Config::parse(
+            "only_branches = [\"main\"]\n[repos.\"/srv/app\"]\nonly_branches = [\"develop\"]\n",
+        )
+        .unwrap();
+        let app = Path::new("/srv/app");
+        let other = Path::new("/srv/other");
+        let cli = vec!["trunk".to_string()];
+        assert_eq!(config.only_branches_for(app, &cli), vec!["develop"]);
+        assert_eq!(config.only_branches_for(other, &cli), vec!["trunk"]);
+        assert_eq!(config.only_branches_for(other, &[]), vec!["main"]);
+        assert!(Config::default().only_branches_for(other, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_pin_for() {
+        let config = Config::parse(
+            "[repos.\"/srv/a\"]\nbranch = \"release\"\n[repos.\"/srv/b\"]\ntag = \"v1.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.pin_for(Path::new("/srv/a")), Some(Pin::Branch("release".into())));
+        assert_eq!(config.pin_for(Path::new("/srv/b")), Some(Pin::Tag("v1.0".into())));
+        assert_eq!(config.pin_for(Path::new("/srv/c")), None);
+
+        let config = Config::parse("[repos.\"/srv/t\"]\ntrack = \"latest-tag\"\n").unwrap();
+        assert_eq!(config.pin_for(Path::new("/srv/t")), Some(Pin::LatestTag));
+        assert!(Config::parse("[repos.\"/srv/t\"]\ntrack = \"newest\"\n").is_err());
+    }
+
+    #[test]
+    fn test_roots_paths_and_tables() {
+        let config = Config::parse(
+            "roots = [\"/srv/src\", { path = \"/srv/work\", depth = 3 }, \
+             { path = \"/srv/all\", recursive = true }]\n",
+        )
+        .unwrap();
+        assert_eq!(config.roots.len(), 3);
+        assert_eq!(config.roots[0].path, Path::new("/srv/src"));
+        let depths = config.root_depths();
+        assert_eq!(depths.get(Path::new("/srv/work")), Some(&Some(3)));
+        assert_eq!(depths.get(Path::new("/srv/all")), Some(&None));
+        assert!(!depths.contains_key(Path::new("/srv/src")));
+
+        assert!(Config::parse("roots = [{ path = \"/a\", depth = 1, recursive = true }]").is_err());
+        assert!(Config::parse("roots = [{ path = \"/a\", deep = 1 }]").is_err());
+    }
+
+    #[test]
+    fn test_strategy_for_precedence() {
+        let config = Config::parse(
+            "strategy = \"merge\"\n\
+             [groups.mirrors]\npaths = [\"/srv\"]\nstrategy = \"reset\"\n\
+             [groups.forks]\npaths = [\"/srv/forks\"]\nstrategy = \"rebase\"\n\
+             [repos.\"/srv/forks/pinned\"]\nstrategy = \"skip\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.strategy_for(Path::new("/srv/forks/pinned")), Strategy::Skip);
+        assert_eq!(config.strategy_for(Path::new("/srv/forks/tool")), Strategy::Rebase);
+        assert_eq!(config.strategy_for(Path::new("/srv/mirror")), Strategy::Reset);
+        assert_eq!(config.strategy_for(Path::new("/home/me/app")), Strategy::Merge);
+        assert_eq!(Config::default().strategy_for(Path::new("/srv")), Strategy::FfOnly);
+        assert!(Config::parse("strategy = \"squash\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_branch_and_tag() {
+        assert!(Config::parse("[repos.\"/srv/a\"]\nbranch = \"main\"\ntag = \"v1\"\n").is_err());
+        let text = "[repos.\"/srv/a\"]\ntag = \"v1\"\ntrack = \"latest-tag\"\n";
+        assert!(Config::parse(text).is_err());
+    }
+
+    #[test]
+    fn test_signing_keys_for() {
+        let config = Config::parse(
+            "signing_keys = [\"SHA256:a\"]\n[repos.\"/srv/mirror\"]\nverify_signatures = true\n\
+             signing_keys = [\"0123456789ABCDEF\"]\n",
+        )
+        .unwrap();
+        let mirror = Path::new("/srv/mirror");
+        let other = Path::new("/srv/other");
+        let (a, b) = ("SHA256:a".to_string(), "0123456789ABCDEF".to_string());
+        assert_eq!(config.signing_keys_for(mirror, false), Some(vec![a.clone(), b]));
+        assert_eq!(config.signing_keys_for(other, true), Some(vec![a]));
+        assert_eq!(config.signing_keys_for(other, false), None);
+    }
+
+    #[test]
+    fn test_short_gpg_key_ids_are_rejected() {
+        for key in ["89ABCDEF", "", "0123 4567 89ab cdeg"] {
+            let text = format!("signing_keys = [{key:?}]\n");
+            assert!(Config::parse(&text).is_err(), "{key:?}");
+        }
+        let text = "[repos.\"/srv/a\"]\nsigning_keys = [\"89ABCDEF\"]\n";
+        assert!(Config::parse(text).is_err());
+        let fingerprint = "0123 4567 89AB CDEF 0123  4567 89AB CDEF 0123 4567";
+        let text = format!("signing_keys = [\"0123 4567 89ab cdef\", {fingerprint:?}]\n");
+        assert!(Config::parse(&text).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_path_expands_tilde() {
+        let Some(home) = home_dir() else { return };
+        assert_eq!(
+            resolve_path(Path::new("~/groppy-no-such-dir")),
+            home.join("groppy-no-such-dir")
+        );
+    }
+
+    #[test]
+    fn test_load_missing_explicit_file() {
+        assert!(Config::load(Some(Path::new("/nonexistent/groppy.toml"))).is_err());
+    }
+}
@@ -0,0 +1,845 @@
+//! Finding repositories: parallel directory scans and repo list files.
+
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+/// Directory names never descended into while scanning: dependency caches and
+/// build output that commonly contain vendored git checkouts.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "target",
+    "build",
+    "dist",
+    "vendor",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".tox",
+];
+
+/// Options controlling how directories are scanned for repositories.
+pub struct ScanOptions {
+    pub follow_symlinks: bool, // Descend into symlinked subdirectories
+    pub hidden: bool,          // Descend into dot-directories below the roots
+    pub max_depth: usize,      // Levels below each root to search (0 = root only)
+    pub exclude: Vec<String>,  // Directory names skipped below the roots
+    pub root_depths: BTreeMap<PathBuf, Option<usize>>, // Per-root max_depth; None = unlimited
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: false,
+            hidden: true,
+            max_depth: 1,
+            exclude: DEFAULT_EXCLUDES.iter().map(|s| s.to_string()).collect(),
+            root_depths: BTreeMap::new(),
+        }
+    }
+}
+
+/// Removes duplicate paths from a Vec while preserving insertion order.
+/// Uses a HashSet for O(1) duplicate detection.
+pub fn unique_ordered(dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = std::collections::HashSet::new(); //  Track which paths we've encountered
+    let mut out = Vec::with_capacity(dirs.len());    // Pre-allocate output vector
+    for d in dirs {
+        if seen.insert(d.clone()) {
+            out.push(d); // Only add paths we haven't seen before
+        }
+    }
+    out
+}
+
+/// Discovers Git repositories in the given directories.
+///
+/// See [`scan_for_repositories`]; this variant does not report progress.
+pub fn find_git_repositories(
+    dirs: &[PathBuf],
+    opts: &ScanOptions,
+) -> (Vec<PathBuf>, Vec<String>) {
+    scan_for_repositories(dirs, opts, &AtomicUsize::new(0))
+}
+
+/// Walks the given directories in parallel looking for Git repositories.
+///
+///  For each directory visited (up to `opts.max_depth` levels below a root, or
+///  the root's own limit in `opts.root_depths`):
+///   - If the directory is a Git repo, add it and prune its subdirectories
+///   - Otherwise, keep descending
+///
+/// The walk honors `.ignore`/`.gitignore` files and never enters directories
+/// named in `opts.exclude`, nor dot-directories unless `opts.hidden` is set
+/// (roots themselves are always scanned). Symlinked subdirectories are
+/// skipped unless `opts.follow_symlinks` is set. Roots and symlink targets are
+/// tracked by canonical path, so a target reached twice (symlink loops, nested
+/// roots) is only walked once. `scanned` counts visited directories.
+///
+/// Returns a sorted, deduplicated list of repository paths and a list of warning
+/// messages for any directories that could not be read (e.g. permission denied).
+pub fn scan_for_repositories(
+    dirs: &[PathBuf],
+    opts: &ScanOptions,
+    scanned: &AtomicUsize,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let scan = walk(dirs, opts, scanned);
+    (scan.repos, scan.warnings)
+}
+
+/// Like [`scan_for_repositories`], but reuses an earlier scan of the same roots
+/// with the same options, cached in `cache_dir`, unless `rescan` is set.
+///
+/// A cached scan is used while every directory it walked keeps its mtime and
+/// every repository it found is still there. Creating or removing an entry
+/// changes the parent directory's mtime, so new and deleted repositories
+/// trigger a fresh walk; edits to `.gitignore` files don't, which is what
+/// `rescan` is for. A cache that cannot be written is reported as a warning.
+pub fn scan_cached(
+    dirs: &[PathBuf],
+    opts: &ScanOptions,
+    scanned: &AtomicUsize,
+    cache_dir: &Path,
+    rescan: bool,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let key = scan_key(dirs, opts);
+    let file = cache_dir.join(format!("{SCAN_CACHE}{:016x}.tsv", hash(&key)));
+    if !rescan
+        && let Some(cached) = Scan::load(&file, &key)
+        && cached.is_fresh()
+    {
+        tracing::debug!(cache = %file.display(), "using cached scan");
+        return (cached.repos, cached.warnings);
+    }
+    let scan = walk(dirs, opts, scanned);
+    let mut warnings = scan.warnings.clone();
+    if let Err(e) = scan.save(cache_dir, &file, &key) {
+        warnings.push(format!("cannot cache scan: {e:#}"));
+    }
+    (scan.repos, warnings)
+}
+
+/// Prefix of the scan cache files in the state dir, one per set of roots and options.
+const SCAN_CACHE: &str = "scan-";
+
+/// The result of walking a set of roots.
+struct Scan {
+    repos: Vec<PathBuf>,
+    warnings: Vec<String>,
+    walked: Vec<(PathBuf, SystemTime)>, // Directories walked that aren't repos, with mtimes
+}
+
+/// Everything that changes what a scan finds, as one line of text.
+fn scan_key(dirs: &[PathBuf], opts: &ScanOptions) -> String {
+    format!(
+        "{dirs:?} follow={} hidden={} depth={} exclude={:?} roots={:?}",
+        opts.follow_symlinks, opts.hidden, opts.max_depth, opts.exclude, opts.root_depths
+    )
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Scan {
+    /// Reads a cache file, `None` if it is missing, unreadable, or for another key.
+    ///
+    /// The format is a `key\t<key>` line, then `repo\t<path>`, `warning\t<text>`,
+    /// and `dir\t<mtime nanos>\t<path>` lines.
+    fn load(file: &Path, key: &str) -> Option<Scan> {
+        let text = std::fs::read_to_string(file).ok()?;
+        let mut lines = text.lines();
+        if lines.next()?.strip_prefix("key\t")? != key {
+            return None;
+        }
+        let mut scan = Scan {
+            repos: Vec::new(),
+            warnings: Vec::new(),
+            walked: Vec::new(),
+        };
+        for line in lines {
+            match line.split_once('\t')? {
+                ("repo", path) => scan.repos.push(PathBuf::from(path)),
+                ("warning", text) => scan.warnings.push(text.to_string()),
+                ("dir", rest) => {
+                    let (nanos, path) = rest.split_once('\t')?;
+                    let mtime = UNIX_EPOCH + Duration::from_nanos(nanos.parse().ok()?);
+                    scan.walked.push((PathBuf::from(path), mtime));
+                }
+                _ => return None,
+            }
+        }
+        Some(scan)
+    }
+
+    /// Whether the walked directories and found repositories are unchanged.
+    fn is_fresh(&self) -> bool {
+        self.walked.iter().all(|(dir, mtime)| modified(dir) == Some(*mtime))
+            && self.repos.iter().all(|repo| looks_like_repo(repo))
+    }
+
+    /// Writes the cache file atomically, so concurrent runs never read half of one.
+    fn save(&self, cache_dir: &Path, file: &Path, key: &str) -> Result<()> {
+        std::fs::create_dir_all(cache_dir)
+            .with_context(|| format!("cannot create state dir {}", cache_dir.display()))?;
+        let mut text = format!("key\t{key}\n");
+        for repo in &self.repos {
+            text += &format!("repo\t{}\n", repo.display());
+        }
+        for warning in &self.warnings {
+            text += &format!("warning\t{}\n", warning.replace('\n', " "));
+        }
+        for (dir, mtime) in &self.walked {
+            let nanos = mtime.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+            text += &format!("dir\t{nanos}\t{}\n", dir.display());
+        }
+        let tmp = file.with_extension(format!("tsv.{}", std::process::id()));
+        std::fs::write(&tmp, text).with_context(|| format!("cannot write {}", tmp.display()))?;
+        std::fs::rename(&tmp, file).with_context(|| format!("cannot replace {}", file.display()))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// The walk behind [`scan_for_repositories`] and [`scan_cached`].
+fn walk(dirs: &[PathBuf], opts: &ScanOptions, scanned: &AtomicUsize) -> Scan {
+    let mut warnings = Vec::new();
+    let mut roots = Vec::new();
+    for d in dirs {
+        match std::fs::metadata(d) {
+            Ok(meta) if meta.is_dir() => roots.push(canonical_or_original(d.clone())),
+            Ok(_) => {} // Skip non-directory paths
+            Err(e) => warnings.push(format!("cannot stat {}: {e}", d.display())),
+        }
+    }
+    let Some((first, rest)) = roots.split_first() else {
+        return Scan {
+            repos: Vec::new(),
+            warnings,
+            walked: Vec::new(),
+        };
+    };
+
+    let mut builder = ignore::WalkBuilder::new(first);
+    for root in rest {
+        builder.add(root);
+    }
+    let exclude: std::collections::HashSet<std::ffi::OsString> =
+        opts.exclude.iter().map(std::ffi::OsString::from).collect();
+    // One walker serves every root, so per-root limits are checked per entry
+    // against the deepest root containing it
+    let limits: Vec<(PathBuf, Option<usize>)> = roots
+        .iter()
+        .map(|root| {
+            let limit = opts.root_depths.get(root).copied().unwrap_or(Some(opts.max_depth));
+            (root.clone(), limit)
+        })
+        .collect();
+    let per_root = !opts.root_depths.is_empty();
+    builder
+        .hidden(!opts.hidden)
+        .follow_links(opts.follow_symlinks)
+        .max_depth((!per_root).then_some(opts.max_depth))
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            if per_root {
+                let limit = limits
+                    .iter()
+                    .filter(|(root, _)| entry.path().starts_with(root))
+                    .max_by_key(|(root, _)| root.components().count())
+                    .and_then(|(_, limit)| *limit);
+                if limit.is_some_and(|max| entry.depth() > max) {
+                    return false;
+                }
+            }
+            !entry.file_type().is_some_and(|t| t.is_dir()) || !exclude.contains(entry.file_name())
+        });
+
+    let repos = Mutex::new(Vec::new());
+    let warnings = Mutex::new(warnings);
+    let visited = Mutex::new(std::collections::HashSet::new());
+    let walked = Mutex::new(Vec::new());
+    builder.build_parallel().run(|| {
+        Box::new(|result| {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warnings.lock().unwrap().push(e.to_string());
+                    return ignore::WalkState::Continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                return ignore::WalkState::Continue;
+            }
+            scanned.fetch_add(1, Ordering::Relaxed);
+
+            let path = entry.path();
+            let key = if entry.path_is_symlink() {
+                canonical_or_original(path.to_path_buf())
+            } else {
+                path.to_path_buf()
+            };
+            if !visited.lock().unwrap().insert(key) {
+                return ignore::WalkState::Skip; // Already reached through another root or symlink
+            }
+            if looks_like_repo(path) && is_git_repo(path) {
+                repos
+                    .lock()
+                    .unwrap()
+                    .push(canonical_or_original(path.to_path_buf()));
+                return ignore::WalkState::Skip; // Don't descend into repositories
+            }
+            if let Some(mtime) = modified(path) {
+                walked.lock().unwrap().push((path.to_path_buf(), mtime));
+            }
+            ignore::WalkState::Continue
+        })
+    });
+
+    let mut repos = repos.into_inner().unwrap();
+    repos.sort();
+    repos.dedup();
+    let mut warnings = warnings.into_inner().unwrap();
+    warnings.sort();
+    Scan {
+        repos,
+        warnings,
+        walked: walked.into_inner().unwrap(),
+    }
+}
+
+/// Cheap filesystem check run before `is_git_repo` so the walker doesn't have
+/// to open every directory it visits: a `.git` entry (directory or gitdir file)
+/// or a bare layout with `HEAD` and `objects/`.
+fn looks_like_repo(path: &Path) -> bool {
+    path.join(".git").exists() || (path.join("HEAD").is_file() && path.join("objects").is_dir())
+}
+
+/// Opens a repository list file (`-` reads stdin) and parses it with `read_repo_list`.
+pub fn load_repo_list(path: &Path) -> Result<(Vec<PathBuf>, Vec<String>)> {
+    if path == Path::new("-") {
+        return Ok(read_repo_list(std::io::stdin().lock()));
+    }
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("cannot open {}: {e}", path.display()))?;
+    Ok(read_repo_list(std::io::BufReader::new(file)))
+}
+
+/// Parses one repository path per line, ignoring blank lines and `#` comments.
+///
+/// Paths are canonicalized and deduplicated. Lines that do not point at a Git
+/// repository are reported as warnings rather than aborting the run.
+pub fn read_repo_list(reader: impl std::io::BufRead) -> (Vec<PathBuf>, Vec<String>) {
+    let mut repos = Vec::new();
+    let mut warnings = Vec::new();
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                warnings.push(format!("cannot read repo list: {e}"));
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = PathBuf::from(line);
+        if is_git_repo(&path) {
+            repos.push(canonical_or_original(path));
+        } else {
+            warnings.push(format!("not a git repository: {line}"));
+        }
+    }
+    (unique_ordered(repos), warnings)
+}
+
+/// Resolves symlinks and relative components so the same repository reached
+/// through different paths (nested roots, symlinks) dedupes to one entry.
+/// Falls back to the original path if it cannot be canonicalized.
+fn canonical_or_original(path: PathBuf) -> PathBuf {
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+/// Checks whether a path is the root of a Git repository; see [`open_repository`].
+pub fn is_git_repo(path: &Path) -> bool {
+    open_repository(path).is_ok()
+}
+
+/// Opens the repository rooted at `path`: a work tree whose `.git` is a
+/// directory or a gitdir file (linked worktrees, submodules), or a bare
+/// repository.
+///
+/// Uses `gix::discover`, which follows gitdir files and `core.worktree` the
+/// way git does, but rejects directories that are merely inside a repository.
+pub fn open_repository(path: &Path) -> Result<gix::Repository> {
+    let repo = gix::discover(path)?;
+    let root = repo.workdir().unwrap_or(repo.path());
+    if canonical_or_original(root.to_path_buf()) != canonical_or_original(path.to_path_buf()) {
+        anyhow::bail!("not a git repository: {} is inside {}", path.display(), root.display());
+    }
+    Ok(repo)
+}
+
+/// Repositories whose object stores `repo` borrows through
+/// `objects/info/alternates` (`git clone --shared`/`--reference`). Garbage
+/// collection in one of them can drop objects `repo` still needs.
+pub fn alternates(repo: &gix::Repository) -> Vec<PathBuf> {
+    let objects = repo.common_dir().join("objects");
+    let file = objects.join("info").join("alternates");
+    parse_alternates(&std::fs::read_to_string(file).unwrap_or_default(), &objects)
+}
+
+/// Alternates file lines, relative ones resolved against `objects`, mapped from
+/// object directories to their repos: `/ref/linux.git/objects` →
+/// `/ref/linux.git`, `/src/app/.git/objects` → `/src/app`.
+fn parse_alternates(text: &str, objects: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let store = canonical_or_original(objects.join(line));
+            let repo = match store.file_name() {
+                Some(name) if name == "objects" => store.parent().unwrap_or(&store),
+                _ => &store,
+            };
+            match repo.file_name() {
+                Some(name) if name == ".git" => repo.parent().unwrap_or(repo).to_path_buf(),
+                _ => repo.to_path_buf(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use std::fs;
+
+    // ────────────────────────────────────────────────────────────
+    // unique_ordered
+    // ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_unique_ordered_no_duplicates() {
+        let input = vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")];
+        let out = unique_ordered(input);
+        assert_eq!(out.len(), 3);
+    }
+
+    #[test]
+    fn test_unique_ordered_with_duplicates() {
+        let input = vec![
+            PathBuf::from("a"),
+            PathBuf::from("b"),
+            PathBuf::from("a"),
+            PathBuf::from("c"),
+            PathBuf::from("b"),
+        ];
+        let out = unique_ordered(input);
+        assert_eq!(out, vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")]);
+    }
+
+    #[test]
+    fn test_unique_ordered_all_same() {
+        let input = vec![PathBuf::from("x"), PathBuf::from("x"), PathBuf::from("x")];
+        let out = unique_ordered(input);
+        assert_eq!(out.len(), 1);
+    }
+
+    #[test]
+    fn test_unique_ordered_empty() {
+        let out = unique_ordered(vec![]);
+        assert!(out.is_empty());
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // is_git_repo
+    // ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_is_git_repo_valid() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        assert!(is_git_repo(tmp.path()));
+    }
+
+    #[test]
+    fn test_is_git_repo_not_a_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(!is_git_repo(tmp.path()));
+    }
+
+    #[test]
+    fn test_is_git_repo_nonexistent() {
+        assert!(!is_git_repo(Path::new("/nonexistent/path/to/repo")));
+    }
+
+    #[test]
+    fn test_is_git_repo_rejects_subdirectory() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        fs::create_dir(tmp.path().join("src")).unwrap();
+        assert!(!is_git_repo(&tmp.path().join("src")));
+    }
+
+    #[test]
+    fn test_linked_worktree_is_found_and_opened() {
+        let tmp = tempfile::tempdir().unwrap();
+        let main = tmp.path().join("main");
+        let linked = tmp.path().join("linked");
+        init_repo_with_commit(&main);
+        git(&main, &["worktree", "add", "-q", "-b", "side", linked.to_str().unwrap()]);
+        assert!(linked.join(".git").is_file());
+
+        let repo = open_repository(&linked).unwrap();
+        assert_eq!(repo.head_name().unwrap().unwrap().shorten(), "side");
+        let (repos, _) =
+            find_git_repositories(&[tmp.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(repos.len(), 2, "{repos:?}");
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // find_git_repositories
+    // ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_find_git_repos_direct_is_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let (repos, _) =
+            find_git_repositories(&[tmp.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[test]
+    fn test_find_git_repos_subdir_repos() {
+        let parent = tempfile::tempdir().unwrap();
+        let repo1 = parent.path().join("repo1");
+        let repo2 = parent.path().join("repo2");
+        let not_repo = parent.path().join("notrepo");
+        fs::create_dir_all(&not_repo).unwrap();
+        init_repo_with_commit(&repo1);
+        init_repo_with_commit(&repo2);
+        let (repos, _) =
+            find_git_repositories(&[parent.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(repos.len(), 2);
+    }
+
+    #[test]
+    fn test_find_git_repos_empty_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (repos, _) =
+            find_git_repositories(&[tmp.path().to_path_buf()], &ScanOptions::default());
+        assert!(repos.is_empty());
+    }
+
+    #[test]
+    fn test_find_git_repos_nonexistent_dir() {
+        let (repos, _) =
+            find_git_repositories(&[PathBuf::from("/nonexistent/path")], &ScanOptions::default());
+        assert!(repos.is_empty());
+    }
+
+    #[test]
+    fn test_find_git_repos_file_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let fpath = tmp.path().join("file.txt");
+        fs::write(&fpath, "hi").unwrap();
+        let (repos, _) = find_git_repositories(&[fpath], &ScanOptions::default());
+        assert!(repos.is_empty());
+    }
+
+    #[test]
+    fn test_find_git_repos_deduplication() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let p = tmp.path().to_path_buf();
+        let (repos, _) = find_git_repositories(&[p.clone(), p], &ScanOptions::default());
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[test]
+    fn test_find_git_repos_dedupes_nested_roots() {
+        let parent = tempfile::tempdir().unwrap();
+        let repo = parent.path().join("repo");
+        init_repo_with_commit(&repo);
+        let (repos, _) =
+            find_git_repositories(&[parent.path().to_path_buf(), repo], &ScanOptions::default());
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[cfg(unix)]
+
+    #[test]
+    fn test_find_git_repos_dedupes_symlinked_repo() {
+        let parent = tempfile::tempdir().unwrap();
+        let repo = parent.path().join("repo");
+        init_repo_with_commit(&repo);
+        std::os::unix::fs::symlink(&repo, parent.path().join("link")).unwrap();
+        let (repos, _) =
+            find_git_repositories(&[parent.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(repos, vec![repo.canonicalize().unwrap()]);
+    }
+
+    #[cfg(unix)]
+
+    #[test]
+    fn test_find_git_repos_skips_symlinks_by_default() {
+        let outside = tempfile::tempdir().unwrap();
+        init_repo_with_commit(outside.path());
+        let parent = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), parent.path().join("link")).unwrap();
+        let (repos, _) =
+            find_git_repositories(&[parent.path().to_path_buf()], &ScanOptions::default());
+        assert!(repos.is_empty());
+    }
+
+    #[cfg(unix)]
+
+    #[test]
+    fn test_find_git_repos_follow_symlinks() {
+        let outside = tempfile::tempdir().unwrap();
+        init_repo_with_commit(outside.path());
+        let parent = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), parent.path().join("link")).unwrap();
+        let opts = ScanOptions {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(repos, vec![outside.path().canonicalize().unwrap()]);
+    }
+
+    #[cfg(unix)]
+
+    #[test]
+    fn test_find_git_repos_symlink_loop() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join("repo"));
+        std::os::unix::fs::symlink(parent.path(), parent.path().join("loop")).unwrap();
+        let opts = ScanOptions {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[test]
+    fn test_find_git_repos_max_depth() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join("a").join("b").join("deep"));
+        let (shallow, _) =
+            find_git_repositories(&[parent.path().to_path_buf()], &ScanOptions::default());
+        assert!(shallow.is_empty());
+        let opts = ScanOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let (deep, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(deep.len(), 1);
+    }
+
+    #[test]
+    fn test_find_git_repos_hidden_dirs() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join(".config").join("nvim"));
+        let root = parent.path().join(".config");
+        let opts = ScanOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let (found, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(found.len(), 1);
+
+        let opts = ScanOptions {
+            hidden: false,
+            ..opts
+        };
+        let (skipped, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert!(skipped.is_empty());
+        let (from_hidden_root, _) = find_git_repositories(&[root], &opts);
+        assert_eq!(from_hidden_root.len(), 1);
+    }
+
+    #[test]
+    fn test_find_git_repos_root_depths() {
+        let work = tempfile::tempdir().unwrap();
+        let dotfiles = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&work.path().join("a").join("b").join("deep"));
+        init_repo_with_commit(&dotfiles.path().join("a").join("nested"));
+        let work_root = work.path().canonicalize().unwrap();
+        let dotfiles_root = dotfiles.path().canonicalize().unwrap();
+        let opts = ScanOptions {
+            root_depths: BTreeMap::from([(work_root.clone(), None)]),
+            ..Default::default()
+        };
+        let (repos, _) = find_git_repositories(&[work_root, dotfiles_root], &opts);
+        assert_eq!(repos.len(), 1, "{repos:?}");
+        assert!(repos[0].ends_with("deep"));
+    }
+
+    #[test]
+    fn test_find_git_repos_skips_excluded_dirs() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join("node_modules").join("dep"));
+        init_repo_with_commit(&parent.path().join("app"));
+        let opts = ScanOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(repos, vec![parent.path().join("app").canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn test_find_git_repos_custom_excludes() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join("node_modules").join("dep"));
+        init_repo_with_commit(&parent.path().join("archive").join("old"));
+        let opts = ScanOptions {
+            max_depth: 2,
+            exclude: vec!["archive".to_string()],
+            ..Default::default()
+        };
+        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()], &opts);
+        assert_eq!(repos.len(), 1);
+        assert!(repos[0].ends_with("node_modules/dep"));
+    }
+
+    #[test]
+    fn test_find_git_repos_excluded_root_still_scanned() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("build");
+        init_repo_with_commit(&root.join("repo"));
+        let (repos, _) = find_git_repositories(&[root], &ScanOptions::default());
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[test]
+    fn test_scan_for_repositories_counts_directories() {
+        let parent = tempfile::tempdir().unwrap();
+        fs::create_dir_all(parent.path().join("one")).unwrap();
+        fs::create_dir_all(parent.path().join("two")).unwrap();
+        let scanned = AtomicUsize::new(0);
+        scan_for_repositories(&[parent.path().to_path_buf()], &ScanOptions::default(), &scanned);
+        assert_eq!(scanned.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_scan_cached_reuses_until_tree_changes() {
+        let parent = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        init_repo_with_commit(&parent.path().join("a"));
+        let dirs = [parent.path().to_path_buf()];
+        let opts = ScanOptions::default();
+        let scan = |rescan| {
+            let scanned = AtomicUsize::new(0);
+            let (repos, _) = scan_cached(&dirs, &opts, &scanned, state.path(), rescan);
+            (repos.len(), scanned.load(Ordering::Relaxed))
+        };
+
+        assert_eq!(scan(false), (1, 2));
+        assert_eq!(scan(false), (1, 0)); // Cached: nothing walked
+        init_repo_with_commit(&parent.path().join("b"));
+        assert_eq!(scan(false), (2, 3)); // New entry in the root changed its mtime
+        assert_eq!(scan(false), (2, 0));
+        assert_eq!(scan(true), (2, 3));
+
+        let other = ScanOptions {
+            max_depth: 0,
+            ..ScanOptions::default()
+        };
+        let (repos, _) = scan_cached(&dirs, &other, &AtomicUsize::new(0), state.path(), false);
+        assert!(repos.is_empty(), "options are part of the cache key");
+    }
+
+    #[test]
+    fn test_looks_like_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(!looks_like_repo(tmp.path()));
+        init_repo_with_commit(tmp.path());
+        assert!(looks_like_repo(tmp.path()));
+        let bare = tmp.path().join("bare.git");
+        init_bare_repo(&bare);
+        assert!(looks_like_repo(&bare));
+    }
+
+    #[test]
+    fn test_find_git_repos_skips_subdir_if_parent_is_repo() {
+        let parent = tempfile::tempdir().unwrap();
+        init_repo_with_commit(parent.path());
+        let nested = parent.path().join("nested");
+        init_repo_with_commit(&nested);
+        let (repos, _) =
+            find_git_repositories(&[parent.path().to_path_buf()], &ScanOptions::default());
+        assert_eq!(repos.len(), 1);
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // read_repo_list
+    // ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_read_repo_list_valid_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        init_repo_with_commit(&repo);
+        let input = format!("{}\n\n# comment\n{}\n", repo.display(), repo.display());
+        let (repos, warnings) = read_repo_list(input.as_bytes());
+        assert_eq!(repos, vec![repo.canonicalize().unwrap()]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_read_repo_list_warns_on_non_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let input = format!("{}\n", tmp.path().display());
+        let (repos, warnings) = read_repo_list(input.as_bytes());
+        assert!(repos.is_empty());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_load_repo_list_missing_file() {
+        assert!(load_repo_list(Path::new("/nonexistent/list.txt")).is_err());
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // alternates
+    // ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_parse_alternates() {
+        let text = "# comment\n/ref/linux.git/objects\n\n/src/app/.git/objects\n/odd/store\n";
+        assert_eq!(
+            parse_alternates(text, Path::new("/nonexistent/objects")),
+            ["/ref/linux.git", "/src/app", "/odd/store"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_alternates_of_shared_clone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let shared = tmp.path().join("shared");
+        git(tmp.path(), &["clone", "-q", "--shared", clone_path.to_str().unwrap(), "shared"]);
+        let repo = open_repository(&shared).unwrap();
+        assert_eq!(alternates(&repo), vec![clone_path.canonicalize().unwrap()]);
+        assert!(alternates(&open_repository(&clone_path).unwrap()).is_empty());
+    }
+}
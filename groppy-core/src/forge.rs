@@ -0,0 +1,1011 @@
+//! Repositories listed by a forge's API, so a run can clone the ones missing
+//! from disk and update the rest: Gitea and Forgejo organizations (Codeberg
+//! included), GitHub organizations, Bitbucket Cloud workspaces, Azure DevOps
+//! projects, and gitolite servers (asked over SSH, no HTTP API).
+
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::discovery::{is_git_repo, open_repository};
+use crate::state::{CachedPage, ListingCache};
+use crate::update::{redact_url, run_git};
+
+/// A repository as a forge lists it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostedRepo {
+    pub name: String,           // Directory it is cloned into
+    pub https_url: String,      // Clone URL over HTTPS
+    pub ssh_url: String,        // Clone URL over SSH
+    pub branch: Option<String>, // Branch to check out; the default branch when None
+}
+
+/// Which of a hosted repo's URLs to clone from.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CloneProtocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+impl CloneProtocol {
+    /// Every protocol, in the order `--clone-protocol` lists them.
+    pub const ALL: [CloneProtocol; 2] = [CloneProtocol::Https, CloneProtocol::Ssh];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CloneProtocol::Https => "https",
+            CloneProtocol::Ssh => "ssh",
+        }
+    }
+}
+
+/// A source of hosted repositories.
+pub trait Forge: Send + Sync {
+    /// Short description for messages, e.g. `gitea codeberg.org/forgejo`.
+    fn describe(&self) -> String;
+
+    /// Every repository that is not archived, in the forge's order.
+    fn list(&self) -> Result<Vec<HostedRepo>>;
+}
+
+/// Repositories asked for per API page; Gitea caps `limit` at 50 by default
+/// and instances may set it lower.
+const PAGE_SIZE: usize = 50;
+
+/// A Gitea or Forgejo organization, read through `/api/v1`.
+pub struct Gitea {
+    pub base_url: String,      // Instance root, e.g. `https://codeberg.org`
+    pub org: String,           // Organization name
+    pub token: Option<String>, // Access token; public repos only without one
+}
+
+/// The fields of Gitea's repository object that groppy uses.
+#[derive(Deserialize)]
+struct GiteaRepo {
+    name: String,
+    clone_url: String,
+    ssh_url: String,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl Forge for Gitea {
+    fn describe(&self) -> String {
+        let host = self.base_url.split("://").last().unwrap_or(&self.base_url);
+        format!("gitea {}/{}", host.trim_end_matches('/'), self.org)
+    }
+
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let client = http_client()?;
+        let base = self.base_url.trim_end_matches('/');
+        gitea_pages(|page| {
+            let org = &self.org;
+            let url = format!("{base}/api/v1/orgs/{org}/repos?page={page}&limit={PAGE_SIZE}");
+            let mut request = client.get(&url);
+            if let Some(token) = &self.token {
+                request = request.header("Authorization", format!("token {token}"));
+            }
+            parse_gitea_page(&send(request, &url)?)
+                .with_context(|| format!("unexpected response from {url}"))
+        })
+    }
+}
+
+/// Every repo of an organization, reading pages from 1 until one lists none.
+/// A short page is not taken as the last: instances with a lower
+/// `MAX_RESPONSE_ITEMS` return fewer than [`PAGE_SIZE`] on every page.
+fn gitea_pages(
+    mut fetch: impl FnMut(usize) -> Result<(Vec<HostedRepo>, usize)>,
+) -> Result<Vec<HostedRepo>> {
+    let mut repos = Vec::new();
+    for page in 1usize.. {
+        let (batch, listed) = fetch(page)?;
+        if listed == 0 {
+            break;
+        }
+        repos.extend(batch);
+    }
+    Ok(repos)
+}
+
+/// One page of `GET /orgs/{org}/repos`: the repos that aren't archived, and
+/// how many the page listed in all (which decides whether another follows).
+fn parse_gitea_page(text: &str) -> Result<(Vec<HostedRepo>, usize)> {
+    let page: Vec<GiteaRepo> = serde_json::from_str(text)?;
+    let listed = page.len();
+    let repos = page
+        .into_iter()
+        .filter(|repo| !repo.archived)
+        .map(|repo| HostedRepo {
+            name: repo.name,
+            https_url: repo.clone_url,
+            ssh_url: repo.ssh_url,
+            branch: None,
+        })
+        .collect();
+    Ok((repos, listed))
+}
+
+/// GitHub's REST API.
+const GITHUB_API: &str = "https://api.github.com";
+
+/// A GitHub organization on github.com.
+pub struct GitHub {
+    pub org: String,
+    pub token: Option<String>,      // Public repos only without it
+    pub cache_dir: Option<PathBuf>, // State dir keeping pages for conditional requests
+}
+
+/// The fields of GitHub's repository object that groppy uses.
+#[derive(Deserialize)]
+struct GitHubRepo {
+    name: String,
+    clone_url: String,
+    ssh_url: String,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl Forge for GitHub {
+    fn describe(&self) -> String {
+        format!("github {}", self.org)
+    }
+
+    /// Pages GitHub answers `304 Not Modified` for (unchanged since the cached
+    /// `ETag`) are read from the cache and don't count against the rate limit.
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let client = http_client()?;
+        let first = format!("{GITHUB_API}/orgs/{}/repos?per_page=100", self.org);
+        let cache = self.cache_dir.as_deref().map(ListingCache::load).unwrap_or_default();
+        let cached = cache.pages(&first);
+        let mut pages = Vec::new();
+        let mut next = Some(first.clone());
+        while let Some(url) = next {
+            let mut request = client
+                .get(&url)
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28");
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let hit = cached.iter().find(|page| page.url == url && !page.etag.is_empty());
+            if let Some(page) = hit {
+                request = request.header(reqwest::header::IF_NONE_MATCH, &page.etag);
+            }
+            let (status, headers, body) = exchange(request, &url)?;
+            let page = match hit {
+                Some(page) if status == reqwest::StatusCode::NOT_MODIFIED => page.clone(),
+                _ => CachedPage {
+                    etag: header_text(&headers, reqwest::header::ETAG).unwrap_or_default(),
+                    next: next_link(&headers),
+                    url,
+                    body,
+                },
+            };
+            next = page.next.clone();
+            pages.push(page);
+        }
+        let mut repos = Vec::new();
+        for page in &pages {
+            let batch = parse_github_page(&page.body);
+            repos.extend(batch.with_context(|| format!("unexpected response from {}", page.url))?);
+        }
+        if let Some(dir) = &self.cache_dir
+            && let Err(e) = ListingCache::store(dir, &first, pages)
+        {
+            tracing::warn!("cannot cache the listing of {first}: {e:#}");
+        }
+        Ok(repos)
+    }
+}
+
+/// A header's value as text, if present and readable.
+fn header_text(
+    headers: &reqwest::header::HeaderMap,
+    name: reqwest::header::HeaderName,
+) -> Option<String> {
+    Some(headers.get(name)?.to_str().ok()?.to_string())
+}
+
+/// One page of `GET /orgs/{org}/repos`: the repos that aren't archived.
+fn parse_github_page(text: &str) -> Result<Vec<HostedRepo>> {
+    let page: Vec<GitHubRepo> = serde_json::from_str(text)?;
+    let repos = page
+        .into_iter()
+        .filter(|repo| !repo.archived)
+        .map(|repo| HostedRepo {
+            name: repo.name,
+            https_url: repo.clone_url,
+            ssh_url: repo.ssh_url,
+            branch: None,
+        })
+        .collect();
+    Ok(repos)
+}
+
+/// The `rel="next"` URL of a `Link` header, which GitHub pages with.
+fn next_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = header_text(headers, reqwest::header::LINK)?;
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.split_once(';')?;
+        let next = params.split(';').any(|param| param.trim() == "rel=\"next\"");
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        next.then(|| url.to_string())
+    })
+}
+
+/// Bitbucket Cloud's REST API.
+const BITBUCKET_API: &str = "https://api.bitbucket.org/2.0";
+
+/// A Bitbucket Cloud workspace.
+pub struct Bitbucket {
+    pub workspace: String,
+    pub auth: Option<BitbucketAuth>, // Public repos only without it
+}
+
+/// How to authenticate to Bitbucket Cloud.
+pub enum BitbucketAuth {
+    AppPassword { username: String, password: String }, // HTTP basic auth
+    Token(String), // OAuth or workspace access token, sent as a bearer token
+}
+
+/// One page of `GET /repositories/{workspace}`.
+#[derive(Deserialize)]
+struct BitbucketPage {
+    values: Vec<BitbucketRepo>,
+    next: Option<String>, // URL of the following page
+}
+
+#[derive(Deserialize)]
+struct BitbucketRepo {
+    slug: String,
+    links: BitbucketLinks,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLinks {
+    #[serde(default)]
+    clone: Vec<BitbucketLink>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLink {
+    name: String, // `https` or `ssh`
+    href: String,
+}
+
+impl Forge for Bitbucket {
+    fn describe(&self) -> String {
+        format!("bitbucket {}", self.workspace)
+    }
+
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let client = http_client()?;
+        let mut repos = Vec::new();
+        let mut next = Some(format!("{BITBUCKET_API}/repositories/{}?pagelen=100", self.workspace));
+        while let Some(url) = next {
+            let request = match &self.auth {
+                Some(BitbucketAuth::AppPassword { username, password }) => {
+                    client.get(&url).basic_auth(username, Some(password))
+                }
+                Some(BitbucketAuth::Token(token)) => client.get(&url).bearer_auth(token),
+                None => client.get(&url),
+            };
+            let (batch, following) = parse_bitbucket_page(&send(request, &url)?)
+                .with_context(|| format!("unexpected response from {url}"))?;
+            repos.extend(batch);
+            next = following;
+        }
+        Ok(repos)
+    }
+}
+
+/// One page of `GET /repositories/{workspace}`: its repos and the next page's URL.
+fn parse_bitbucket_page(text: &str) -> Result<(Vec<HostedRepo>, Option<String>)> {
+    let page: BitbucketPage = serde_json::from_str(text)?;
+    let repos = page
+        .values
+        .into_iter()
+        .map(|repo| {
+            let link = |name: &str| {
+                let link = repo.links.clone.iter().find(|link| link.name == name);
+                link.map(|link| link.href.clone()).unwrap_or_default()
+            };
+            HostedRepo {
+                https_url: link("https"),
+                ssh_url: link("ssh"),
+                name: repo.slug,
+                branch: None,
+            }
+        })
+        .collect();
+    Ok((repos, page.next))
+}
+
+/// An Azure DevOps project.
+pub struct AzureDevOps {
+    pub organization: String,
+    pub project: String,
+    pub pat: Option<String>, // Personal access token (Code: Read)
+}
+
+impl AzureDevOps {
+    /// Parses `org/project` or a project URL in any of its forms:
+    /// `https://dev.azure.com/org/project`, `https://org@dev.azure.com/org/project`,
+    /// `https://org.visualstudio.com/project`, the same with `/DefaultCollection`,
+    /// and repo URLs below them (`.../_git/<repo>`). `%20` in URLs reads as a space.
+    pub fn parse_project(text: &str) -> Result<(String, String)> {
+        let invalid = || {
+            anyhow::anyhow!("invalid Azure DevOps project {text:?}: expected ORG/PROJECT or a URL")
+        };
+        let (host, path) = match text.split_once("://") {
+            Some((_, rest)) => {
+                let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+                (authority.rsplit_once('@').map_or(authority, |(_, host)| host), path)
+            }
+            None => ("dev.azure.com", text),
+        };
+        let mut parts = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.replace("%20", " "));
+        let organization = match host.strip_suffix(".visualstudio.com") {
+            Some(org) => org.to_string(),
+            None if host == "dev.azure.com" => parts.next().ok_or_else(invalid)?,
+            None => return Err(invalid()),
+        };
+        let mut project = parts.next().ok_or_else(invalid)?;
+        if project.eq_ignore_ascii_case("DefaultCollection") {
+            project = parts.next().ok_or_else(invalid)?;
+        }
+        Ok((organization, project))
+    }
+}
+
+#[derive(Deserialize)]
+struct AzureRepos {
+    value: Vec<AzureRepo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureRepo {
+    name: String,
+    remote_url: String,
+    #[serde(default)]
+    ssh_url: String,
+    #[serde(default)]
+    is_disabled: bool,
+}
+
+impl Forge for AzureDevOps {
+    fn describe(&self) -> String {
+        format!("azure devops {}/{}", self.organization, self.project)
+    }
+
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let url = format!(
+            "https://dev.azure.com/{}/{}/_apis/git/repositories?api-version=7.1",
+            self.organization,
+            self.project.replace(' ', "%20")
+        );
+        let mut request = http_client()?.get(&url);
+        if let Some(pat) = &self.pat {
+            request = request.basic_auth("", Some(pat)); // PATs go in the password
+        }
+        parse_azure_repos(&send(request, &url)?)
+            .with_context(|| format!("unexpected response from {url}"))
+    }
+}
+
+/// `GET /{project}/_apis/git/repositories` (one page, unpaginated), disabled repos left out.
+fn parse_azure_repos(text: &str) -> Result<Vec<HostedRepo>> {
+    let repos: AzureRepos = serde_json::from_str(text)?;
+    let repos = repos
+        .value
+        .into_iter()
+        .filter(|repo| !repo.is_disabled)
+        .map(|repo| HostedRepo {
+            name: repo.name,
+            https_url: repo.remote_url,
+            ssh_url: repo.ssh_url,
+            branch: None,
+        })
+        .collect();
+    Ok(repos)
+}
+
+/// A gitolite server, asked with `ssh <host> info` which repos the key can read.
+pub struct Gitolite {
+    pub host: String, // SSH destination, e.g. `git@git.example.com` or a `~/.ssh/config` alias
+}
+
+impl Forge for Gitolite {
+    fn describe(&self) -> String {
+        format!("gitolite {}", self.host)
+    }
+
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let output = std::process::Command::new("ssh")
+            .args(["-o", "BatchMode=yes", &self.host, "info"])
+            .stdin(std::process::Stdio::null())
+            .output()
+            .context("cannot run ssh")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("ssh {} info failed: {}", self.host, stderr.trim());
+        }
+        Ok(parse_gitolite_info(&String::from_utf8_lossy(&output.stdout), &self.host))
+    }
+}
+
+/// Output of gitolite's `info` command: a greeting, then one `<perms>\t<repo>`
+/// line per repo. Wildcard patterns (`CREATOR/..*`) aren't repos and are left
+/// out, as are repos without read access. Both URLs are SSH: gitolite has no HTTPS.
+fn parse_gitolite_info(text: &str, host: &str) -> Vec<HostedRepo> {
+    text.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(perms, name)| perms.contains('R') && is_gitolite_repo_name(name))
+        .map(|(_, name)| {
+            let url = format!("{host}:{name}");
+            HostedRepo {
+                name: name.to_string(),
+                https_url: url.clone(),
+                ssh_url: url,
+                branch: None,
+            }
+        })
+        .collect()
+}
+
+/// Whether a name matches gitolite's plain repo name pattern rather than a regex.
+fn is_gitolite_repo_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "-._@/+".contains(c))
+        && !name.contains("..")
+}
+
+/// Blocking HTTP client identifying groppy to the server.
+fn http_client() -> Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .user_agent(concat!("groppy/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("cannot create HTTP client")
+}
+
+/// Tries per request while the server answers that the rate limit is used up.
+const RATE_LIMIT_ATTEMPTS: u32 = 4;
+
+/// Sends a request and returns the body, turning non-2xx answers into errors
+/// that carry the server's message. Rate-limited answers are retried after the
+/// wait the server asks for, however long: an hourly limit resets within the
+/// hour, and the listing cannot go on without it.
+fn send(request: reqwest::blocking::RequestBuilder, url: &str) -> Result<String> {
+    exchange(request, url).map(|(_, _, body)| body)
+}
+
+/// [`send`], also returning the status and headers (e.g. for `Link` paging);
+/// `304 Not Modified` passes as well, for conditional requests.
+fn exchange(
+    request: reqwest::blocking::RequestBuilder,
+    url: &str,
+) -> Result<(reqwest::StatusCode, reqwest::header::HeaderMap, String)> {
+    let mut request = request;
+    let mut attempt = 1;
+    loop {
+        let retry = request.try_clone();
+        let response = request.send().with_context(|| format!("cannot reach {url}"))?;
+        let status = response.status();
+        let wait = rate_limit_wait(status, response.headers(), attempt, SystemTime::now());
+        if let Some(wait) = wait
+            && attempt < RATE_LIMIT_ATTEMPTS
+            && let Some(retry) = retry
+        {
+            tracing::warn!("{url}: rate limited, retrying in {}s", wait.as_secs());
+            std::thread::sleep(wait);
+            request = retry;
+            attempt += 1;
+            continue;
+        }
+        let headers = response.headers().clone();
+        let body = response.text().with_context(|| format!("cannot read response from {url}"))?;
+        if !status.is_success() && status != reqwest::StatusCode::NOT_MODIFIED {
+            anyhow::bail!("{url}: HTTP status {status}: {}", body.trim());
+        }
+        return Ok((status, headers, body));
+    }
+}
+
+/// How long to wait before retrying a rate-limited answer (429, or 403 with no
+/// requests remaining or a `Retry-After`): `Retry-After` seconds, else until
+/// `X-RateLimit-Reset` (Unix seconds), else 10s doubling per attempt. None when
+/// not rate limited.
+fn rate_limit_wait(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    attempt: u32,
+    now: SystemTime,
+) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<u64>().ok();
+    let exhausted = header("x-ratelimit-remaining") == Some(0);
+    let secondary = header("retry-after").is_some(); // GitHub's limits on bursts
+    let limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN && (exhausted || secondary));
+    if !limited {
+        return None;
+    }
+    if let Some(secs) = header("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    if let Some(reset) = header("x-ratelimit-reset") {
+        let now = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        return Some(Duration::from_secs(reset.saturating_sub(now).max(1)));
+    }
+    Some(Duration::from_secs(5 << attempt.min(10)))
+}
+
+/// Local checkouts of a forge's repositories after cloning the missing ones.
+#[derive(Default)]
+pub struct Checkouts {
+    pub repos: Vec<PathBuf>,          // Every hosted repo now on disk, cloned or not
+    pub cloned: Vec<PathBuf>,         // Those cloned by this call
+    pub mismatches: Vec<UrlMismatch>, // Existing repos whose origin is another URL
+    pub warnings: Vec<String>,        // Failed clones, and non-repo directories in the way
+}
+
+/// An existing checkout whose `origin` isn't the URL it is listed with, e.g.
+/// after the project moved to another organization or was renamed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlMismatch {
+    pub path: PathBuf,
+    pub origin: String, // What `remote.origin.url` was
+    pub listed: String, // The forge's, manifest's or config's URL
+    pub fixed: bool,    // Whether `origin` was pointed at `listed`
+}
+
+/// A repository to have on disk, cloned when its directory is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneSpec {
+    pub path: PathBuf,             // Checkout directory
+    pub url: String,               // Where to clone from
+    pub branch: Option<String>,    // Branch to check out; the default branch when None
+    pub depth: Option<NonZeroU32>, // Commits to fetch for a shallow clone; all when None
+    pub alt_url: Option<String>,   // The same repo over another protocol; not a mismatch
+}
+
+/// Clones each hosted repo without a directory under `root` yet into
+/// `root/<name>`, as [`ensure_cloned`] does. Names that could land outside
+/// `root` (absolute, or with `..`) are refused and reported.
+pub fn clone_missing(
+    hosted: &[HostedRepo],
+    root: &Path,
+    protocol: CloneProtocol,
+    fix_remotes: bool,
+    interrupt: &AtomicBool,
+) -> Checkouts {
+    let mut refused = Vec::new();
+    let mut specs = Vec::new();
+    for repo in hosted {
+        let mut components = Path::new(&repo.name).components().peekable();
+        let plain = components.peek().is_some()
+            && components.all(|part| matches!(part, std::path::Component::Normal(_)));
+        if !plain {
+            refused.push(format!("refusing repo name {:?}", repo.name));
+            continue;
+        }
+        let (url, alt_url) = match protocol {
+            CloneProtocol::Https => (&repo.https_url, &repo.ssh_url),
+            CloneProtocol::Ssh => (&repo.ssh_url, &repo.https_url),
+        };
+        specs.push(CloneSpec {
+            path: root.join(&repo.name),
+            url: url.clone(),
+            branch: repo.branch.clone(),
+            depth: None,
+            alt_url: Some(alt_url.clone()),
+        });
+    }
+    let mut checkouts = ensure_cloned(&specs, fix_remotes, interrupt);
+    checkouts.warnings.splice(0..0, refused);
+    checkouts
+}
+
+/// Clones each spec whose directory doesn't exist yet, in parallel on the
+/// current rayon pool. Existing repositories are returned for updating, with
+/// an `origin` at another URL reported, and repointed when `fix_remotes` is
+/// set; existing directories that aren't repositories are left alone and
+/// reported.
+pub fn ensure_cloned(specs: &[CloneSpec], fix_remotes: bool, interrupt: &AtomicBool) -> Checkouts {
+    let results: Vec<(PathBuf, Result<bool>)> = specs
+        .par_iter()
+        .map(|spec| {
+            let path = &spec.path;
+            let outcome = match path.exists() {
+                true if is_git_repo(path) => Ok(false),
+                true => Err(anyhow::anyhow!("{} exists but is not a repository", path.display())),
+                false => clone_repo(spec, interrupt).map(|()| true),
+            };
+            (path.clone(), outcome)
+        })
+        .collect();
+
+    let mut checkouts = Checkouts::default();
+    for (path, outcome) in results {
+        match outcome {
+            Ok(cloned) => {
+                if cloned {
+                    checkouts.cloned.push(path.clone());
+                }
+                checkouts.repos.push(path);
+            }
+            Err(e) => checkouts.warnings.push(format!("{e:#}")),
+        }
+    }
+    let was_there = |spec: &&CloneSpec| {
+        checkouts.repos.contains(&spec.path) && !checkouts.cloned.contains(&spec.path)
+    };
+    let existing: Vec<&CloneSpec> = specs.iter().filter(was_there).collect();
+    for spec in existing {
+        match check_origin(spec, fix_remotes) {
+            Ok(mismatch) => checkouts.mismatches.extend(mismatch),
+            Err(e) => checkouts.warnings.push(format!("{}: {e:#}", spec.path.display())),
+        }
+    }
+    checkouts
+}
+
+/// Compares a checkout's `origin` with its spec's URLs, pointing it at the
+/// spec's `url` when they differ and `fix` is set. No `origin`, no mismatch.
+fn check_origin(spec: &CloneSpec, fix: bool) -> Result<Option<UrlMismatch>> {
+    let repo = open_repository(&spec.path)?;
+    let Ok(remote) = repo.find_remote("origin") else {
+        return Ok(None);
+    };
+    let Some(origin) = remote.url(gix::remote::Direction::Fetch) else {
+        return Ok(None);
+    };
+    let origin = origin.to_bstring().to_string();
+    let mut listed = std::iter::once(&spec.url).chain(&spec.alt_url);
+    if listed.any(|url| same_repo_url(url, &origin)) {
+        return Ok(None);
+    }
+    if fix {
+        run_git(&spec.path, &["remote", "set-url", "origin", &spec.url])?;
+    }
+    let path = spec.path.display();
+    let (old, listed) = (redact_url(&origin), redact_url(&spec.url));
+    tracing::info!(%path, origin = %old, %listed, fixed = fix, "origin moved");
+    Ok(Some(UrlMismatch {
+        path: spec.path.clone(),
+        origin,
+        listed: spec.url.clone(),
+        fixed: fix,
+    }))
+}
+
+/// Whether two URLs name the same repository, ignoring a `.git` suffix, a
+/// trailing slash, and the user name in an HTTP(S) URL.
+fn same_repo_url(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let url = url.trim_end_matches('/');
+        let url = url.strip_suffix(".git").unwrap_or(url);
+        match url.split_once("://") {
+            Some((scheme @ ("http" | "https"), rest)) => {
+                let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+                let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+                format!("{scheme}://{host}/{path}")
+            }
+            _ => url.to_string(),
+        }
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Clones a spec's URL into its path and checks out its branch, else the
+/// default branch. A failed clone leaves nothing behind.
+fn clone_repo(spec: &CloneSpec, interrupt: &AtomicBool) -> Result<()> {
+    let (url, path) = (&spec.url, &spec.path);
+    let shown = redact_url(url);
+    tracing::info!(url = %shown, path = %path.display(), depth = ?spec.depth, "cloning");
+    let fail = || format!("cannot clone {shown} into {}", path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(fail)?; // Nested names, new machines
+    }
+    let mut prepare = gix::prepare_clone(url.as_str(), path).with_context(fail)?;
+    if let Some(branch) = &spec.branch {
+        prepare = prepare.with_ref_name(Some(branch.as_str())).with_context(fail)?;
+    }
+    if let Some(depth) = spec.depth {
+        prepare = prepare.with_shallow(gix::remote::fetch::Shallow::DepthAtRemote(depth));
+    }
+    let (mut checkout, _) = prepare
+        .fetch_then_checkout(gix::progress::Discard, interrupt)
+        .with_context(fail)?;
+    checkout
+        .main_worktree(gix::progress::Discard, interrupt)
+        .with_context(fail)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_gitea_page_skips_archived() {
+        let text = r#"[
+            {"name": "app", "clone_url": "https://codeberg.org/org/app.git",
+             "ssh_url": "git@codeberg.org:org/app.git", "archived": false, "stars_count": 3},
+            {"name": "old", "clone_url": "https://codeberg.org/org/old.git",
+             "ssh_url": "git@codeberg.org:org/old.git", "archived": true}
+        ]"#;
+        let (repos, listed) = parse_gitea_page(text).unwrap();
+        assert_eq!(listed, 2);
+        assert_eq!(
+            repos,
+            vec![HostedRepo {
+                name: "app".to_string(),
+                https_url: "https://codeberg.org/org/app.git".to_string(),
+                ssh_url: "git@codeberg.org:org/app.git".to_string(),
+                branch: None,
+            }]
+        );
+        assert!(parse_gitea_page(r#"{"message": "not found"}"#).is_err());
+    }
+
+    #[test]
+    fn test_gitea_pages_continue_past_short_pages() {
+        let repo = |n: usize| HostedRepo {
+            name: format!("r{n}"),
+            https_url: String::new(),
+            ssh_url: String::new(),
+            branch: None,
+        };
+        let mut asked = Vec::new();
+        let repos = gitea_pages(|page| {
+            asked.push(page);
+            // The server caps pages at 30, below the limit asked for
+            let listed = if page <= 2 { 30 } else { 0 };
+            Ok(((0..listed).map(|n| repo(page * 100 + n)).collect(), listed))
+        })
+        .unwrap();
+        assert_eq!(asked, [1, 2, 3]);
+        assert_eq!(repos.len(), 60);
+    }
+
+    #[test]
+    fn test_parse_github_page_and_links() {
+        let text = r#"[
+            {"name": "api", "clone_url": "https://github.com/org/api.git",
+             "ssh_url": "git@github.com:org/api.git", "archived": false, "fork": true},
+            {"name": "old", "clone_url": "https://github.com/org/old.git",
+             "ssh_url": "git@github.com:org/old.git", "archived": true}
+        ]"#;
+        let repos = parse_github_page(text).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].ssh_url, "git@github.com:org/api.git");
+        assert!(parse_github_page(r#"{"message": "Not Found"}"#).is_err());
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(next_link(&headers), None);
+        let link = "<https://api.github.com/organizations/1/repos?page=1>; rel=\"prev\", \
+                    <https://api.github.com/organizations/1/repos?page=3>; rel=\"next\", \
+                    <https://api.github.com/organizations/1/repos?page=5>; rel=\"last\"";
+        headers.insert(reqwest::header::LINK, link.parse().unwrap());
+        let next = next_link(&headers);
+        assert_eq!(next.as_deref(), Some("https://api.github.com/organizations/1/repos?page=3"));
+    }
+
+    #[test]
+    fn test_parse_bitbucket_page() {
+        let text = r#"{"pagelen": 100, "values": [
+            {"slug": "api", "name": "API", "links": {"clone": [
+                {"name": "https", "href": "https://ann@bitbucket.org/team/api.git"},
+                {"name": "ssh", "href": "git@bitbucket.org:team/api.git"}]}}],
+            "next": "https://api.bitbucket.org/2.0/repositories/team?page=2"}"#;
+        let (repos, next) = parse_bitbucket_page(text).unwrap();
+        assert_eq!(
+            repos,
+            vec![HostedRepo {
+                name: "api".to_string(),
+                https_url: "https://ann@bitbucket.org/team/api.git".to_string(),
+                ssh_url: "git@bitbucket.org:team/api.git".to_string(),
+                branch: None,
+            }]
+        );
+        assert_eq!(next.as_deref(), Some("https://api.bitbucket.org/2.0/repositories/team?page=2"));
+        let (_, next) = parse_bitbucket_page(r#"{"values": []}"#).unwrap();
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn test_rate_limit_wait() {
+        use reqwest::StatusCode;
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for &(name, value) in pairs {
+                headers.insert(name, HeaderValue::from_static(value));
+            }
+            headers
+        };
+        let wait = |status, pairs: &[(&'static str, &'static str)], attempt| {
+            rate_limit_wait(status, &headers(pairs), attempt, now)
+        };
+        let secs = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(wait(StatusCode::TOO_MANY_REQUESTS, &[("retry-after", "7")], 1), secs(7));
+        assert_eq!(wait(StatusCode::TOO_MANY_REQUESTS, &[], 1), secs(10));
+        assert_eq!(wait(StatusCode::TOO_MANY_REQUESTS, &[], 2), secs(20));
+        let exhausted = [("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1030")];
+        assert_eq!(wait(StatusCode::FORBIDDEN, &exhausted, 1), secs(30));
+        assert_eq!(wait(StatusCode::FORBIDDEN, &[("x-ratelimit-remaining", "12")], 1), None);
+        assert_eq!(wait(StatusCode::FORBIDDEN, &[], 1), None);
+        assert_eq!(wait(StatusCode::FORBIDDEN, &[("retry-after", "60")], 1), secs(60));
+        assert_eq!(wait(StatusCode::OK, &[("retry-after", "7")], 1), None);
+    }
+
+    #[test]
+    fn test_azure_parse_project_url_forms() {
+        let expected = ("contoso".to_string(), "Fabrikam Fiber".to_string());
+        for text in [
+            "contoso/Fabrikam Fiber",
+            "https://dev.azure.com/contoso/Fabrikam%20Fiber",
+            "https://contoso@dev.azure.com/contoso/Fabrikam%20Fiber/_git/web",
+            "https://contoso.visualstudio.com/Fabrikam%20Fiber/",
+            "https://contoso.visualstudio.com/DefaultCollection/Fabrikam%20Fiber",
+        ] {
+            assert_eq!(AzureDevOps::parse_project(text).unwrap(), expected, "{text}");
+        }
+        assert!(AzureDevOps::parse_project("contoso").is_err());
+        assert!(AzureDevOps::parse_project("https://github.com/contoso/web").is_err());
+    }
+
+    #[test]
+    fn test_parse_azure_repos_skips_disabled() {
+        let text = r#"{"count": 2, "value": [
+            {"id": "1", "name": "web", "isDisabled": false,
+             "remoteUrl": "https://contoso@dev.azure.com/contoso/Fabrikam/_git/web",
+             "sshUrl": "git@ssh.dev.azure.com:v3/contoso/Fabrikam/web"},
+            {"id": "2", "name": "old", "isDisabled": true,
+             "remoteUrl": "https://contoso@dev.azure.com/contoso/Fabrikam/_git/old"}]}"#;
+        let repos = parse_azure_repos(text).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].ssh_url, "git@ssh.dev.azure.com:v3/contoso/Fabrikam/web");
+    }
+
+    #[test]
+    fn test_parse_gitolite_info() {
+        let text = concat!(
+            "hello ann, this is git@git.example.com running gitolite3 v3.6.13 on git 2.43.0\n",
+            "\n",
+            " R W\tgitolite-admin\n",
+            " R  \tteam/app\n",
+            " R W C\tCREATOR/..*\n",
+            "   W \twrite-only\n",
+        );
+        let repos = parse_gitolite_info(text, "git@git.example.com");
+        let names: Vec<&str> = repos.iter().map(|repo| repo.name.as_str()).collect();
+        assert_eq!(names, ["gitolite-admin", "team/app"]);
+        assert_eq!(repos[1].ssh_url, "git@git.example.com:team/app");
+        assert_eq!(repos[1].https_url, repos[1].ssh_url);
+    }
+
+    #[test]
+    fn test_gitea_describe() {
+        let gitea = Gitea {
+            base_url: "https://codeberg.org/".to_string(),
+            org: "forgejo".to_string(),
+            token: None,
+        };
+        assert_eq!(gitea.describe(), "gitea codeberg.org/forgejo");
+    }
+
+    #[test]
+    fn test_clone_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_remote_and_clone(tmp.path());
+        let remote = tmp.path().join("remote.git").display().to_string();
+        let root = tmp.path().join("checkouts");
+        fs::create_dir_all(root.join("in-the-way")).unwrap();
+        let hosted = |name: &str, url: &str| HostedRepo {
+            name: name.to_string(),
+            https_url: url.to_string(),
+            ssh_url: String::new(),
+            branch: None,
+        };
+        let hosted = [
+            hosted("fresh", &remote),
+            hosted("in-the-way", &remote),
+            hosted("broken", &tmp.path().join("missing.git").display().to_string()),
+            hosted("team/nested", &remote),
+            hosted("../escape", &remote),
+        ];
+
+        let checkouts =
+            clone_missing(&hosted, &root, CloneProtocol::Https, false, &AtomicBool::new(false));
+        let cloned = vec![root.join("fresh"), root.join("team/nested")];
+        assert_eq!(checkouts.cloned, cloned);
+        assert_eq!(checkouts.repos, cloned);
+        assert_eq!(checkouts.warnings.len(), 3, "{:?}", checkouts.warnings);
+        assert!(!tmp.path().join("escape").exists());
+        assert!(is_git_repo(&root.join("fresh")));
+        assert!(!root.join("broken").exists(), "failed clone cleaned up");
+
+        let interrupt = AtomicBool::new(false);
+        let again = clone_missing(&hosted[..1], &root, CloneProtocol::Https, false, &interrupt);
+        assert!(again.cloned.is_empty());
+        assert_eq!(again.repos, vec![root.join("fresh")]);
+    }
+
+    #[test]
+    fn test_ensure_cloned_shallow_branch() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (work, _) = init_remote_and_clone(tmp.path());
+        git(&work, &["checkout", "-b", "release"]);
+        fs::write(work.join("b.txt"), "b").unwrap();
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "-m", "second"]);
+        git(&work, &["push", "origin", "release"]);
+        let spec = CloneSpec {
+            path: tmp.path().join("laptop/app"),
+            url: format!("file://{}", tmp.path().join("remote.git").display()),
+            branch: Some("release".to_string()),
+            depth: NonZeroU32::new(1),
+            alt_url: None,
+        };
+
+        let checkouts = ensure_cloned(std::slice::from_ref(&spec), false, &AtomicBool::new(false));
+        assert_eq!(checkouts.cloned, vec![spec.path.clone()], "{:?}", checkouts.warnings);
+        let repo = gix::open(&spec.path).unwrap();
+        let head = repo.head_name().unwrap().unwrap();
+        assert_eq!(head.shorten().to_string(), "release");
+        assert!(repo.is_shallow());
+    }
+
+    #[test]
+    fn test_ensure_cloned_reports_and_fixes_moved_origin() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone) = init_remote_and_clone(tmp.path());
+        let remote = tmp.path().join("remote.git").display().to_string();
+        let spec = |url: &str| CloneSpec {
+            path: clone.clone(),
+            url: url.to_string(),
+            branch: None,
+            depth: None,
+            alt_url: None,
+        };
+        let interrupt = AtomicBool::new(false);
+
+        let same = ensure_cloned(&[spec(&format!("{remote}/"))], false, &interrupt);
+        assert!(same.mismatches.is_empty());
+        let moved = "https://git.example.com/new-org/app.git";
+        let report = ensure_cloned(&[spec(moved)], false, &interrupt);
+        assert_eq!(report.repos, vec![clone.clone()]);
+        assert_eq!(report.mismatches.len(), 1);
+        assert!(!report.mismatches[0].fixed);
+        assert_eq!(report.mismatches[0].origin, remote);
+
+        let fixed = ensure_cloned(&[spec(moved)], true, &interrupt);
+        assert!(fixed.mismatches[0].fixed);
+        assert!(ensure_cloned(&[spec(moved)], false, &interrupt).mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_same_repo_url() {
+        let bitbucket = "https://bitbucket.org/team/api";
+        assert!(same_repo_url("https://ann@bitbucket.org/team/api.git", bitbucket));
+        assert!(same_repo_url("git@host:team/app.git", "git@host:team/app"));
+        assert!(!same_repo_url("https://host/old-org/app", "https://host/new-org/app"));
+    }
+}
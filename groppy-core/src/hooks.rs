@@ -0,0 +1,597 @@
+//! User hooks: a rhai script, named by `hooks` in the config, that decides
+//! per repo whether to update and runs custom logic afterwards.
+//!
+//! Both functions are optional; a script defining neither does nothing.
+//!
+//! ```rhai
+//! // `repo`: #{ path, branch, remote, url } ("" when unknown)
+//! fn should_update(repo) {
+//!     if repo.url.contains("github.com/big-corp/") && weekday() != "Friday" {
+//!         return "big-corp repos update on Fridays";   // Skip, with this reason
+//!     }
+//!     true                                              // Or false: skip silently
+//! }
+//!
+//! // `result`: #{ path, outcome, success, message, files_changed, commits }
+//! fn after_update(repo, result) {
+//!     if result.outcome == "updated" && repo.path.ends_with("/site") {
+//!         run("make", ["-C", repo.path, "deploy"]);    // Exit code, -1 if not started
+//!     }
+//! }
+//! ```
+//!
+//! Besides rhai's standard library, scripts get `run(program, args)`,
+//! `weekday()` ("Monday".."Sunday") and `hour()` (0-23), both in UTC, and
+//! `print`/`debug`, which go to the log. In `after_update`, `changed_files()`
+//! lists the paths the update changed and `touched(patterns)` says whether any
+//! of them matches one of the `.gitignore`-style patterns (a string or an
+//! array), e.g. `if touched(["Cargo.lock"]) { run("cargo", ["fetch"]) }`.
+//!
+//! Commands started by `run`, and `--exec` commands, see the repo in
+//! `GROPPY_REPO` and `GROPPY_BRANCH`, HEAD before the update in
+//! `GROPPY_OLD_SHA`, and, from `after_update`, HEAD afterwards in
+//! `GROPPY_NEW_SHA` and the outcome word in `GROPPY_RESULT` ("" for what is
+//! unknown). The same values fill in `{repo}`, `{name}` (the directory name),
+//! `{branch}`, `{old}`, `{new}` and `{status}` in `run`'s program and
+//! arguments and, shell-quoted, in `--exec` commands, so one command line
+//! serves every repo: `run("git", ["-C", "{repo}", "tag", "deployed-{new}"])`.
+//! Other braces are passed through as written.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use rhai::{AST, Array, Dynamic, Engine, Map, Scope};
+
+use crate::discovery::open_repository;
+use crate::status::RepoStatus;
+use crate::update::{UpdateOptions, redact_url, select_remote};
+
+const SHOULD_UPDATE: &str = "should_update";
+const AFTER_UPDATE: &str = "after_update";
+
+/// Script steps per hook call before it is stopped, so a runaway loop can't
+/// hold a worker forever; real hooks need a few thousand at most.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Nested function calls a hook may make.
+const MAX_CALL_LEVELS: usize = 32;
+
+/// Nesting depth of expressions (and of statements in functions).
+const MAX_EXPR_DEPTH: usize = 64;
+
+thread_local! {
+    /// `GROPPY_*` variables for commands `run` starts, set while this
+    /// thread is inside a hook.
+    static RUN_ENV: RefCell<Vec<(&'static str, String)>> = const { RefCell::new(Vec::new()) };
+
+    /// The run's interrupt flag (`--deadline`, `--max-errors`, an abort) while
+    /// this thread is inside a hook; a set flag stops the script.
+    static INTERRUPT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// A compiled hook script, shared by every worker.
+pub struct Hooks {
+    engine: Engine,
+    ast: AST,
+}
+
+/// What `should_update` decided for a repository.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Update,
+    Skip(String), // Reason given by the script; empty for a bare `false`
+}
+
+impl Hooks {
+    /// Compiles the script at `path`.
+    pub fn load(path: &Path) -> Result<Hooks> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read hooks {}", path.display()))?;
+        Hooks::compile(&text).with_context(|| format!("invalid hooks {}", path.display()))
+    }
+
+    /// Compiles script text.
+    pub fn compile(text: &str) -> Result<Hooks> {
+        let engine = engine();
+        let ast = engine.compile(text).map_err(|e| anyhow::anyhow!("{e}"))?;
+        Ok(Hooks { engine, ast })
+    }
+
+    /// Asks the script whether to update a repository. No `should_update`
+    /// function means update.
+    pub fn should_update(
+        &self,
+        path: &Path,
+        repo: &gix::Repository,
+        opts: &UpdateOptions,
+    ) -> Result<Verdict> {
+        if !self.defines(SHOULD_UPDATE) {
+            return Ok(Verdict::Update);
+        }
+        let map = repo_map(path, repo, opts);
+        let head = repo.head_id().ok().map(|id| id.to_string());
+        let env = run_env(&map, head, None, None);
+        let answer = self.call(SHOULD_UPDATE, (map,), env, opts)?;
+        if let Ok(update) = answer.as_bool() {
+            return Ok(if update { Verdict::Update } else { Verdict::Skip(String::new()) });
+        }
+        match answer.into_string() {
+            Ok(reason) => Ok(Verdict::Skip(reason)),
+            Err(kind) => anyhow::bail!("should_update returned {kind}, expected bool or string"),
+        }
+    }
+
+    /// Hands a finished repository to the script's `after_update`, if any.
+    pub fn after_update(
+        &self,
+        path: &Path,
+        status: &RepoStatus,
+        opts: &UpdateOptions,
+    ) -> Result<()> {
+        if !self.defines(AFTER_UPDATE) {
+            return Ok(());
+        }
+        let (repo, env) = finished_env(path, status, opts);
+        self.call(AFTER_UPDATE, (repo, status_map(status)), env, opts).map(drop)
+    }
+
+    fn defines(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn call(
+        &self,
+        name: &str,
+        args: impl rhai::FuncArgs,
+        env: Vec<(&'static str, String)>,
+        opts: &UpdateOptions,
+    ) -> Result<Dynamic> {
+        RUN_ENV.set(env);
+        INTERRUPT.set(Some(opts.interrupt.clone()));
+        let answer = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args);
+        INTERRUPT.take();
+        RUN_ENV.take();
+        answer.map_err(|e| anyhow::anyhow!("{name} hook: {e}"))
+    }
+}
+
+/// `--exec`: runs `command` through the shell in the repo's directory once its
+/// update finished, with the same `GROPPY_*` variables as `after_update`'s
+/// commands and its placeholders expanded, shell-quoted. Failing to start or a
+/// non-zero exit is an error.
+pub fn exec(command: &str, path: &Path, status: &RepoStatus, opts: &UpdateOptions) -> Result<()> {
+    let (_, env) = finished_env(path, status, opts);
+    let command = expand(command, &env, shell_quote);
+    let command = command.as_str();
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    tracing::debug!(command, "exec command");
+    let exit = std::process::Command::new(shell)
+        .args([flag, command])
+        .current_dir(path)
+        .envs(env)
+        .status()
+        .map_err(|e| anyhow::anyhow!("--exec failed to start: {e}"))?;
+    match exit.code() {
+        Some(0) => Ok(()),
+        Some(code) => anyhow::bail!("--exec exited with {code}"),
+        None => anyhow::bail!("--exec was killed by a signal"),
+    }
+}
+
+/// The script engine with groppy's helper functions registered.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.on_progress(|_| {
+        let interrupted = INTERRUPT.with_borrow(|flag| {
+            flag.as_ref().is_some_and(|flag| flag.load(Ordering::Acquire))
+        });
+        interrupted.then(|| Dynamic::from("run interrupted"))
+    });
+    engine.on_print(|text| tracing::info!(target: "groppy_core::hooks", "{text}"));
+    engine.on_debug(|text, _, _| tracing::debug!(target: "groppy_core::hooks", "{text}"));
+    engine.register_fn("run", run);
+    engine.register_fn("changed_files", || -> Array {
+        let env = RUN_ENV.with_borrow(|env| env.clone());
+        changed_files(&env).into_iter().map(Dynamic::from).collect()
+    });
+    engine.register_fn("touched", |patterns: Array| {
+        let patterns: Vec<String> = patterns.into_iter().map(|p| p.to_string()).collect();
+        touched(&patterns)
+    });
+    engine.register_fn("touched", |pattern: &str| touched(&[pattern.to_string()]));
+    engine.register_fn("weekday", || WEEKDAYS[(days_since_epoch() + 4) as usize % 7].to_string());
+    engine.register_fn("hour", || (seconds_since_epoch() % 86_400 / 3_600) as i64);
+    engine
+}
+
+/// `run(program, args)`: runs a command with inherited output, returning its
+/// exit code (-1 when it could not be started or was killed by a signal).
+/// Placeholders in `program` and `args` are expanded first.
+fn run(program: &str, args: Array) -> i64 {
+    let env = RUN_ENV.with_borrow(|env| env.clone());
+    let program = expand(program, &env, str::to_string);
+    let program = program.as_str();
+    let args: Vec<String> =
+        args.into_iter().map(|arg| expand(&arg.to_string(), &env, str::to_string)).collect();
+    tracing::debug!(program, ?args, "hook command");
+    match std::process::Command::new(program).args(&args).envs(env).status() {
+        Ok(status) => status.code().map_or(-1, i64::from),
+        Err(e) => {
+            tracing::warn!(program, "hook command failed to start: {e}");
+            -1
+        }
+    }
+}
+
+/// Paths (relative to the repo, `/`-separated) that differ between HEAD
+/// before and after the update; renames count under both names. Empty when
+/// HEAD did not move or the trees cannot be read.
+fn changed_files(env: &[(&'static str, String)]) -> Vec<String> {
+    let var = |name: &str| env_var(env, name);
+    let (old, new) = (var("GROPPY_OLD_SHA"), var("GROPPY_NEW_SHA"));
+    if old.is_empty() || new.is_empty() || old == new {
+        return Vec::new();
+    }
+    diff_paths(Path::new(var("GROPPY_REPO")), old, new).unwrap_or_else(|e| {
+        tracing::warn!("cannot list changed files: {e:#}");
+        Vec::new()
+    })
+}
+
+fn diff_paths(path: &Path, old: &str, new: &str) -> Result<Vec<String>> {
+    use gix::object::tree::diff::{Action, Change};
+    let repo = open_repository(path)?;
+    let tree = |hex: &str| -> Result<gix::Tree<'_>> {
+        let id = gix::ObjectId::from_hex(hex.as_bytes())?;
+        Ok(repo.find_object(id)?.peel_to_tree()?)
+    };
+    let (old_tree, new_tree) = (tree(old)?, tree(new)?);
+    let mut paths = Vec::new();
+    old_tree
+        .changes()?
+        .options(|o| {
+            o.track_path();
+        })
+        .for_each_to_obtain_tree(&new_tree, |change| {
+            match change {
+                Change::Rewrite { source_location, location, .. } => {
+                    paths.push(source_location.to_string());
+                    paths.push(location.to_string());
+                }
+                Change::Addition { location, .. }
+                | Change::Deletion { location, .. }
+                | Change::Modification { location, .. } => paths.push(location.to_string()),
+            }
+            Ok::<_, std::convert::Infallible>(Action::Continue(()))
+        })?;
+    Ok(paths)
+}
+
+/// Whether a file the update changed matches any of `patterns`, read like
+/// `.gitignore` lines: `Cargo.lock` at any depth, `/Cargo.lock` at the top,
+/// `migrations/**`, `!` to exclude.
+fn touched(patterns: &[String]) -> bool {
+    let env = RUN_ENV.with_borrow(|env| env.clone());
+    let repo = Path::new(env_var(&env, "GROPPY_REPO"));
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(repo);
+    for pattern in patterns {
+        if let Err(e) = builder.add_line(None, pattern) {
+            tracing::warn!(pattern, "bad touched() pattern: {e}");
+        }
+    }
+    let matcher = match builder.build() {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            tracing::warn!("bad touched() patterns: {e}");
+            return false;
+        }
+    };
+    changed_files(&env)
+        .iter()
+        .any(|file| matcher.matched_path_or_any_parents(repo.join(file), false).is_ignore())
+}
+
+/// The value of `name` in a `run_env` list; "" when absent.
+fn env_var<'a>(env: &'a [(&'static str, String)], name: &str) -> &'a str {
+    env.iter().find(|(key, _)| *key == name).map_or("", |(_, value)| value)
+}
+
+/// Replaces `{repo}`, `{name}`, `{branch}`, `{old}`, `{new}` and `{status}`
+/// in `text` with the current repo's values from `env` (see [`run_env`]),
+/// each passed through `quote`.
+fn expand(text: &str, env: &[(&'static str, String)], quote: fn(&str) -> String) -> String {
+    let var = |name: &str| env_var(env, name);
+    let repo = var("GROPPY_REPO");
+    let name = Path::new(repo).file_name().map(|name| name.to_string_lossy());
+    let placeholders = [
+        ("{repo}", repo),
+        ("{name}", name.as_deref().unwrap_or_default()),
+        ("{branch}", var("GROPPY_BRANCH")),
+        ("{old}", var("GROPPY_OLD_SHA")),
+        ("{new}", var("GROPPY_NEW_SHA")),
+        ("{status}", var("GROPPY_RESULT")),
+    ];
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('{') {
+        expanded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                expanded.push_str(&quote(value));
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// `value` as one word for `sh -c` (`cmd /C` on Windows), so a path or branch
+/// name with spaces or metacharacters stays literal.
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+const WEEKDAYS: [&str; 7] =
+    ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn days_since_epoch() -> u64 {
+    seconds_since_epoch() / 86_400
+}
+
+/// `#{ path, branch, remote, url }` for a repository, "" for what is unknown.
+fn repo_map(path: &Path, repo: &gix::Repository, opts: &UpdateOptions) -> Map {
+    let mut map = path_map(path);
+    let branch = repo.head_name().ok().flatten().map(|name| name.shorten().to_string());
+    map.insert("branch".into(), branch.unwrap_or_default().into());
+    if let Ok((remote, name)) = select_remote(repo, opts) {
+        let url = remote
+            .url(gix::remote::Direction::Fetch)
+            .map(|url| redact_url(&url.to_bstring().to_string()));
+        map.insert("remote".into(), name.into());
+        map.insert("url".into(), url.unwrap_or_default().into());
+    }
+    map
+}
+
+/// `GROPPY_*` variables for `run` from a repo map and what the hook knows
+/// about the update.
+fn run_env(
+    repo: &Map,
+    old: Option<String>,
+    new: Option<String>,
+    outcome: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let field = |key: &str| repo.get(key).map(|value| value.to_string()).unwrap_or_default();
+    vec![
+        ("GROPPY_REPO", field("path")),
+        ("GROPPY_BRANCH", field("branch")),
+        ("GROPPY_OLD_SHA", old.unwrap_or_default()),
+        ("GROPPY_NEW_SHA", new.unwrap_or_default()),
+        ("GROPPY_RESULT", outcome.unwrap_or_default().to_string()),
+    ]
+}
+
+/// The repo map and `GROPPY_*` variables for a repository whose update
+/// finished with `status`.
+fn finished_env(
+    path: &Path,
+    status: &RepoStatus,
+    opts: &UpdateOptions,
+) -> (Map, Vec<(&'static str, String)>) {
+    let repo = match open_repository(path) {
+        Ok(repo) => repo_map(path, &repo, opts),
+        Err(_) => path_map(path),
+    };
+    let old = status.old_id.map(|id| id.to_string());
+    let new = status.new_id.or(status.old_id).map(|id| id.to_string());
+    let env = run_env(&repo, old, new, Some(status.outcome()));
+    (repo, env)
+}
+
+/// A repo map with only the path known.
+fn path_map(path: &Path) -> Map {
+    let mut map = Map::new();
+    map.insert("path".into(), path.display().to_string().into());
+    for key in ["branch", "remote", "url"] {
+        map.insert(key.into(), String::new().into());
+    }
+    map
+}
+
+/// `#{ path, outcome, success, message, files_changed, commits }` for a result.
+fn status_map(status: &RepoStatus) -> Map {
+    let mut map = Map::new();
+    map.insert("path".into(), status.path.display().to_string().into());
+    map.insert("outcome".into(), status.outcome().into());
+    map.insert("success".into(), status.success.into());
+    map.insert("message".into(), status.message.clone().into());
+    map.insert("files_changed".into(), (status.files_changed as i64).into());
+    map.insert("commits".into(), (status.commit_count() as i64).into());
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use std::fs;
+
+    fn verdict(script: &str, repo_path: &Path) -> Result<Verdict> {
+        let repo = gix::open(repo_path).unwrap();
+        Hooks::compile(script)?.should_update(repo_path, &repo, &UpdateOptions::default())
+    }
+
+    #[test]
+    fn test_should_update_answers() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        assert_eq!(verdict("", tmp.path()).unwrap(), Verdict::Update);
+        let script = "fn should_update(repo) { true }";
+        assert_eq!(verdict(script, tmp.path()).unwrap(), Verdict::Update);
+        assert_eq!(
+            verdict("fn should_update(repo) { false }", tmp.path()).unwrap(),
+            Verdict::Skip(String::new())
+        );
+        let script = r#"fn should_update(repo) { "on " + repo.branch }"#;
+        let Verdict::Skip(reason) = verdict(script, tmp.path()).unwrap() else {
+            panic!("expected a skip");
+        };
+        assert!(reason.starts_with("on "), "{reason}");
+        assert!(verdict("fn should_update(repo) { 42 }", tmp.path()).is_err());
+    }
+
+    #[test]
+    fn test_should_update_sees_remote_url() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let script = r#"fn should_update(repo) { !repo.url.contains("remote") }"#;
+        assert_eq!(verdict(script, &clone_path).unwrap(), Verdict::Skip(String::new()));
+    }
+
+    #[test]
+    fn test_runaway_hooks_are_stopped() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let looping = "fn should_update(repo) { loop {} }";
+        let err = verdict(looping, tmp.path()).unwrap_err();
+        assert!(err.to_string().contains("operations"), "{err}");
+        let recursing = "fn f(n) { f(n + 1) } fn should_update(repo) { f(0) }";
+        assert!(verdict(recursing, tmp.path()).is_err());
+
+        let repo = gix::open(tmp.path()).unwrap();
+        let opts = UpdateOptions::default();
+        opts.interrupt.store(true, Ordering::Release);
+        let hooks = Hooks::compile(looping).unwrap();
+        let err = hooks.should_update(tmp.path(), &repo, &opts).unwrap_err();
+        assert!(err.to_string().contains("terminated"), "{err}");
+        assert!(INTERRUPT.with_borrow(Option::is_none));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_sees_update_env() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let out = tmp.path().join("env.txt");
+        let vars = "$GROPPY_RESULT $GROPPY_BRANCH $GROPPY_OLD_SHA $GROPPY_NEW_SHA $GROPPY_REPO";
+        let command = format!("echo {vars} > {}", out.display());
+        let script =
+            format!(r#"fn after_update(repo, result) {{ run("sh", ["-c", "{command}"]); }}"#);
+        let old = gix::open(tmp.path()).unwrap().head_id().unwrap().detach();
+        let status = RepoStatus {
+            path: tmp.path().to_path_buf(),
+            success: true,
+            old_id: Some(old),
+            ..Default::default()
+        };
+        let hooks = Hooks::compile(&script).unwrap();
+        hooks.after_update(tmp.path(), &status, &UpdateOptions::default()).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        let branch = gix::open(tmp.path()).unwrap().head_name().unwrap().unwrap();
+        let expected = format!("ok {} {old} {old} {}\n", branch.shorten(), tmp.path().display());
+        assert_eq!(text, expected);
+        assert!(RUN_ENV.with_borrow(Vec::is_empty));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_runs_in_repo_with_env() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let old = gix::open(tmp.path()).unwrap().head_id().unwrap().detach();
+        let status = RepoStatus {
+            path: tmp.path().to_path_buf(),
+            success: true,
+            old_id: Some(old),
+            ..Default::default()
+        };
+        let opts = UpdateOptions::default();
+        let command = r#"echo "$GROPPY_RESULT $GROPPY_NEW_SHA" > exec.txt"#;
+        exec(command, tmp.path(), &status, &opts).unwrap();
+        let text = std::fs::read_to_string(tmp.path().join("exec.txt")).unwrap();
+        assert_eq!(text, format!("ok {old}\n"));
+        exec("echo {status} {name} > named.txt", tmp.path(), &status, &opts).unwrap();
+        let text = std::fs::read_to_string(tmp.path().join("named.txt")).unwrap();
+        let name = tmp.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(text, format!("ok {name}\n"));
+        let err = exec("exit 3", tmp.path(), &status, &opts).unwrap_err();
+        assert_eq!(err.to_string(), "--exec exited with 3");
+
+        let repo = path_map(Path::new("/src/it's here"));
+        let env = run_env(&repo, None, None, None);
+        assert_eq!(expand("cd {repo}", &env, shell_quote), r"cd '/src/it'\''s here'");
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let repo = path_map(Path::new("/src/api"));
+        let env = run_env(&repo, Some("abc".into()), None, Some("updated"));
+        let text = "{name}@{old}..{new} {status} in {repo} {{json}} {unknown} {";
+        let expanded = expand(text, &env, str::to_string);
+        assert_eq!(expanded, "api@abc.. updated in /src/api {{json}} {unknown} {");
+        assert_eq!(expand("{repo}", &[], str::to_string), "");
+    }
+
+    #[test]
+    fn test_touched_matches_changed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let head = || gix::open(tmp.path()).unwrap().head_id().unwrap().to_string();
+        let old = head();
+        fs::create_dir_all(tmp.path().join("app/migrations")).unwrap();
+        fs::write(tmp.path().join("app/Cargo.lock"), "lock").unwrap();
+        fs::write(tmp.path().join("app/migrations/001.sql"), "create").unwrap();
+        git(tmp.path(), &["add", "."]);
+        git(tmp.path(), &["commit", "-m", "lock"]);
+        let repo = path_map(tmp.path());
+        RUN_ENV.set(run_env(&repo, Some(old.clone()), Some(head()), Some("updated")));
+        let mut files = changed_files(&RUN_ENV.with_borrow(|env| env.clone()));
+        files.sort();
+        assert_eq!(files, ["app/Cargo.lock", "app/migrations/001.sql"]);
+        let patterns = |patterns: &[&str]| {
+            touched(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+        };
+        assert!(patterns(&["Cargo.lock"]));
+        assert!(patterns(&["package-lock.json", "app/migrations/**"]));
+        assert!(!patterns(&["/Cargo.lock"]));
+        assert!(!patterns(&["*.md"]));
+        RUN_ENV.set(run_env(&repo, Some(old.clone()), Some(old), Some("ok")));
+        assert!(!patterns(&["Cargo.lock"]));
+        RUN_ENV.take();
+    }
+
+    #[test]
+    fn test_compile_error_is_reported() {
+        assert!(Hooks::compile("fn should_update(repo) {").is_err());
+    }
+
+    #[test]
+    fn test_weekday_and_hour() {
+        let hooks = Hooks::compile("").unwrap();
+        let day: String = hooks.engine.eval("weekday()").unwrap();
+        assert!(WEEKDAYS.contains(&day.as_str()));
+        let hour: i64 = hooks.engine.eval("hour()").unwrap();
+        assert!((0..24).contains(&hour));
+    }
+}
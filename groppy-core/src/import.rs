@@ -0,0 +1,369 @@
+//! Repo lists from other multi-repo tools, turned into `[repos."<path>"]`
+//! entries for `config.toml`: vcstool's `.repos` YAML (the shape `vcs export`
+//! writes) and myrepos' `.mrconfig`.
+//!
+//! ```text
+//! repositories:                          [src/app]
+//!   src/app:                             checkout = git clone -b main 'https://...' 'app'
+//!     type: git
+//!     url: https://git.example.com/app.git
+//!     version: main
+//! ```
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::config::home_dir;
+
+/// A tool whose repo list can be imported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Vcstool,
+    Mrconfig,
+}
+
+impl ImportFormat {
+    /// Every format, in the order `--from` lists them.
+    pub const ALL: [ImportFormat; 2] = [ImportFormat::Vcstool, ImportFormat::Mrconfig];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ImportFormat::Vcstool => "vcstool",
+            ImportFormat::Mrconfig => "mrconfig",
+        }
+    }
+}
+
+/// A Git repository the other tool checks out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedRepo {
+    pub path: PathBuf,          // Absolute checkout directory
+    pub url: String,            // Clone URL
+    pub branch: Option<String>, // Branch it is kept on, if the tool names one
+}
+
+/// What an import found: the repos, and why any entries were left out.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub repos: Vec<ImportedRepo>,
+    pub warnings: Vec<String>,
+}
+
+/// Reads a repo list; relative paths in it are taken from `base`.
+pub fn parse(format: ImportFormat, text: &str, base: &Path) -> Result<Import> {
+    match format {
+        ImportFormat::Vcstool => parse_vcstool(text, base),
+        ImportFormat::Mrconfig => Ok(parse_mrconfig(text, base)),
+    }
+}
+
+/// The repos as config entries, paths under `$HOME` written with `~`.
+pub fn to_toml(repos: &[ImportedRepo]) -> String {
+    let quote = |text: &str| toml::Value::String(text.to_string()).to_string();
+    let mut out = String::new();
+    for repo in repos {
+        let path = home_dir()
+            .and_then(|home| Some(Path::new("~").join(repo.path.strip_prefix(home).ok()?)))
+            .unwrap_or_else(|| repo.path.clone());
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("[repos.{}]\n", quote(&path.display().to_string())));
+        out.push_str(&format!("url = {}\n", quote(&repo.url)));
+        if let Some(branch) = &repo.branch {
+            out.push_str(&format!("branch = {}\n", quote(branch)));
+        }
+    }
+    out
+}
+
+/// vcstool's YAML, limited to what `vcs export` writes: a `repositories` map
+/// of path to `type`, `url` and `version`. Only `type: git` is imported.
+fn parse_vcstool(text: &str, base: &Path) -> Result<Import> {
+    let mut entries: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    let mut in_repositories = false;
+    let mut found = false;
+    let mut repo_indent = None;
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_yaml_comment(line);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed == "---" {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let (key, value) = match trimmed.strip_suffix(':') {
+            Some(key) => (key, ""),
+            None => trimmed.split_once(": ").unwrap_or((trimmed, "")),
+        };
+        let (key, value) = (unquote(key.trim()), unquote(value.trim()));
+        if indent == 0 {
+            in_repositories = key == "repositories";
+            found |= in_repositories;
+            continue;
+        }
+        if !in_repositories {
+            continue;
+        }
+        match repo_indent.get_or_insert(indent) {
+            level if indent == *level => entries.push((key, Vec::new())),
+            level if indent > *level => match entries.last_mut() {
+                Some((_, fields)) => fields.push((key, value)),
+                None => anyhow::bail!("line {}: field outside a repository", number + 1),
+            },
+            _ => anyhow::bail!("line {}: unexpected indentation", number + 1),
+        }
+    }
+    anyhow::ensure!(found, "no `repositories:` map");
+
+    let mut import = Import::default();
+    for (path, fields) in entries {
+        let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, v)| v);
+        match (field("type").map(String::as_str), field("url")) {
+            (Some("git"), Some(url)) => {
+                let version = field("version");
+                let branch = version.and_then(|version| kept_branch(&path, version, &mut import));
+                import.repos.push(ImportedRepo {
+                    path: under(base, &path),
+                    url: url.clone(),
+                    branch,
+                });
+            }
+            (Some("git"), None) => import.warnings.push(format!("{path}: no url")),
+            (kind, _) => import.warnings.push(format!(
+                "{path}: {} repositories are not supported",
+                kind.unwrap_or("untyped")
+            )),
+        }
+    }
+    Ok(import)
+}
+
+/// myrepos' ini-style `.mrconfig`: one `[path]` section per repo, whose
+/// `checkout` command must be a `git clone`.
+fn parse_mrconfig(text: &str, base: &Path) -> Import {
+    let mut sections: Vec<(String, Option<String>)> = Vec::new(); // Path, checkout command
+    let mut in_checkout = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            // Continuation of the previous value
+            if let (true, Some((_, Some(checkout)))) = (in_checkout, sections.last_mut()) {
+                checkout.push(' ');
+                checkout.push_str(trimmed.trim_end_matches('\\'));
+            }
+            continue;
+        }
+        if let Some(section) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            sections.push((section.trim().to_string(), None));
+            in_checkout = false;
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=') else { continue };
+        in_checkout = key.trim() == "checkout";
+        if let (true, Some((_, checkout))) = (in_checkout, sections.last_mut()) {
+            *checkout = Some(value.trim().trim_end_matches('\\').to_string());
+        }
+    }
+
+    let mut import = Import::default();
+    for (path, checkout) in sections.into_iter().filter(|(path, _)| path != "DEFAULT") {
+        match checkout.as_deref().and_then(git_clone) {
+            Some((url, version)) => {
+                let branch = version.and_then(|version| kept_branch(&path, &version, &mut import));
+                import.repos.push(ImportedRepo {
+                    path: under(base, &path),
+                    url,
+                    branch,
+                });
+            }
+            None => import.warnings.push(format!("{path}: checkout is not a git clone")),
+        }
+    }
+    import
+}
+
+/// Options of `git clone` that take a separate value.
+const CLONE_VALUE_OPTIONS: &[&str] = &[
+    "-b", "--branch", "-o", "--origin", "-u", "--upload-pack", "-c", "--config", "-j", "--jobs",
+    "--depth", "--reference", "--reference-if-able", "--separate-git-dir", "--template",
+    "--shallow-since", "--shallow-exclude", "--server-option", "--bundle-uri",
+];
+
+/// The URL and `--branch` of the first `git clone` in a shell command.
+fn git_clone(command: &str) -> Option<(String, Option<String>)> {
+    let words = shell_words(command);
+    let start = words.windows(2).position(|pair| pair[0] == "git" && pair[1] == "clone")?;
+    let mut branch = None;
+    let mut args = words[start + 2..].iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--branch=") {
+            branch = Some(name.to_string());
+        } else if CLONE_VALUE_OPTIONS.contains(&arg.as_str()) {
+            let value = args.next()?;
+            if arg == "-b" || arg == "--branch" {
+                branch = Some(value.clone());
+            }
+        } else if !arg.starts_with('-') {
+            return Some((arg.clone(), branch));
+        }
+    }
+    None
+}
+
+/// Splits a command into words, honouring single and double quotes and
+/// backslash escapes; stops at `;`, `&&` or `|` outside quotes.
+fn shell_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                word.get_or_insert_default().extend(chars.next());
+            }
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, ';' | '&' | '|') => break,
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// A version as a branch to keep; commits and tags can't be, so they are noted
+/// and dropped.
+fn kept_branch(path: &str, version: &str, import: &mut Import) -> Option<String> {
+    let commit = version.len() >= 7 && version.bytes().all(|b| b.is_ascii_hexdigit());
+    let tag = version.starts_with("refs/tags/");
+    if commit || tag {
+        import.warnings.push(format!("{path}: pinned to {version}, imported without it"));
+        return None;
+    }
+    Some(version.strip_prefix("refs/heads/").unwrap_or(version).to_string())
+}
+
+/// `path` resolved under `base`, without `.` components.
+fn under(base: &Path, path: &str) -> PathBuf {
+    base.join(path)
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// A YAML line without its ` # comment` (`#` inside a value stays).
+fn strip_yaml_comment(line: &str) -> &str {
+    match line.find(" #") {
+        _ if line.starts_with('#') => "",
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+/// A YAML scalar without its surrounding quotes.
+fn unquote(text: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_parse_vcstool() {
+        let text = r#"repositories:
+  src/app:
+    type: git
+    url: https://git.example.com/app.git   # Primary
+    version: main
+  "src/pinned":
+    type: git
+    url: git@git.example.com:tools/pinned.git
+    version: 0a1b2c3d4e5f
+  src/legacy:
+    type: svn
+    url: https://svn.example.com/legacy
+"#;
+        let import = parse(ImportFormat::Vcstool, text, Path::new("/ws")).unwrap();
+        assert_eq!(
+            import.repos,
+            vec![
+                ImportedRepo {
+                    path: PathBuf::from("/ws/src/app"),
+                    url: "https://git.example.com/app.git".to_string(),
+                    branch: Some("main".to_string()),
+                },
+                ImportedRepo {
+                    path: PathBuf::from("/ws/src/pinned"),
+                    url: "git@git.example.com:tools/pinned.git".to_string(),
+                    branch: None,
+                },
+            ]
+        );
+        assert_eq!(import.warnings.len(), 2, "{:?}", import.warnings);
+        assert!(parse(ImportFormat::Vcstool, "repos: []\n", Path::new("/ws")).is_err());
+    }
+
+    #[test]
+    fn test_parse_mrconfig() {
+        let text = "[DEFAULT]\njobs = 4\n\n\
+                    [src/app]\ncheckout = git clone --depth 1 -b develop \\\n    \
+                    'https://git.example.com/app.git' 'app'\n\n\
+                    [/srv/site]\ncheckout = git clone \"git@git.example.com:site\" site\n\
+                    update = git pull --rebase\n\n\
+                    [src/notes]\ncheckout = hg clone https://hg.example.com/notes notes\n";
+        let import = parse(ImportFormat::Mrconfig, text, Path::new("/home/ann")).unwrap();
+        let repos: Vec<(&Path, &str, Option<&str>)> = import
+            .repos
+            .iter()
+            .map(|repo| (repo.path.as_path(), repo.url.as_str(), repo.branch.as_deref()))
+            .collect();
+        assert_eq!(
+            repos,
+            [
+                (
+                    Path::new("/home/ann/src/app"),
+                    "https://git.example.com/app.git",
+                    Some("develop"),
+                ),
+                (Path::new("/srv/site"), "git@git.example.com:site", None),
+            ]
+        );
+        assert_eq!(import.warnings, ["src/notes: checkout is not a git clone"]);
+    }
+
+    #[test]
+    fn test_shell_words() {
+        let words = shell_words(r#"git clone 'a b' "c\"d" e\ f && cd x"#);
+        assert_eq!(words, ["git", "clone", "a b", "c\"d", "e f"]);
+    }
+
+    #[test]
+    fn test_to_toml_loads_as_config() {
+        let repos = [ImportedRepo {
+            path: PathBuf::from("/srv/app \"quoted\""),
+            url: "https://git.example.com/app.git".to_string(),
+            branch: Some("main".to_string()),
+        }];
+        let config = Config::parse(&to_toml(&repos)).unwrap();
+        let repo = config.repo(Path::new("/srv/app \"quoted\""));
+        assert_eq!(repo.url.as_deref(), Some("https://git.example.com/app.git"));
+        assert_eq!(repo.branch.as_deref(), Some("main"));
+    }
+}
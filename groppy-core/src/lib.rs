@@ -0,0 +1,57 @@
+//! groppy-core - the multi-repository update engine behind `groppy`
+//!
+//! Finds Git repositories ([`discovery`]), fast-forwards them with gitoxide
+//! ([`update`]), works out what an update would do without changing anything
+//! ([`plan`]), and runs whole batches on a rayon pool with progress callbacks
+//! ([`run`]), optionally steered by a user script ([`hooks`]). Repositories
+//! hosted on a forge, or named in a `repo` manifest, can be listed and cloned
+//! first ([`forge`], [`manifest`]). Every outcome is a [`RepoStatus`];
+//! nothing here draws to the terminal, so the same engine can sit behind a
+//! CLI, a TUI, or a service.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use std::sync::atomic::AtomicUsize;
+//!
+//! use groppy_core::discovery::{ScanOptions, scan_for_repositories};
+//! use groppy_core::run::{Event, RunControl, update_all};
+//! use groppy_core::UpdateOptions;
+//!
+//! let scanned = AtomicUsize::new(0);
+//! let roots = [PathBuf::from("/home/me/src")];
+//! let (repos, _warnings) = scan_for_repositories(&roots, &ScanOptions::default(), &scanned);
+//!
+//! let pool = rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+//! let control = RunControl::default();
+//! let results = update_all(&pool, &repos, &|_| UpdateOptions::default(), &control, &|event| {
+//!     if let Event::Finished { status, .. } = event {
+//!         println!("{}: {}", status.path.display(), status.message);
+//!     }
+//! });
+//! assert_eq!(results.len(), repos.len());
+//! ```
+
+pub mod config;
+pub mod discovery;
+pub mod forge;
+pub mod hooks;
+pub mod import;
+pub mod manifest;
+pub mod netrc;
+pub mod plan;
+pub mod run;
+pub mod state;
+pub mod status;
+pub mod update;
+
+#[cfg(test)]
+mod test_util;
+
+pub use gix; // For the commit IDs in results
+pub use status::{Bucket, ErrorKind, HeadCommit, PulledCommit, RepoStatus};
+pub use update::{
+    AskCredentials, AskDirty, DirtyAction, Interaction, IpVersion, Unclean, UpdateOptions,
+    update_repository,
+};
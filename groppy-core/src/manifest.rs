@@ -0,0 +1,400 @@
+//! Google `repo` manifests (AOSP-style `manifest.xml`), read as a list of
+//! repositories to clone and update, so a `repo sync` layout moves to groppy
+//! without rewriting the manifest.
+//!
+//! Understood: `<remote>`, `<default>`, `<project>` (leaving out the
+//! `notdefault` group, as `repo sync` does), `<include>` and
+//! `<remove-project>`. Relative `fetch` URLs (`..`) resolve against the
+//! manifest repository's `origin`, as in `repo`. File copies and links,
+//! annotations and the rest are ignored.
+//!
+//! Repositories left in the checkout tree after the manifest dropped them are
+//! found by [`extraneous`] and moved aside, never deleted, by [`archive`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+
+use anyhow::{Context, Result};
+
+use crate::discovery::{ScanOptions, scan_for_repositories};
+use crate::forge::{Forge, HostedRepo};
+
+/// How deep `<include>` may nest before the manifest counts as circular.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// A manifest file on disk.
+pub struct RepoManifest {
+    pub path: PathBuf,
+}
+
+impl Forge for RepoManifest {
+    fn describe(&self) -> String {
+        format!("manifest {}", self.path.display())
+    }
+
+    fn list(&self) -> Result<Vec<HostedRepo>> {
+        let mut manifest = Manifest::default();
+        manifest.read(&self.path, 0)?;
+        let relative = manifest.remotes.values().any(Remote::is_relative);
+        let base_url = if relative { Some(origin_url(&self.path)?) } else { None };
+        manifest.hosted(base_url.as_deref())
+    }
+}
+
+/// Elements gathered from a manifest and its includes, resolved only once
+/// everything is read (`<default>` may come after the projects).
+#[derive(Default)]
+struct Manifest {
+    remotes: HashMap<String, Remote>,
+    default_remote: Option<String>,
+    default_revision: Option<String>,
+    projects: Vec<Project>,
+}
+
+struct Remote {
+    fetch: String,            // URL prefix, absolute or relative to the manifest repo
+    revision: Option<String>, // Revision for this remote's projects
+}
+
+impl Remote {
+    fn is_relative(&self) -> bool {
+        self.fetch.starts_with('.')
+    }
+}
+
+struct Project {
+    name: String,             // Path on the remote
+    path: String,             // Checkout directory; the name when not given
+    remote: Option<String>,   // Remote name; the default remote when None
+    revision: Option<String>, // Branch, tag or commit; the remote's or default when None
+    upstream: Option<String>, // Branch a commit revision was taken from
+}
+
+impl Manifest {
+    fn read(&mut self, file: &Path, depth: usize) -> Result<()> {
+        anyhow::ensure!(depth <= MAX_INCLUDE_DEPTH, "includes nested too deep: {}", file.display());
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("cannot read manifest {}", file.display()))?;
+        self.parse(&text, file, depth)
+            .with_context(|| format!("invalid manifest {}", file.display()))
+    }
+
+    fn parse(&mut self, text: &str, file: &Path, depth: usize) -> Result<()> {
+        let doc = roxmltree::Document::parse(text)?;
+        let root = doc.root_element();
+        anyhow::ensure!(root.has_tag_name("manifest"), "root element is not <manifest>");
+        for node in root.children().filter(|node| node.is_element()) {
+            let tag = node.tag_name().name();
+            let attr = |name: &str| node.attribute(name).map(str::to_string);
+            let required =
+                |name: &str| attr(name).with_context(|| format!("<{tag}> without {name}"));
+            match tag {
+                "remote" => {
+                    let remote = Remote {
+                        fetch: required("fetch")?,
+                        revision: attr("revision"),
+                    };
+                    self.remotes.insert(required("name")?, remote);
+                }
+                "default" => {
+                    self.default_remote = attr("remote").or(self.default_remote.take());
+                    self.default_revision = attr("revision").or(self.default_revision.take());
+                }
+                "project" => {
+                    let groups = node.attribute("groups").unwrap_or_default();
+                    if groups.split([',', ' ']).any(|group| group == "notdefault") {
+                        continue;
+                    }
+                    let name = required("name")?;
+                    self.projects.push(Project {
+                        path: attr("path").unwrap_or_else(|| name.clone()),
+                        name,
+                        remote: attr("remote"),
+                        revision: attr("revision"),
+                        upstream: attr("upstream"),
+                    });
+                }
+                "include" => {
+                    let dir = file.parent().unwrap_or(Path::new("."));
+                    self.read(&dir.join(required("name")?), depth + 1)?;
+                }
+                "remove-project" => {
+                    let name = required("name")?;
+                    let path = attr("path");
+                    self.projects.retain(|project| {
+                        project.name != name || path.as_ref().is_some_and(|p| *p != project.path)
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The projects as clonable repos; relative `fetch` URLs resolve against
+    /// `base_url`, the manifest repo's.
+    fn hosted(&self, base_url: Option<&str>) -> Result<Vec<HostedRepo>> {
+        let mut hosted = Vec::new();
+        for project in &self.projects {
+            let remote_name = project
+                .remote
+                .as_ref()
+                .or(self.default_remote.as_ref())
+                .with_context(|| format!("project {} has no remote", project.name))?;
+            let remote = self.remotes.get(remote_name).with_context(|| {
+                format!("project {} names unknown remote {remote_name}", project.name)
+            })?;
+            let fetch = match (remote.is_relative(), base_url) {
+                (true, Some(base_url)) => join_url(base_url, &remote.fetch),
+                (true, None) => anyhow::bail!("relative fetch URL without a manifest repo"),
+                (false, _) => remote.fetch.clone(),
+            };
+            let url = if fetch.ends_with(':') {
+                format!("{fetch}{}", project.name) // scp-like `host:`
+            } else {
+                format!("{}/{}", fetch.trim_end_matches('/'), project.name)
+            };
+            let revision = project
+                .revision
+                .as_ref()
+                .or(remote.revision.as_ref())
+                .or(self.default_revision.as_ref());
+            hosted.push(HostedRepo {
+                name: project.path.clone(),
+                https_url: url.clone(),
+                ssh_url: url,
+                branch: revision.and_then(|revision| branch(revision, project.upstream.as_deref())),
+            });
+        }
+        Ok(hosted)
+    }
+}
+
+/// Repositories under `root`, at any depth, that aren't among `managed` (the
+/// manifest's checkouts), don't hold one of them (a parent checkout the
+/// manifest dropped while keeping a project nested in it), and aren't under
+/// `archive_dir`. Dot-directories such as `repo`'s own `.repo` aren't
+/// searched, nor are the managed repos themselves.
+/// Returns the repos and any directories that could not be read.
+pub fn extraneous(
+    root: &Path,
+    managed: &[PathBuf],
+    archive_dir: Option<&Path>,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let canonical = |path: &Path| std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let root = canonical(root);
+    let opts = ScanOptions {
+        follow_symlinks: false,
+        hidden: false,
+        max_depth: 0,
+        exclude: Vec::new(), // Manifests have `build/`, `vendor/`, ... projects
+        root_depths: BTreeMap::from([(root.clone(), None)]),
+    };
+    let (repos, warnings) = scan_for_repositories(&[root.clone()], &opts, &AtomicUsize::new(0));
+    let managed: HashSet<PathBuf> = managed.iter().map(|path| canonical(path)).collect();
+    let archive_dir = archive_dir.map(canonical);
+    let repos = repos
+        .into_iter()
+        .filter(|repo| *repo != root && !managed.iter().any(|path| path.starts_with(repo)))
+        .filter(|repo| archive_dir.as_ref().is_none_or(|dir| !repo.starts_with(dir)))
+        .collect();
+    (repos, warnings)
+}
+
+/// Moves each repo under `root` to the same relative path under `archive_dir`.
+/// Nothing is overwritten: a taken name gets a `.1`, `.2`, ... suffix. Returns
+/// where each repo went.
+pub fn archive(
+    repos: &[PathBuf],
+    root: &Path,
+    archive_dir: &Path,
+) -> Vec<(PathBuf, Result<PathBuf>)> {
+    let root = std::fs::canonicalize(root).unwrap_or(root.to_path_buf());
+    repos
+        .iter()
+        .map(|repo| {
+            let relative = match repo.strip_prefix(&root) {
+                Ok(relative) => relative,
+                Err(_) => Path::new(repo.file_name().unwrap_or_default()),
+            };
+            let mut target = archive_dir.join(relative);
+            for n in 1.. {
+                if !target.exists() {
+                    break;
+                }
+                target = archive_dir.join(format!("{}.{n}", relative.display()));
+            }
+            let moved = target
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::rename(repo, &target))
+                .with_context(|| format!("cannot move {} to {}", repo.display(), target.display()))
+                .map(|()| target);
+            (repo.clone(), moved)
+        })
+        .collect()
+}
+
+/// The branch to clone for a revision: `refs/heads/` dropped, and for a pinned
+/// commit its `upstream` branch, if given (else the default branch).
+fn branch(revision: &str, upstream: Option<&str>) -> Option<String> {
+    let pinned = revision.len() == 40 && revision.bytes().all(|b| b.is_ascii_hexdigit());
+    let branch = if pinned { upstream? } else { revision };
+    Some(branch.strip_prefix("refs/heads/").unwrap_or(branch).to_string())
+}
+
+/// The fetch URL of `origin` in the repository holding the manifest.
+fn origin_url(manifest: &Path) -> Result<String> {
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let fail = || format!("relative fetch URL needs {} in a clone of its repo", dir.display());
+    let repo = gix::discover(dir).with_context(fail)?;
+    let remote = repo.find_remote("origin").with_context(fail)?;
+    let url = remote.url(gix::remote::Direction::Fetch).with_context(fail)?;
+    Ok(url.to_bstring().to_string())
+}
+
+/// Resolves a relative URL against `base` the way `repo` (and `urljoin`) do:
+/// the last path segment of `base` is a file, `..` climbs one directory.
+fn join_url(base: &str, relative: &str) -> String {
+    let root_len = match base.find("://") {
+        Some(i) => base[i + 3..].find('/').map_or(base.len(), |j| i + 3 + j),
+        None => base.find(':').map_or(0, |i| i + 1), // scp-like `user@host:path`
+    };
+    let (root, path) = base.split_at(root_len);
+    let mut segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    if !path.ends_with('/') {
+        segments.pop();
+    }
+    for part in relative.split('/') {
+        match part {
+            ".." => {
+                segments.pop();
+            }
+            "." | "" => {}
+            _ => segments.push(part),
+        }
+    }
+    let separator = if root.ends_with(':') { "" } else { "/" };
+    format!("{root}{separator}{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use std::fs;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <remote name="aosp" fetch=".." review="https://android-review.googlesource.com/" />
+  <remote name="github" fetch="https://github.com/" revision="refs/heads/main" />
+  <default revision="refs/heads/android14-release" remote="aosp" sync-j="4" />
+
+  <project path="build/make" name="platform/build" groups="pdk">
+    <copyfile src="core/root.mk" dest="Makefile" />
+  </project>
+  <project name="platform/art" revision="0123456789abcdef0123456789abcdef01234567"
+           upstream="refs/heads/main" />
+  <project name="tools/tradefed" groups="notdefault,tradefed" />
+  <project path="external/lib" name="acme/lib" remote="github" />
+  <include name="extra.xml" />
+</manifest>
+"#;
+
+    const EXTRA: &str = r#"<manifest>
+  <remove-project name="platform/art" />
+  <project path="docs" name="platform/docs" revision="stable" />
+</manifest>
+"#;
+
+    fn list(text: &str, base_url: &str) -> Result<Vec<HostedRepo>> {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("extra.xml"), EXTRA).unwrap();
+        let file = tmp.path().join("default.xml");
+        let mut manifest = Manifest::default();
+        manifest.parse(text, &file, 0)?;
+        manifest.hosted(Some(base_url))
+    }
+
+    #[test]
+    fn test_manifest_projects() {
+        let repos = list(MANIFEST, "https://android.googlesource.com/platform/manifest").unwrap();
+        let summary: Vec<(&str, &str, Option<&str>)> = repos
+            .iter()
+            .map(|repo| (repo.name.as_str(), repo.https_url.as_str(), repo.branch.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "build/make",
+                    "https://android.googlesource.com/platform/build",
+                    Some("android14-release"),
+                ),
+                ("external/lib", "https://github.com/acme/lib", Some("main")),
+                ("docs", "https://android.googlesource.com/platform/docs", Some("stable")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_manifest_errors() {
+        assert!(list("<manifest><project name=\"a\" /></manifest>", "").is_err()); // No remote
+        assert!(list("<repos />", "").is_err());
+        assert!(list("<manifest><remote name=\"a\" /></manifest>", "").is_err()); // No fetch
+    }
+
+    #[test]
+    fn test_branch_for_pinned_commit() {
+        let pinned = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(branch(pinned, Some("refs/heads/main")).as_deref(), Some("main"));
+        assert_eq!(branch(pinned, None), None);
+        assert_eq!(branch("refs/tags/v1.0", None).as_deref(), Some("refs/tags/v1.0"));
+    }
+
+    #[test]
+    fn test_extraneous_and_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("tree");
+        for repo in ["build/make", "old/tool", ".repo/manifests"] {
+            init_repo_with_commit(&root.join(repo));
+        }
+        let archive_dir = root.join("attic");
+        init_repo_with_commit(&archive_dir.join("old/tool"));
+
+        let managed = [root.join("build/make")];
+        let (extra, warnings) = extraneous(&root, &managed, Some(&archive_dir));
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let canonical_root = root.canonicalize().unwrap();
+        assert_eq!(extra, [canonical_root.join("old/tool")]);
+
+        let moved = archive(&extra, &root, &archive_dir);
+        assert_eq!(moved[0].1.as_ref().unwrap(), &archive_dir.join("old/tool.1"));
+        assert!(!root.join("old/tool").exists());
+        assert!(archive_dir.join("old/tool.1/.git").exists());
+    }
+
+    #[test]
+    fn test_extraneous_keeps_parents_of_managed_repos() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("tree");
+        for repo in ["build", "build/soong", "old"] {
+            init_repo_with_commit(&root.join(repo));
+        }
+        let managed = [root.join("build/soong")];
+        let (extra, warnings) = extraneous(&root, &managed, None);
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(extra, [root.canonicalize().unwrap().join("old")]);
+    }
+
+    #[test]
+    fn test_join_url() {
+        let base = "https://android.googlesource.com/platform/manifest";
+        assert_eq!(join_url(base, ".."), "https://android.googlesource.com/");
+        assert_eq!(join_url(base, "../tools"), "https://android.googlesource.com/tools");
+        assert_eq!(join_url(base, "."), "https://android.googlesource.com/platform");
+        assert_eq!(join_url("git@host:team/manifest.git", ".."), "git@host:");
+        assert_eq!(join_url("ssh://git@host/a/b/", ".."), "ssh://git@host/a");
+    }
+}
@@ -0,0 +1,153 @@
+//! `.netrc` logins for HTTPS fetches, the way curl (and so git) reads them:
+//! `$NETRC`, else `~/.netrc`, else `~/_netrc`.
+//!
+//! Only `machine`, `default`, `login` and `password` matter; `account` is
+//! skipped and `macdef` bodies run to the next blank line.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::config::home_dir;
+
+/// One entry's login.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Login {
+    pub login: Option<String>, // None when the entry only has a password
+    pub password: String,
+}
+
+/// The machine entries of a `.netrc` file.
+#[derive(Debug, Default)]
+pub struct Netrc {
+    machines: BTreeMap<String, Login>, // Host → first entry naming it
+    default: Option<Login>,            // The `default` entry, for any other host
+}
+
+impl Netrc {
+    /// Reads the user's `.netrc`; empty when there is none.
+    pub fn load() -> Result<Netrc> {
+        let Some(path) = path() else {
+            return Ok(Netrc::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(Netrc::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Netrc::default()),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Netrc {
+        let mut netrc = Netrc::default();
+        let mut entry: Option<(Option<String>, Login)> = None; // (machine, login) being read
+        let finish = |netrc: &mut Netrc, entry: Option<(Option<String>, Login)>| {
+            match entry {
+                Some((_, login)) if login.password.is_empty() => {}
+                Some((Some(machine), login)) => {
+                    netrc.machines.entry(machine).or_insert(login);
+                }
+                Some((None, login)) => {
+                    netrc.default.get_or_insert(login);
+                }
+                None => {}
+            }
+        };
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let mut words = line.split_whitespace();
+            while let Some(word) = words.next() {
+                match word {
+                    "machine" => {
+                        finish(&mut netrc, entry.take());
+                        let machine = words.next().unwrap_or_default().to_string();
+                        entry = Some((Some(machine), Login::default()));
+                    }
+                    "default" => {
+                        finish(&mut netrc, entry.take());
+                        entry = Some((None, Login::default()));
+                    }
+                    "login" => {
+                        let value = words.next().map(str::to_string);
+                        if let Some((_, login)) = entry.as_mut() {
+                            login.login = value;
+                        }
+                    }
+                    "password" => {
+                        let value = words.next().unwrap_or_default().to_string();
+                        if let Some((_, login)) = entry.as_mut() {
+                            login.password = value;
+                        }
+                    }
+                    "account" => {
+                        words.next();
+                    }
+                    "macdef" => {
+                        finish(&mut netrc, entry.take());
+                        lines.by_ref().take_while(|line| !line.trim().is_empty()).for_each(drop);
+                        break;
+                    }
+                    word if word.starts_with('#') => break,
+                    _ => {}
+                }
+            }
+        }
+        finish(&mut netrc, entry);
+        netrc
+    }
+
+    /// The login for `host`: its own entry, else `default`. With `user` (from
+    /// the remote URL), only an entry for that login or without one matches.
+    pub fn login(&self, host: &str, user: Option<&str>) -> Option<&Login> {
+        let fits = |login: &&Login| match (user, &login.login) {
+            (Some(user), Some(login)) => user == login,
+            _ => true,
+        };
+        match self.machines.get(host) {
+            Some(login) => Some(login).filter(fits),
+            None => self.default.as_ref().filter(fits),
+        }
+    }
+}
+
+/// Where the `.netrc` file is looked for.
+fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let home = home_dir()?;
+    let dotted = home.join(".netrc");
+    if cfg!(windows) && !dotted.exists() {
+        return Some(home.join("_netrc"));
+    }
+    Some(dotted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_machines_and_default() {
+        let netrc = Netrc::parse(
+            "# internal hosts\n\
+             machine git.example.com login alice password s3cret\n\
+             machine other.example.com\n  login bob\n  account x\n  password hunter2\n\
+             macdef init\ncd /\n\n\
+             machine git.example.com login mallory password wrong\n\
+             machine nopass.example.com login carol\n\
+             default login anon password guest\n",
+        );
+        let login = |login: &str, password: &str| Login {
+            login: Some(login.to_string()),
+            password: password.to_string(),
+        };
+        assert_eq!(netrc.login("git.example.com", None), Some(&login("alice", "s3cret")));
+        assert_eq!(netrc.login("other.example.com", None), Some(&login("bob", "hunter2")));
+        assert_eq!(netrc.login("unknown.example.com", None), Some(&login("anon", "guest")));
+        assert_eq!(netrc.login("nopass.example.com", None), Some(&login("anon", "guest")));
+        assert_eq!(netrc.login("git.example.com", Some("bob")), None);
+        assert!(netrc.login("git.example.com", Some("alice")).is_some());
+        assert!(Netrc::parse("").login("git.example.com", None).is_none());
+    }
+}
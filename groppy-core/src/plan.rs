@@ -0,0 +1,348 @@
+//! Dry runs: what an update would do to each repository, from the fetch
+//! handshake alone. Nothing is downloaded and no ref or file changes.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::config::{Pin, Strategy};
+use crate::hooks::Verdict;
+use crate::update::{
+    NoRemote, UpdateOptions, disallowed_branch, find_updated_target, guess_upstream,
+    has_local_changes, is_ancestor, latest_release_tag, open_for_fetch, resolve_upstream,
+    select_remote, tag_refspec,
+};
+
+/// What `--confirm` expects an update to do to a repository.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanAction {
+    FastForward, // Upstream has new commits that HEAD can move to
+    UpToDate,    // HEAD already matches upstream
+    Ahead,       // HEAD has local commits upstream lacks
+    Diverged,    // Both sides have commits; a fast-forward is impossible
+    Dirty,       // Local changes would block the update
+    Detached,    // HEAD is not on a branch
+    OffBranch,   // HEAD is on a branch outside --only-branch
+    Integrate,   // The strategy rebases, merges, or resets onto upstream
+    Fetch,       // Upstream moved; fetch-only leaves HEAD alone
+    Skipped,     // Left alone by strategy skip or the hook script
+    NoUpstream,  // HEAD's branch has no upstream configured
+    NoRemote,    // No usable remote
+    Error,       // The repo could not be checked
+}
+
+impl PlanAction {
+    /// Every action, in the order the plan summary lists them.
+    pub const ALL: [PlanAction; 13] = [
+        PlanAction::FastForward,
+        PlanAction::Integrate,
+        PlanAction::Fetch,
+        PlanAction::UpToDate,
+        PlanAction::Ahead,
+        PlanAction::Diverged,
+        PlanAction::Dirty,
+        PlanAction::Detached,
+        PlanAction::OffBranch,
+        PlanAction::Skipped,
+        PlanAction::NoUpstream,
+        PlanAction::NoRemote,
+        PlanAction::Error,
+    ];
+
+    /// Short label used in plan lines and the summary counts.
+    pub fn label(self) -> &'static str {
+        match self {
+            PlanAction::FastForward => "fast-forward",
+            PlanAction::Integrate => "integrate",
+            PlanAction::Fetch => "fetch only",
+            PlanAction::UpToDate => "up to date",
+            PlanAction::Ahead => "ahead",
+            PlanAction::Diverged => "diverged",
+            PlanAction::Dirty => "dirty",
+            PlanAction::Detached => "detached",
+            PlanAction::OffBranch => "other branch",
+            PlanAction::Skipped => "skipped",
+            PlanAction::NoUpstream => "no upstream",
+            PlanAction::NoRemote => "no remote",
+            PlanAction::Error => "error",
+        }
+    }
+}
+
+/// One repository's entry in the `--confirm` plan.
+pub struct PlannedRepo {
+    pub path: PathBuf,
+    pub action: PlanAction,
+    pub detail: String, // Commit range or error message; may be empty
+}
+
+/// Checks every repository with [`plan_repository`] on `pool`, calling
+/// `on_checked` from the worker thread as each one finishes.
+/// Results keep the order of `repos`.
+pub fn plan_all(
+    pool: &rayon::ThreadPool,
+    repos: &[PathBuf],
+    options_for: &(dyn Fn(&Path) -> UpdateOptions + Sync),
+    on_checked: &(dyn Fn(&PlannedRepo) + Sync),
+) -> Vec<PlannedRepo> {
+    use rayon::prelude::*;
+
+    pool.install(|| {
+        repos
+            .par_iter()
+            .map(|path| {
+                let planned = plan_repository(path, &options_for(path));
+                on_checked(&planned);
+                planned
+            })
+            .collect()
+    })
+}
+
+/// Works out what an update would do to a repository without changing it.
+/// Errors become `PlanAction::Error` entries.
+pub fn plan_repository(path: &Path, opts: &UpdateOptions) -> PlannedRepo {
+    let _span = tracing::info_span!("plan", path = %path.display()).entered();
+    let (action, detail) = try_plan_repository(path, opts).unwrap_or_else(|e| {
+        let action = match e.downcast_ref::<NoRemote>() {
+            Some(_) => PlanAction::NoRemote,
+            None => PlanAction::Error,
+        };
+        (action, e.to_string())
+    });
+    PlannedRepo {
+        path: path.to_path_buf(),
+        action,
+        detail,
+    }
+}
+
+/// Classifies a repository using only the fetch handshake: the remote's ref
+/// advertisement says where upstream points, and no objects are downloaded.
+///
+/// When the advertised commit is not present locally the ancestry check falls
+/// back to the current tracking ref: if HEAD already contains commits that the
+/// last-fetched upstream lacks, the update would not be a fast-forward.
+fn try_plan_repository(path: &Path, opts: &UpdateOptions) -> Result<(PlanAction, String)> {
+    if opts.strategy() == Strategy::Skip {
+        return Ok((PlanAction::Skipped, "strategy skip".to_string()));
+    }
+    let repo = open_for_fetch(path, opts)?;
+    if let Some(hooks) = &opts.hooks
+        && let Verdict::Skip(reason) = hooks.should_update(path, &repo, opts)?
+    {
+        let detail = match reason.is_empty() {
+            true => "hook".to_string(),
+            false => format!("hook: {reason}"),
+        };
+        return Ok((PlanAction::Skipped, detail));
+    }
+    if opts.pin.is_none()
+        && let Some(branch) = disallowed_branch(&repo, opts)?
+    {
+        return Ok((PlanAction::OffBranch, branch));
+    }
+    if opts.strategy() != Strategy::FetchOnly && has_local_changes(&repo, path, opts)? {
+        return Ok((PlanAction::Dirty, String::new()));
+    }
+    match &opts.pin {
+        Some(Pin::Tag(tag)) => return plan_pinned_tag(&repo, Some(tag), opts),
+        Some(Pin::LatestTag) => return plan_pinned_tag(&repo, None, opts),
+        Some(Pin::Branch(branch))
+            if repo.head_name()?.is_none_or(|name| name.shorten() != branch.as_str()) =>
+        {
+            return Ok((PlanAction::FastForward, format!("switch to {branch}")));
+        }
+        _ => {}
+    }
+    let Some(head_ref) = repo.head_ref()? else {
+        return Ok((PlanAction::Detached, String::new()));
+    };
+    let old_id = head_ref.id().detach();
+    let Some((remote, tracking_ref)) = resolve_upstream(&repo, head_ref.name(), opts)? else {
+        return Ok(match guess_upstream(&repo, opts)? {
+            Some(guess) if opts.set_upstream => {
+                (PlanAction::FastForward, format!("set upstream to {guess}"))
+            }
+            _ => (PlanAction::NoUpstream, String::new()),
+        });
+    };
+
+    let prepare = remote
+        .connect(gix::remote::Direction::Fetch)?
+        .prepare_fetch(gix::progress::Discard, Default::default())?;
+    let Some(new_id) = find_updated_target(prepare.ref_map(), &tracking_ref) else {
+        return Ok((PlanAction::UpToDate, String::new()));
+    };
+    if new_id == old_id {
+        return Ok((PlanAction::UpToDate, String::new()));
+    }
+
+    let range = format!("{}..{}", old_id.to_hex_with_len(7), new_id.to_hex_with_len(7));
+    if opts.strategy() == Strategy::FetchOnly {
+        return Ok((PlanAction::Fetch, range));
+    }
+    let action = if repo.has_object(new_id) {
+        if is_ancestor(&repo, old_id, new_id)? {
+            PlanAction::FastForward
+        } else if is_ancestor(&repo, new_id, old_id)? {
+            PlanAction::Ahead
+        } else {
+            PlanAction::Diverged
+        }
+    } else {
+        let last_fetched = repo
+            .try_find_reference(tracking_ref.as_str())?
+            .and_then(|mut r| r.peel_to_id().ok())
+            .map(|id| id.detach());
+        match last_fetched {
+            Some(tracking) if !is_ancestor(&repo, old_id, tracking)? => PlanAction::Diverged,
+            _ => PlanAction::FastForward,
+        }
+    };
+    Ok(match (action, opts.strategy()) {
+        (PlanAction::FastForward, _) => (action, range),
+        (PlanAction::Diverged, Strategy::Rebase | Strategy::Merge | Strategy::Reset)
+        | (PlanAction::Ahead, Strategy::Reset) => {
+            (PlanAction::Integrate, format!("{} {range}", opts.strategy().label()))
+        }
+        _ => (action, String::new()),
+    })
+}
+
+/// Plans a tag pin (`None`: latest release tag) from the handshake: up to date
+/// when HEAD is detached at the local tag and the remote advertises the same
+/// tag object.
+fn plan_pinned_tag(
+    repo: &gix::Repository,
+    tag: Option<&str>,
+    opts: &UpdateOptions,
+) -> Result<(PlanAction, String)> {
+    let refspec = tag_refspec(tag);
+    let (remote, _) = select_remote(repo, opts)?;
+    let remote = remote.with_refspecs([refspec.as_str()], gix::remote::Direction::Fetch)?;
+    let prepare = remote
+        .connect(gix::remote::Direction::Fetch)?
+        .prepare_fetch(gix::progress::Discard, Default::default())?;
+    let tag = match tag {
+        Some(tag) => tag.to_string(),
+        None => latest_release_tag(prepare.ref_map())
+            .ok_or_else(|| anyhow::anyhow!("No release tags on remote"))?,
+    };
+    let tag_ref = format!("refs/tags/{tag}");
+    let Some(advertised) = find_updated_target(prepare.ref_map(), &tag_ref) else {
+        anyhow::bail!("Tag {tag} not found on remote");
+    };
+    let at_tag = match repo.try_find_reference(tag_ref.as_str())? {
+        Some(mut local) => {
+            local.id().detach() == advertised
+                && repo.head_ref()?.is_none()
+                && local.peel_to_id()?.detach() == repo.head_id()?.detach()
+        }
+        None => false,
+    };
+    if at_tag {
+        Ok((PlanAction::UpToDate, String::new()))
+    } else {
+        Ok((PlanAction::FastForward, format!("check out {tag}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use std::fs;
+
+    #[test]
+    fn test_plan_repository_up_to_date() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::UpToDate, "{}", plan.detail);
+    }
+
+    #[test]
+    fn test_plan_repository_no_remote() {
+        let tmp = tempfile::tempdir().unwrap();
+        init_repo_with_commit(tmp.path());
+        let plan = plan_repository(tmp.path(), &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::NoRemote);
+    }
+
+    #[test]
+    fn test_plan_repository_fast_forward_changes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (work_path, clone_path) = init_remote_and_clone(tmp.path());
+        push_new_file(&work_path, "planned.txt");
+
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::FastForward, "{}", plan.detail);
+        assert!(plan.detail.contains(".."));
+        assert!(!clone_path.join("planned.txt").exists());
+    }
+
+    #[test]
+    fn test_plan_repository_dirty_and_detached() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        fs::write(clone_path.join("local.txt"), "wip").unwrap();
+        git(&clone_path, &["add", "local.txt"]);
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::Dirty);
+
+        git(&clone_path, &["rm", "--cached", "-q", "local.txt"]);
+        git(&clone_path, &["checkout", "--detach"]);
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::Detached);
+    }
+
+    #[test]
+    fn test_plan_repository_ahead() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        fs::write(clone_path.join("local.txt"), "mine").unwrap();
+        git(&clone_path, &["add", "."]);
+        git(&clone_path, &["commit", "-m", "local"]);
+
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::Ahead, "{}", plan.detail);
+    }
+
+    #[test]
+    fn test_plan_repository_follows_strategy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (work_path, clone_path) = init_remote_and_clone(tmp.path());
+        push_new_file(&work_path, "theirs.txt");
+        let plan_with = |strategy| {
+            let opts = UpdateOptions {
+                strategy,
+                ..Default::default()
+            };
+            plan_repository(&clone_path, &opts)
+        };
+        assert_eq!(plan_with(Strategy::FetchOnly).action, PlanAction::Fetch);
+        assert_eq!(plan_with(Strategy::Skip).action, PlanAction::Skipped);
+
+        fs::write(clone_path.join("mine.txt"), "mine").unwrap();
+        git(&clone_path, &["add", "."]);
+        git(&clone_path, &["commit", "-m", "local"]);
+        let plan = plan_with(Strategy::Rebase);
+        assert_eq!(plan.action, PlanAction::Integrate, "{}", plan.detail);
+        assert!(plan.detail.starts_with("rebase "), "{}", plan.detail);
+        assert_eq!(plan_with(Strategy::FfOnly).action, PlanAction::Diverged);
+    }
+
+    #[test]
+    fn test_plan_repository_diverged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (work_path, clone_path) = init_remote_and_clone(tmp.path());
+        push_new_file(&work_path, "theirs.txt");
+        fs::write(clone_path.join("mine.txt"), "mine").unwrap();
+        git(&clone_path, &["add", "."]);
+        git(&clone_path, &["commit", "-m", "local"]);
+
+        let plan = plan_repository(&clone_path, &UpdateOptions::default());
+        assert_eq!(plan.action, PlanAction::Diverged, "{}", plan.detail);
+    }
+}
@@ -0,0 +1,774 @@
+//! Whole runs: many repositories updated in parallel, with progress reported
+//! through a callback and run-wide controls (abort, deadline, pause).
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use gix::bstr::ByteSlice;
+use gix::url::Scheme;
+use rayon::prelude::*;
+
+use crate::discovery::open_repository;
+use crate::state::UpdateTimes;
+use crate::status::{Bucket, ErrorKind, RepoStatus};
+use crate::update::{
+    IpVersion, UpdateOptions, open_for_fetch, select_remote, ssh_destination, update_repository,
+    url_matches,
+};
+
+/// Consecutive authentication failures after which a host's remaining repos
+/// are skipped instead of asking it (and the credential helper) again.
+pub const AUTH_FAILURE_LIMIT: usize = 3;
+
+/// How long [`RunControl::check_hosts`] waits for a host to accept a connection.
+pub const HOST_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variables that send HTTP(S) through a proxy, which makes a
+/// direct connection to the host meaningless as a check.
+const PROXY_VARS: [&str; 6] =
+    ["http_proxy", "https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY", "HTTP_PROXY"];
+
+/// Run-wide switches shared by the workers and whatever steers the run:
+/// prompts, timers, key handlers, a UI.
+#[derive(Default)]
+pub struct RunControl {
+    aborted: AtomicBool,          // Skip every repo not yet started
+    interrupted: Arc<AtomicBool>, // Cancel fetches in flight; shared with each UpdateOptions
+    paused: Mutex<bool>,          // Hold repos not yet started; in-flight ones finish
+    resumed: Condvar,
+    slots: Mutex<Option<Slots>>, // Adaptive limit on repos in flight, when enabled
+    freed: Condvar,
+    auth_failures: Mutex<HashMap<String, usize>>, // Consecutive auth failures per remote host
+    unreachable: Mutex<BTreeMap<String, usize>>,  // Hosts found down before the run → repos
+    offline: AtomicBool,                          // Fetch nothing, report the last fetch's state
+    failures: AtomicUsize,                        // Repos failed so far
+    error_limit: AtomicUsize,                     // Failures that abort the run; 0 = no limit
+}
+
+/// Concurrency for [`RunControl::adapt_jobs`]: additive increase while repos
+/// succeed, halved on every network failure (timeouts, throttling).
+struct Slots {
+    max: usize,     // Ceiling: the pool's thread count
+    limit: usize,   // Repos allowed in flight now, 1..=max
+    running: usize, // Repos in flight
+    streak: usize,  // Clean finishes since the limit last changed
+}
+
+impl RunControl {
+    /// Skips every repo not yet started; repos in flight finish normally.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::Release);
+    }
+
+    /// Whether the run was aborted or interrupted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
+    }
+
+    /// Aborts and also cancels fetches in flight, leaving their HEADs untouched
+    /// (e.g. when a deadline passes).
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Release);
+        self.abort();
+    }
+
+    /// Whether the run was interrupted.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Acquire)
+    }
+
+    /// Aborts the run once `max` repos have failed (`--max-errors`), before
+    /// the worker that saw the last failure starts another repo. 0 = no limit.
+    pub fn stop_after_failures(&self, max: usize) {
+        self.error_limit.store(max, Ordering::Release);
+    }
+
+    /// Whether the run was aborted for reaching the failure limit.
+    pub fn hit_error_limit(&self) -> bool {
+        let max = self.error_limit.load(Ordering::Acquire);
+        max > 0 && self.failures.load(Ordering::Acquire) >= max
+    }
+
+    /// Counts a failed repo, aborting the run when that reaches the limit.
+    fn record_failure(&self, status: &RepoStatus) {
+        if status.success || status.bucket.is_some() {
+            return;
+        }
+        self.failures.fetch_add(1, Ordering::AcqRel);
+        if self.hit_error_limit() && !self.is_aborted() {
+            tracing::warn!(failures = self.failures.load(Ordering::Acquire), "error limit reached");
+            self.abort();
+        }
+    }
+
+    /// Stops or restarts starting new repos; returns false when already in
+    /// that state.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        let mut current = self.paused.lock().unwrap();
+        if *current == paused {
+            return false;
+        }
+        *current = paused;
+        self.resumed.notify_all();
+        true
+    }
+
+    /// Whether starting new repos is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap()
+    }
+
+    /// Varies how many repos are in flight between 1 and `max` instead of
+    /// keeping every pool thread busy: starts at half of `max`, grows by one
+    /// after each `limit` clean finishes, halves on a network failure.
+    pub fn adapt_jobs(&self, max: usize) {
+        let max = max.max(1);
+        let limit = max.div_ceil(2);
+        *self.slots.lock().unwrap() = Some(Slots { max, limit, running: 0, streak: 0 });
+    }
+
+    /// The current adaptive job limit, if [`adapt_jobs`](Self::adapt_jobs) is on.
+    pub fn jobs_limit(&self) -> Option<usize> {
+        self.slots.lock().unwrap().as_ref().map(|slots| slots.limit)
+    }
+
+    /// Blocks a worker about to start a repo until the adaptive limit has room,
+    /// or the run is aborted (the repo is then skipped at once).
+    fn acquire_slot(&self) {
+        let mut slots = self.slots.lock().unwrap();
+        while let Some(s) = slots.as_mut() {
+            if s.running < s.limit || self.is_aborted() {
+                s.running += 1;
+                break;
+            }
+            slots = self.freed.wait_timeout(slots, Duration::from_millis(100)).unwrap().0;
+        }
+    }
+
+    /// Frees the slot of a finished repo and adjusts the limit by its outcome.
+    fn release_slot(&self, status: &RepoStatus) {
+        let mut slots = self.slots.lock().unwrap();
+        let Some(s) = slots.as_mut() else {
+            return;
+        };
+        s.running -= 1;
+        if status.error_kind() == Some(ErrorKind::Network) {
+            let limit = (s.limit / 2).max(1);
+            if limit < s.limit {
+                tracing::info!(limit, "network failure, fewer jobs");
+            }
+            (s.limit, s.streak) = (limit, 0);
+        } else if status.success && status.bucket.is_none() {
+            s.streak += 1;
+            if s.streak >= s.limit && s.limit < s.max {
+                (s.limit, s.streak) = (s.limit + 1, 0);
+                tracing::debug!(limit = s.limit, "more jobs");
+            }
+        }
+        self.freed.notify_all();
+    }
+
+    /// Hosts whose credentials failed [`AUTH_FAILURE_LIMIT`] times in a row;
+    /// their other repos were skipped.
+    pub fn failing_hosts(&self) -> Vec<String> {
+        let failures = self.auth_failures.lock().unwrap();
+        let mut hosts: Vec<String> = failures
+            .iter()
+            .filter(|&(_, &count)| count >= AUTH_FAILURE_LIMIT)
+            .map(|(host, _)| host.clone())
+            .collect();
+        hosts.sort();
+        hosts
+    }
+
+    fn host_failing(&self, host: &str) -> bool {
+        let failures = self.auth_failures.lock().unwrap();
+        failures.get(host).is_some_and(|&count| count >= AUTH_FAILURE_LIMIT)
+    }
+
+    /// Counts an authentication failure against `host`, or clears its count
+    /// when the repo got through.
+    fn record_auth(&self, host: &str, status: &RepoStatus) {
+        let mut failures = self.auth_failures.lock().unwrap();
+        if status.error_kind() == Some(ErrorKind::Auth) {
+            let count = failures.entry(host.to_string()).or_default();
+            *count += 1;
+            if *count == AUTH_FAILURE_LIMIT {
+                tracing::warn!(host, "credentials are not working, skipping its other repos");
+            }
+        } else if status.success && status.bucket.is_none() {
+            failures.remove(host);
+        }
+    }
+
+    /// Runs offline: no repo not yet started is fetched; each reports what its
+    /// last fetch left behind, in the `offline` bucket.
+    pub fn set_offline(&self) {
+        self.offline.store(true, Ordering::Release);
+    }
+
+    /// Whether the run is offline, by request or because no host was reachable.
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Acquire)
+    }
+
+    /// Connects once to each remote host of `repos`, in parallel on `pool`,
+    /// before the run, so the repos of a host that cannot be reached (VPN down)
+    /// are skipped at once instead of each timing out on a worker. Hosts behind
+    /// a proxy or jump host are not checked. When none of the hosts can be
+    /// reached and none went unchecked, the network is down: the run goes
+    /// [offline](Self::set_offline) instead of skipping everything.
+    pub fn check_hosts(
+        &self,
+        pool: &rayon::ThreadPool,
+        repos: &[PathBuf],
+        options_for: &(dyn Fn(&Path) -> UpdateOptions + Sync),
+    ) {
+        let targets: Vec<(String, Option<Target>)> = pool.install(|| {
+            repos.par_iter().filter_map(|path| check_target(path, &options_for(path))).collect()
+        });
+        // A host counts as down only if every way its repos reach it was checked and failed
+        let mut hosts: BTreeMap<String, (usize, Vec<Target>)> = BTreeMap::new();
+        let mut unchecked = Vec::new();
+        for (host, target) in targets {
+            let (repos, checks) = hosts.entry(host.clone()).or_default();
+            *repos += 1;
+            match target {
+                Some(target) if !checks.contains(&target) => checks.push(target),
+                Some(_) => {}
+                None => unchecked.push(host),
+            }
+        }
+        let checked = hosts.len();
+        hosts.retain(|host, _| !unchecked.contains(host));
+        let down: BTreeMap<String, usize> = pool.install(|| {
+            hosts
+                .into_par_iter()
+                .filter(|(_, (_, checks))| !checks.par_iter().any(reachable))
+                .map(|(host, (repos, _))| (host, repos))
+                .collect()
+        });
+        if checked > 0 && down.len() == checked {
+            tracing::warn!(hosts = checked, "no remote host is reachable, going offline");
+            self.set_offline();
+            return;
+        }
+        for (host, repos) in &down {
+            tracing::warn!(host, repos, "host is unreachable, skipping its repos");
+        }
+        *self.unreachable.lock().unwrap() = down;
+    }
+
+    /// Hosts [`check_hosts`](Self::check_hosts) could not reach, with how many
+    /// repos each has; those repos were skipped.
+    pub fn unreachable_hosts(&self) -> Vec<(String, usize)> {
+        let unreachable = self.unreachable.lock().unwrap();
+        unreachable.iter().map(|(host, &repos)| (host.clone(), repos)).collect()
+    }
+
+    fn host_unreachable(&self, host: &str) -> bool {
+        self.unreachable.lock().unwrap().contains_key(host)
+    }
+
+    /// Blocks a worker about to start a repo until the run is resumed, or until
+    /// it is aborted so a deadline or error limit is not held up by a pause.
+    fn wait_while_paused(&self) {
+        let mut paused = self.paused.lock().unwrap();
+        while *paused && !self.is_aborted() {
+            paused = self.resumed.wait_timeout(paused, Duration::from_millis(100)).unwrap().0;
+        }
+    }
+}
+
+/// Progress of an [`update_all`] run, delivered on the worker thread.
+pub enum Event<'a> {
+    /// A worker (the pool's thread index) started on a repo.
+    Started { worker: usize, path: &'a Path },
+    /// A repo is done: updated, failed, or skipped.
+    Finished { worker: usize, status: &'a RepoStatus },
+}
+
+/// Updates every repository on `pool` and returns the results sorted by path.
+/// Repos start in the order given (see [`slowest_first`]).
+///
+/// `options_for` supplies each repo's settings; its `interrupt` flag is
+/// replaced by `control`'s, and an offline `control` makes it offline. Once
+/// `control` is aborted, or its failure limit reached, repos not yet started
+/// are reported in the `skipped` bucket instead of being updated.
+pub fn update_all(
+    pool: &rayon::ThreadPool,
+    repos: &[PathBuf],
+    options_for: &(dyn Fn(&Path) -> UpdateOptions + Sync),
+    control: &RunControl,
+    on_event: &(dyn Fn(Event<'_>) + Sync),
+) -> Vec<RepoStatus> {
+    let results = Mutex::new(Vec::with_capacity(repos.len()));
+    pool.scope_fifo(|s| {
+        for path in repos {
+            let results = &results;
+            s.spawn_fifo(move |_| {
+                control.wait_while_paused();
+                control.acquire_slot();
+                let worker = rayon::current_thread_index().unwrap_or(0);
+                on_event(Event::Started { worker, path });
+                let started = Instant::now();
+                let mut status = if control.is_aborted() {
+                    skipped(path, control)
+                } else {
+                    let opts = options_for(path);
+                    let opts = UpdateOptions {
+                        interrupt: control.interrupted.clone(),
+                        offline: opts.offline || control.is_offline(),
+                        ..opts
+                    };
+                    match remote_host(path, &opts) {
+                        Some(host) if control.host_unreachable(&host) => {
+                            unreachable_skipped(path, &host)
+                        }
+                        Some(host) if control.host_failing(&host) => host_skipped(path, &host),
+                        Some(host) => {
+                            let status = update_repository(path, &opts);
+                            control.record_auth(&host, &status);
+                            status
+                        }
+                        None => update_repository(path, &opts),
+                    }
+                };
+                status.duration = started.elapsed();
+                control.record_failure(&status);
+                control.release_slot(&status);
+                on_event(Event::Finished { worker, status: &status });
+                results.lock().unwrap().push(status);
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    results
+}
+
+/// Orders repos so the historically slowest start first and a big monorepo
+/// does not finish alone at the end of the run. Repos never measured go first,
+/// since they may be slow too; ties keep their order.
+pub fn slowest_first(repos: &mut [PathBuf], times: &UpdateTimes) {
+    repos.sort_by_key(|repo| std::cmp::Reverse(times.typical(repo).unwrap_or(Duration::MAX)));
+}
+
+/// Status for a repo never started because the run was aborted.
+fn skipped(path: &Path, control: &RunControl) -> RepoStatus {
+    let reason = if control.is_interrupted() {
+        "deadline reached"
+    } else if control.hit_error_limit() {
+        "too many failures"
+    } else {
+        "run aborted"
+    };
+    RepoStatus {
+        path: path.to_path_buf(),
+        success: true,
+        message: format!("Skipped - {reason}"),
+        files_changed: 0,
+        bucket: Some(Bucket::Skipped),
+        ..Default::default()
+    }
+}
+
+/// Host of the remote a repo would fetch from; None for local paths or when
+/// the repo or remote cannot be read (the update reports that itself).
+fn remote_host(path: &Path, opts: &UpdateOptions) -> Option<String> {
+    let repo = open_repository(path).ok()?;
+    let (remote, _) = select_remote(&repo, opts).ok()?;
+    let url = remote.url(gix::remote::Direction::Fetch)?;
+    url.host().map(str::to_string)
+}
+
+/// Address a fetch connects to, checked by [`RunControl::check_hosts`].
+#[derive(Clone, PartialEq)]
+struct Target {
+    host: String,
+    port: u16,
+    ip_version: Option<IpVersion>, // SSH honors --ipv4/--ipv6; other transports don't
+}
+
+/// A repo's remote host with the address its fetch connects to; the address is
+/// None when a proxy or jump host sits in between. None for local remotes or
+/// unreadable repos.
+fn check_target(path: &Path, opts: &UpdateOptions) -> Option<(String, Option<Target>)> {
+    let repo = open_for_fetch(path, opts).ok()?; // The URL --prefer rewrote it to
+    let (remote, _) = select_remote(&repo, opts).ok()?;
+    let url = remote.url(gix::remote::Direction::Fetch)?;
+    let host = url.host()?.to_string();
+    let target = match url.scheme {
+        Scheme::Ssh => ssh_destination(&repo, url).map(|(host, port)| Target {
+            host,
+            port,
+            ip_version: opts.ip_version,
+        }),
+        Scheme::Http | Scheme::Https | Scheme::Git if !proxied(&repo, &remote, url) => {
+            url.port_or_default().map(|port| Target {
+                host: host.clone(),
+                port,
+                ip_version: None,
+            })
+        }
+        _ => None,
+    };
+    Some((host, target))
+}
+
+/// Repos whose fetches `--ipv4`/`--ipv6` cannot steer: their remote (as
+/// `--prefer` rewrote it) is HTTP(S) or `git://`, where gix picks the address
+/// family itself. Empty when no address family was asked for.
+pub fn ip_version_ignored(
+    pool: &rayon::ThreadPool,
+    repos: &[PathBuf],
+    options_for: &(dyn Fn(&Path) -> UpdateOptions + Sync),
+) -> Vec<PathBuf> {
+    let ignored = |path: &Path| {
+        let opts = options_for(path);
+        opts.ip_version?;
+        let repo = open_for_fetch(path, &opts).ok()?;
+        let (remote, _) = select_remote(&repo, &opts).ok()?;
+        let scheme = remote.url(gix::remote::Direction::Fetch)?.scheme.clone();
+        matches!(scheme, Scheme::Http | Scheme::Https | Scheme::Git).then_some(())
+    };
+    pool.install(|| {
+        repos.par_iter().filter(|path| ignored(path).is_some()).cloned().collect()
+    })
+}
+
+/// Whether fetches of `remote` from `url` go through a proxy: `core.gitProxy`
+/// for `git://`; for HTTP(S) git's `http.proxy`, an `http.<url>.proxy` matching
+/// `url`, `remote.<name>.proxy`, or the proxy environment variables.
+fn proxied(repo: &gix::Repository, remote: &gix::Remote<'_>, url: &gix::Url) -> bool {
+    let config = repo.config_snapshot();
+    if url.scheme == Scheme::Git {
+        return config.string("core.gitProxy").is_some();
+    }
+    let name = remote.name().map(|name| name.as_bstr().to_str_lossy());
+    proxy_configured(config.plumbing(), name.as_deref(), url)
+        || PROXY_VARS.iter().any(|name| std::env::var_os(name).is_some())
+}
+
+/// Whether `config` sets a `proxy` in `http`, an `http.<url>` section matching
+/// `url`, or the `remote.<name>` section of `remote`. Any value counts, even
+/// the empty one that turns a proxy off: the host is then merely not checked.
+fn proxy_configured(config: &gix::config::File<'_>, remote: Option<&str>, url: &gix::Url) -> bool {
+    let sets_proxy = |section_name: &str, applies: &dyn Fn(Option<&str>) -> bool| {
+        config.sections_by_name(section_name).into_iter().flatten().any(|section| {
+            let subsection = section.header().subsection_name().map(|name| name.to_str_lossy());
+            applies(subsection.as_deref()) && section.value("proxy").is_some()
+        })
+    };
+    sets_proxy("http", &|pattern| pattern.is_none_or(|pattern| url_matches(pattern, url)))
+        || sets_proxy("remote", &|name| name.is_some() && name == remote)
+}
+
+/// Whether `target` resolves and accepts a TCP connection within
+/// [`HOST_CHECK_TIMEOUT`] on any of its addresses.
+fn reachable(target: &Target) -> bool {
+    let Ok(addrs) = (target.host.as_str(), target.port).to_socket_addrs() else {
+        return false; // Not resolvable, e.g. an internal name off the VPN
+    };
+    addrs
+        .filter(|addr| match target.ip_version {
+            Some(IpVersion::V4) => addr.is_ipv4(),
+            Some(IpVersion::V6) => addr.is_ipv6(),
+            None => true,
+        })
+        .any(|addr| TcpStream::connect_timeout(&addr, HOST_CHECK_TIMEOUT).is_ok())
+}
+
+/// Status for a repo not started because its host could not be reached.
+fn unreachable_skipped(path: &Path, host: &str) -> RepoStatus {
+    RepoStatus {
+        path: path.to_path_buf(),
+        success: true,
+        message: format!("Skipped - {host} is unreachable"),
+        bucket: Some(Bucket::Skipped),
+        ..Default::default()
+    }
+}
+
+/// Status for a repo not started because its host keeps rejecting credentials.
+fn host_skipped(path: &Path, host: &str) -> RepoStatus {
+    RepoStatus {
+        path: path.to_path_buf(),
+        success: true,
+        message: format!("Skipped - credentials for {host} are not working"),
+        bucket: Some(Bucket::Skipped),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::update::run_git;
+
+    fn pool() -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap()
+    }
+
+    #[test]
+    fn test_update_all_reports_each_repo_sorted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repos = vec![tmp.path().join("b"), tmp.path().join("a")];
+        for repo in &repos {
+            init_repo_with_commit(repo);
+        }
+        let finished = Mutex::new(Vec::new());
+        let results = update_all(
+            &pool(),
+            &repos,
+            &|_| UpdateOptions::default(),
+            &RunControl::default(),
+            &|event| {
+                if let Event::Finished { status, .. } = event {
+                    finished.lock().unwrap().push(status.path.clone());
+                }
+            },
+        );
+        let paths: Vec<PathBuf> = results.iter().map(|status| status.path.clone()).collect();
+        assert_eq!(paths, vec![tmp.path().join("a"), tmp.path().join("b")]);
+        assert_eq!(finished.into_inner().unwrap().len(), 2);
+        assert!(results.iter().all(|status| status.bucket == Some(Bucket::NoRemote)));
+    }
+
+    #[test]
+    fn test_update_all_skips_after_abort() {
+        let control = RunControl::default();
+        control.interrupt();
+        let repos = [PathBuf::from("/nonexistent/repo")];
+        let results = update_all(&pool(), &repos, &|_| UpdateOptions::default(), &control, &|_| {});
+        assert_eq!(results[0].bucket, Some(Bucket::Skipped));
+        assert_eq!(results[0].message, "Skipped - deadline reached");
+    }
+
+    #[test]
+    fn test_error_limit_skips_repos_not_started() {
+        let control = RunControl::default();
+        control.stop_after_failures(2);
+        let repos: Vec<PathBuf> =
+            ["a", "b", "c", "d"].iter().map(|name| Path::new("/nonexistent").join(name)).collect();
+        let updated = Mutex::new(Vec::new());
+        let one_worker = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let options_for = |path: &Path| {
+            updated.lock().unwrap().push(path.to_path_buf());
+            UpdateOptions::default()
+        };
+        let results = update_all(&one_worker, &repos, &options_for, &control, &|_| {});
+        assert_eq!(updated.into_inner().unwrap(), repos[..2]);
+        assert!(results[..2].iter().all(|status| !status.success));
+        for status in &results[2..] {
+            assert_eq!(status.bucket, Some(Bucket::Skipped));
+            assert_eq!(status.message, "Skipped - too many failures");
+        }
+        assert!(control.hit_error_limit());
+    }
+
+    #[test]
+    fn test_auth_failures_block_host() {
+        let control = RunControl::default();
+        let auth = RepoStatus {
+            error: Some(ErrorKind::Auth),
+            ..Default::default()
+        };
+        let ok = RepoStatus {
+            success: true,
+            ..Default::default()
+        };
+        control.record_auth("github.com", &auth);
+        control.record_auth("github.com", &auth);
+        control.record_auth("github.com", &ok);
+        control.record_auth("github.com", &auth);
+        control.record_auth("gitlab.com", &auth);
+        assert!(!control.host_failing("github.com"));
+        control.record_auth("github.com", &auth);
+        control.record_auth("github.com", &auth);
+        assert!(control.host_failing("github.com"));
+        assert!(!control.host_failing("gitlab.com"));
+        assert_eq!(control.failing_hosts(), ["github.com"]);
+        let status = host_skipped(Path::new("/src/a"), "github.com");
+        assert_eq!(status.message, "Skipped - credentials for github.com are not working");
+    }
+
+    #[test]
+    fn test_unreachable_host_skips_its_repos() {
+        let target = |host: &str| Target {
+            host: host.to_string(),
+            port: 443,
+            ip_version: None,
+        };
+        assert!(!reachable(&target("nonexistent.invalid")));
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let url = "https://nonexistent.invalid/a.git";
+        run_git(&clone_path, &["remote", "set-url", "origin", url]).unwrap();
+        let (host, _) = check_target(&clone_path, &UpdateOptions::default()).unwrap();
+        assert_eq!(host, "nonexistent.invalid");
+
+        let control = RunControl::default();
+        control.unreachable.lock().unwrap().insert(host, 1);
+        let repos = [clone_path];
+        let results = update_all(&pool(), &repos, &|_| UpdateOptions::default(), &control, &|_| {});
+        assert_eq!(results[0].bucket, Some(Bucket::Skipped));
+        assert_eq!(results[0].message, "Skipped - nonexistent.invalid is unreachable");
+        assert_eq!(control.unreachable_hosts(), [("nonexistent.invalid".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_check_hosts_goes_offline_when_nothing_is_reachable() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let repos = [clone_path.clone()];
+        let control = RunControl::default();
+        control.check_hosts(&pool(), &repos, &|_| UpdateOptions::default());
+        assert!(!control.is_offline()); // Local remotes have no host to check
+
+        let url = "https://nonexistent.invalid/a.git";
+        run_git(&clone_path, &["remote", "set-url", "origin", url]).unwrap();
+        control.check_hosts(&pool(), &repos, &|_| UpdateOptions::default());
+        let proxied = PROXY_VARS.iter().any(|name| std::env::var_os(name).is_some());
+        assert_eq!(control.is_offline(), !proxied); // A proxy leaves the host unchecked
+        assert!(control.unreachable_hosts().is_empty());
+
+        control.set_offline();
+        let results = update_all(&pool(), &repos, &|_| UpdateOptions::default(), &control, &|_| {});
+        assert_eq!(results[0].bucket, Some(Bucket::Offline));
+    }
+
+    #[test]
+    fn test_ip_version_ignored_outside_ssh() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let repos = [clone_path.clone()];
+        let v4 = |_: &Path| UpdateOptions {
+            ip_version: Some(IpVersion::V4),
+            ..Default::default()
+        };
+        assert!(ip_version_ignored(&pool(), &repos, &v4).is_empty()); // Local remote
+        run_git(&clone_path, &["remote", "set-url", "origin", "git@github.com:a/b.git"]).unwrap();
+        assert!(ip_version_ignored(&pool(), &repos, &v4).is_empty());
+        run_git(&clone_path, &["remote", "set-url", "origin", "https://github.com/a/b"]).unwrap();
+        assert_eq!(ip_version_ignored(&pool(), &repos, &v4), repos);
+        let either = |_: &Path| UpdateOptions::default();
+        assert!(ip_version_ignored(&pool(), &repos, &either).is_empty());
+    }
+
+    #[test]
+    fn test_scoped_proxies_leave_hosts_unchecked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        let url = gix::Url::try_from("https://ghe.example.com/org/repo.git").unwrap();
+        let configured = |remote| {
+            let repo = gix::open(&clone_path).unwrap();
+            proxy_configured(repo.config_snapshot().plumbing(), remote, &url)
+        };
+        assert!(!configured(Some("origin")));
+        let key = "http.https://github.com.proxy";
+        run_git(&clone_path, &["config", key, "http://proxy:3128"]).unwrap();
+        assert!(!configured(Some("origin")), "scoped to another host");
+        let key = "http.https://ghe.example.com.proxy";
+        run_git(&clone_path, &["config", key, "http://proxy:3128"]).unwrap();
+        assert!(configured(Some("origin")));
+        run_git(&clone_path, &["config", "--unset", key]).unwrap();
+        run_git(&clone_path, &["config", "remote.origin.proxy", "http://proxy:3128"]).unwrap();
+        assert!(configured(Some("origin")));
+        assert!(!configured(Some("upstream")));
+    }
+
+    #[test]
+    fn test_remote_host() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (_, clone_path) = init_remote_and_clone(tmp.path());
+        assert_eq!(remote_host(&clone_path, &UpdateOptions::default()), None);
+        run_git(&clone_path, &["remote", "set-url", "origin", "git@github.com:a/b.git"]).unwrap();
+        let host = remote_host(&clone_path, &UpdateOptions::default());
+        assert_eq!(host.as_deref(), Some("github.com"));
+    }
+
+    #[test]
+    fn test_slowest_first() {
+        let mut times = UpdateTimes::default();
+        times.record(Path::new("/quick"), Duration::from_millis(10));
+        times.record(Path::new("/mono"), Duration::from_secs(90));
+        times.record(Path::new("/medium"), Duration::from_secs(2));
+        let mut repos: Vec<PathBuf> =
+            ["/quick", "/mono", "/new", "/medium"].into_iter().map(PathBuf::from).collect();
+        slowest_first(&mut repos, &times);
+        let order: Vec<&str> = repos.iter().map(|repo| repo.to_str().unwrap()).collect();
+        assert_eq!(order, ["/new", "/mono", "/medium", "/quick"]);
+    }
+
+    #[test]
+    fn test_pause_holds_workers_until_resumed() {
+        let control = RunControl::default();
+        assert!(control.set_paused(true));
+        assert!(!control.set_paused(true));
+        std::thread::scope(|s| {
+            let waiter = s.spawn(|| control.wait_while_paused());
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!waiter.is_finished());
+            assert!(control.set_paused(false));
+        });
+        assert!(!control.is_paused());
+    }
+
+    #[test]
+    fn test_adaptive_jobs_back_off_and_grow() {
+        let control = RunControl::default();
+        assert_eq!(control.jobs_limit(), None);
+        control.adapt_jobs(8);
+        assert_eq!(control.jobs_limit(), Some(4));
+
+        let finish = |status: &RepoStatus| {
+            control.acquire_slot();
+            control.release_slot(status);
+        };
+        let failed = RepoStatus {
+            success: false,
+            error: Some(ErrorKind::Network),
+            ..Default::default()
+        };
+        finish(&failed);
+        assert_eq!(control.jobs_limit(), Some(2));
+        finish(&failed);
+        finish(&failed);
+        assert_eq!(control.jobs_limit(), Some(1));
+
+        let ok = RepoStatus { success: true, ..Default::default() };
+        finish(&ok);
+        assert_eq!(control.jobs_limit(), Some(2));
+        finish(&ok);
+        assert_eq!(control.jobs_limit(), Some(2));
+        finish(&ok);
+        assert_eq!(control.jobs_limit(), Some(3));
+    }
+
+    #[test]
+    fn test_adaptive_jobs_hold_workers_at_the_limit() {
+        let control = RunControl::default();
+        control.adapt_jobs(1);
+        control.acquire_slot();
+        std::thread::scope(|s| {
+            let waiter = s.spawn(|| control.acquire_slot());
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!waiter.is_finished());
+            control.release_slot(&RepoStatus { success: true, ..Default::default() });
+        });
+    }
+
+    #[test]
+    fn test_abort_releases_paused_workers() {
+        let control = RunControl::default();
+        control.set_paused(true);
+        control.abort();
+        control.wait_while_paused();
+    }
+}
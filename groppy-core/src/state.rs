@@ -0,0 +1,390 @@
+//! State kept between runs in `$XDG_STATE_HOME/groppy` (else
+//! `~/.local/state/groppy`): when each repository was last fetched, how long
+//! its updates usually take, which commits recent runs pulled, and the last
+//! forge listings.
+//!
+//! Several groppy processes may run at once (e.g. from shell hooks), so saves
+//! merge with what is on disk and replace the file atomically.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::status::PulledCommit;
+
+/// File in the state dir holding the fetch times, one `<unix secs>\t<path>` per line.
+const FETCH_LOG: &str = "last-fetch.tsv";
+
+/// File in the state dir holding update times, one `<millis>\t<path>` per line.
+const UPDATE_TIMES: &str = "update-times.tsv";
+
+/// File in the state dir holding pulled commits, one
+/// `<unix secs>\t<commit id>\t<summary>\t<path>` per line.
+const PULL_LOG: &str = "pulled.tsv";
+
+/// File in the state dir holding forge listing pages, as JSON.
+const LISTINGS: &str = "listings.json";
+
+/// How long pulled commits are kept for `groppy changelog`.
+const PULL_LOG_KEEP: Duration = Duration::from_secs(90 * 86_400);
+
+/// Directory for groppy's state, following the XDG base directory spec.
+pub fn state_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = std::env::var_os("HOME").filter(|v| !v.is_empty())?;
+            Some(PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(base.join("groppy"))
+}
+
+/// When each repository was last fetched successfully.
+#[derive(Default)]
+pub struct FetchLog {
+    fetched: BTreeMap<PathBuf, u64>, // Repo path → unix seconds
+}
+
+impl FetchLog {
+    /// Reads the log from the state dir; missing or unreadable lines count as
+    /// never fetched.
+    pub fn load(dir: &Path) -> FetchLog {
+        let text = std::fs::read_to_string(dir.join(FETCH_LOG)).unwrap_or_default();
+        FetchLog::parse(&text)
+    }
+
+    fn parse(text: &str) -> FetchLog {
+        let fetched = text
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(secs, path)| Some((PathBuf::from(path), secs.parse().ok()?)))
+            .collect();
+        FetchLog { fetched }
+    }
+
+    /// When `repo` was last fetched, if ever.
+    pub fn last_fetched(&self, repo: &Path) -> Option<SystemTime> {
+        let secs = *self.fetched.get(repo)?;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Records a fetch of `repo` at `when`.
+    pub fn record(&mut self, repo: &Path, when: SystemTime) {
+        let secs = when.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.fetched.insert(repo.to_path_buf(), secs);
+    }
+
+    /// Writes the log, keeping the newer time for repos another run recorded
+    /// since this one loaded it.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let mut merged = FetchLog::load(dir).fetched;
+        for (path, &secs) in &self.fetched {
+            let entry = merged.entry(path.clone()).or_default();
+            *entry = (*entry).max(secs);
+        }
+        let text: String = merged
+            .iter()
+            .map(|(path, secs)| format!("{secs}\t{}\n", path.display()))
+            .collect();
+        write_atomically(dir, FETCH_LOG, &text)
+    }
+}
+
+/// How long updating each repository usually takes, averaged over runs with
+/// the latest run weighted as heavily as all earlier ones.
+#[derive(Default)]
+pub struct UpdateTimes {
+    millis: BTreeMap<PathBuf, u64>,   // Repo path → smoothed update time
+    recorded: BTreeMap<PathBuf, u64>, // Times measured by this run, not yet saved
+}
+
+impl UpdateTimes {
+    /// Reads the times from the state dir; unreadable lines count as unknown.
+    pub fn load(dir: &Path) -> UpdateTimes {
+        let text = std::fs::read_to_string(dir.join(UPDATE_TIMES)).unwrap_or_default();
+        UpdateTimes::parse(&text)
+    }
+
+    fn parse(text: &str) -> UpdateTimes {
+        let millis = text
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(millis, path)| Some((PathBuf::from(path), millis.parse().ok()?)))
+            .collect();
+        UpdateTimes { millis, recorded: BTreeMap::new() }
+    }
+
+    /// The usual update time of `repo`, if it was ever measured.
+    pub fn typical(&self, repo: &Path) -> Option<Duration> {
+        self.millis.get(repo).map(|&millis| Duration::from_millis(millis))
+    }
+
+    /// Records that updating `repo` took `took`.
+    pub fn record(&mut self, repo: &Path, took: Duration) {
+        let took = u64::try_from(took.as_millis()).unwrap_or(u64::MAX);
+        let smoothed = match self.millis.get(repo) {
+            Some(&before) => before / 2 + took / 2,
+            None => took,
+        };
+        self.millis.insert(repo.to_path_buf(), smoothed);
+        self.recorded.insert(repo.to_path_buf(), smoothed);
+    }
+
+    /// Writes the times, replacing only those this run recorded so times saved
+    /// by another run since this one loaded are kept.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let mut merged = UpdateTimes::load(dir).millis;
+        merged.extend(self.recorded.iter().map(|(path, &millis)| (path.clone(), millis)));
+        let text: String = merged
+            .iter()
+            .map(|(path, millis)| format!("{millis}\t{}\n", path.display()))
+            .collect();
+        write_atomically(dir, UPDATE_TIMES, &text)
+    }
+}
+
+/// One commit a run pulled into a repository.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pull {
+    pub at: u64,         // Unix seconds the run recorded it
+    pub repo: PathBuf,   // Repository it was pulled into
+    pub id: String,      // Full hex commit id
+    pub summary: String, // First line of the commit message
+}
+
+/// The commits recent runs pulled, oldest run first, for `groppy changelog`.
+#[derive(Default)]
+pub struct PullLog {
+    pulls: Vec<Pull>,
+    recorded: Vec<Pull>, // Pulled by this run, not yet saved
+}
+
+impl PullLog {
+    /// Reads the log from the state dir; unreadable lines are left out.
+    pub fn load(dir: &Path) -> PullLog {
+        let text = std::fs::read_to_string(dir.join(PULL_LOG)).unwrap_or_default();
+        PullLog::parse(&text)
+    }
+
+    fn parse(text: &str) -> PullLog {
+        let pulls = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                let at = fields.next()?.parse().ok()?;
+                let id = fields.next()?.to_string();
+                let summary = fields.next()?.to_string();
+                let repo = PathBuf::from(fields.next()?);
+                Some(Pull { at, repo, id, summary })
+            })
+            .collect();
+        PullLog { pulls, recorded: Vec::new() }
+    }
+
+    /// Records that a run at `when` pulled `commits` into `repo`.
+    pub fn record(&mut self, repo: &Path, commits: &[PulledCommit], when: SystemTime) {
+        let at = when.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        for commit in commits {
+            let pull = Pull {
+                at,
+                repo: repo.to_path_buf(),
+                id: commit.id.to_string(),
+                summary: commit.summary.replace(['\t', '\n', '\r'], " "),
+            };
+            self.pulls.push(pull.clone());
+            self.recorded.push(pull);
+        }
+    }
+
+    /// When the latest run that pulled anything recorded its commits.
+    pub fn last_run(&self) -> Option<u64> {
+        self.pulls.iter().map(|pull| pull.at).max()
+    }
+
+    /// The commits pulled at or after `secs`, oldest run first.
+    pub fn since(&self, secs: u64) -> Vec<&Pull> {
+        self.pulls.iter().filter(|pull| pull.at >= secs).collect()
+    }
+
+    /// Appends this run's commits to what is on disk, dropping those older
+    /// than 90 days.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        if self.recorded.is_empty() {
+            return Ok(());
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let cutoff = now.saturating_sub(PULL_LOG_KEEP.as_secs());
+        let on_disk = PullLog::load(dir).pulls;
+        let text: String = on_disk
+            .iter()
+            .chain(&self.recorded)
+            .filter(|pull| pull.at >= cutoff)
+            .map(|pull| {
+                format!("{}\t{}\t{}\t{}\n", pull.at, pull.id, pull.summary, pull.repo.display())
+            })
+            .collect();
+        write_atomically(dir, PULL_LOG, &text)
+    }
+}
+
+/// One page of a forge listing as last received, for a conditional request
+/// (`If-None-Match`) the next time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CachedPage {
+    pub url: String,
+    pub etag: String,         // Empty when the forge sent none
+    pub next: Option<String>, // URL of the page after it
+    pub body: String,
+}
+
+/// The pages of each forge listing, keyed by the URL of its first page.
+#[derive(Default)]
+pub struct ListingCache {
+    listings: BTreeMap<String, Vec<CachedPage>>,
+}
+
+impl ListingCache {
+    /// Reads the cache from the state dir; a missing or unreadable file is an
+    /// empty cache.
+    pub fn load(dir: &Path) -> ListingCache {
+        let text = std::fs::read_to_string(dir.join(LISTINGS)).unwrap_or_default();
+        let listings = serde_json::from_str(&text).unwrap_or_default();
+        ListingCache { listings }
+    }
+
+    /// The pages cached for the listing starting at `first`.
+    pub fn pages(&self, first: &str) -> &[CachedPage] {
+        self.listings.get(first).map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Replaces the pages of the listing starting at `first`, keeping listings
+    /// another run saved since.
+    pub fn store(dir: &Path, first: &str, pages: Vec<CachedPage>) -> Result<()> {
+        let mut merged = ListingCache::load(dir).listings;
+        merged.insert(first.to_string(), pages);
+        write_atomically(dir, LISTINGS, &serde_json::to_string(&merged)?)
+    }
+}
+
+/// Replaces `name` in the state dir with `text` via a per-process temp file.
+fn write_atomically(dir: &Path, name: &str, text: &str) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("cannot create state dir {}", dir.display()))?;
+    let tmp = dir.join(format!("{name}.{}", std::process::id()));
+    std::fs::write(&tmp, text).with_context(|| format!("cannot write {}", tmp.display()))?;
+    std::fs::rename(&tmp, dir.join(name))
+        .with_context(|| format!("cannot replace {}", dir.join(name).display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_log_round_trip_keeps_newest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Path::new("/src/app");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        let mut first = FetchLog::load(tmp.path());
+        assert!(first.last_fetched(repo).is_none());
+        first.record(repo, at(200));
+        first.save(tmp.path()).unwrap();
+
+        let mut stale = FetchLog::default();
+        stale.record(repo, at(100));
+        stale.record(Path::new("/src/lib"), at(50));
+        stale.save(tmp.path()).unwrap();
+
+        let log = FetchLog::load(tmp.path());
+        assert_eq!(log.last_fetched(repo), Some(at(200)));
+        assert_eq!(log.last_fetched(Path::new("/src/lib")), Some(at(50)));
+    }
+
+    #[test]
+    fn test_fetch_log_ignores_bad_lines() {
+        let log = FetchLog::parse("garbage\nnot-a-number\t/src/a\n30\t/src/b\n");
+        assert!(log.last_fetched(Path::new("/src/a")).is_none());
+        let b = log.last_fetched(Path::new("/src/b"));
+        assert_eq!(b, Some(UNIX_EPOCH + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_update_times_smooth_and_keep_other_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = Path::new("/src/app");
+
+        let mut times = UpdateTimes::load(tmp.path());
+        assert!(times.typical(repo).is_none());
+        times.record(repo, Duration::from_millis(4000));
+        times.save(tmp.path()).unwrap();
+
+        let mut other = UpdateTimes::default();
+        other.record(Path::new("/src/lib"), Duration::from_millis(300));
+        let mut times = UpdateTimes::load(tmp.path());
+        other.save(tmp.path()).unwrap();
+        times.record(repo, Duration::from_millis(2000));
+        times.save(tmp.path()).unwrap();
+
+        let times = UpdateTimes::load(tmp.path());
+        assert_eq!(times.typical(repo), Some(Duration::from_millis(3000)));
+        assert_eq!(times.typical(Path::new("/src/lib")), Some(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_listing_cache_replaces_one_listing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let page = |url: &str, etag: &str| CachedPage {
+            url: url.to_string(),
+            etag: etag.to_string(),
+            body: "[]".to_string(),
+            ..Default::default()
+        };
+        assert!(ListingCache::load(tmp.path()).pages("https://a/1").is_empty());
+        ListingCache::store(tmp.path(), "https://a/1", vec![page("https://a/1", "\"x\"")]).unwrap();
+        ListingCache::store(tmp.path(), "https://b/1", vec![page("https://b/1", "")]).unwrap();
+        ListingCache::store(tmp.path(), "https://a/1", vec![page("https://a/1", "\"y\"")]).unwrap();
+
+        let cache = ListingCache::load(tmp.path());
+        assert_eq!(cache.pages("https://a/1"), [page("https://a/1", "\"y\"")]);
+        assert_eq!(cache.pages("https://b/1").len(), 1);
+        std::fs::write(tmp.path().join(LISTINGS), "{not json").unwrap();
+        assert!(ListingCache::load(tmp.path()).pages("https://a/1").is_empty());
+    }
+
+    #[test]
+    fn test_pull_log_appends_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let commit = |digit: &str, summary: &str| PulledCommit {
+            id: gix::ObjectId::from_hex(digit.repeat(40).as_bytes()).unwrap(),
+            summary: summary.to_string(),
+        };
+
+        let mut old = PullLog::default();
+        old.record(Path::new("/src/gone"), &[commit("1", "ancient")], at(100));
+        old.save(tmp.path()).unwrap();
+        let mut first = PullLog::load(tmp.path());
+        assert!(first.last_run().is_none());
+        first.record(Path::new("/src/app"), &[commit("2", "fix\tparser")], at(now - 60));
+        first.save(tmp.path()).unwrap();
+        let mut second = PullLog::load(tmp.path());
+        second.record(Path::new("/src/lib"), &[commit("3", "add api")], at(now));
+        second.save(tmp.path()).unwrap();
+
+        let log = PullLog::load(tmp.path());
+        assert_eq!(log.last_run(), Some(now));
+        let summaries = |pulls: Vec<&Pull>| -> Vec<String> {
+            pulls.iter().map(|pull| pull.summary.clone()).collect()
+        };
+        assert_eq!(summaries(log.since(0)), ["fix parser", "add api"]);
+        assert_eq!(summaries(log.since(now)), ["add api"]);
+        assert_eq!(log.since(now)[0].repo, Path::new("/src/lib"));
+        assert_eq!(log.since(now)[0].id, "3".repeat(40));
+    }
+}
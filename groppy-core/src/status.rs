@@ -0,0 +1,260 @@
+//! Per-repository results: what an update did, and how the summary groups it.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Represents the outcome of updating a single Git repository.
+/// Contains all information needed to display the result to the user.
+#[derive(Default)]
+pub struct RepoStatus {
+    pub path: PathBuf,       //  Absolute filesystem path to the repository
+    pub success: bool,       // Whether the update operation succeeded
+    pub message: String,     // Human-readable description of what happened
+    pub files_changed: usize, // Number of files modified by the update
+    pub bucket: Option<Bucket>, // Summary category for repos that were neither updated nor failed
+    pub old_id: Option<gix::ObjectId>, // HEAD before the update, once known
+    pub new_id: Option<gix::ObjectId>, // HEAD after a successful move
+    pub commits: Vec<PulledCommit>, // Commits HEAD moved over, newest first; see commit_count
+    pub commits_omitted: usize, // Older commits moved over but not listed (MAX_PULLED_COMMITS)
+    pub duration: Duration,  // Wall time spent on this repo
+    pub bytes_received: usize, // Pack bytes fetched for this repo, even if the update failed
+    pub error: Option<ErrorKind>, // Why the update failed, when known; see error_kind
+    pub ahead_behind: Option<(usize, usize)>, // Commits HEAD has / lacks vs upstream, afterwards
+    pub head: Option<HeadCommit>, // Commit HEAD moved to, with --show-head
+    pub alternates: Vec<PathBuf>, // Repos whose object stores this one borrows (alternates)
+}
+
+/// One commit brought in by an update, for reports.
+pub struct PulledCommit {
+    pub id: gix::ObjectId,
+    pub summary: String, // First line of the commit message
+}
+
+/// The commit an update left HEAD on, for `--show-head`.
+pub struct HeadCommit {
+    pub id: gix::ObjectId,
+    pub author: String, // Author name
+    pub seconds: i64,   // Commit time, Unix seconds
+}
+
+impl RepoStatus {
+    /// Short outcome word used by reports: the bucket label, `failed`,
+    /// `updated` when HEAD moved, else `ok`.
+    pub fn outcome(&self) -> &'static str {
+        match self.bucket {
+            Some(bucket) => bucket.label(),
+            None if !self.success => "failed",
+            None if self.new_id.is_some() && self.new_id != self.old_id => "updated",
+            None => "ok",
+        }
+    }
+
+    /// How many commits HEAD moved over, including those beyond the listed
+    /// `commits`.
+    pub fn commit_count(&self) -> usize {
+        self.commits.len() + self.commits_omitted
+    }
+
+    /// `ahead N, behind M` (zero counts left out) when HEAD and its upstream
+    /// differ, else None.
+    pub fn divergence(&self) -> Option<String> {
+        let parts: Vec<String> = match self.ahead_behind? {
+            (0, 0) => return None,
+            (ahead, behind) => [("ahead", ahead), ("behind", behind)]
+                .into_iter()
+                .filter(|&(_, n)| n > 0)
+                .map(|(label, n)| format!("{label} {n}"))
+                .collect(),
+        };
+        Some(parts.join(", "))
+    }
+
+    /// Failure category for the summary: None unless the repo failed,
+    /// `Other` when the failure was not classified.
+    pub fn error_kind(&self) -> Option<ErrorKind> {
+        match self.success || self.bucket.is_some() {
+            true => None,
+            false => Some(self.error.unwrap_or(ErrorKind::Other)),
+        }
+    }
+}
+
+/// Summary categories listed separately from ok/fail, e.g. repos with no upstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bucket {
+    NoUpstream, // HEAD's branch has no upstream configured
+    NoRemote,   // No remotes, or none matching the requested/default one
+    Skipped,    // Never started: the run was aborted or hit --max-errors
+    Dirty,      // Local changes, left alone by --unclean skip
+    Recent,     // Fetched within --min-age; not fetched again
+    Offline,    // Not fetched: offline run, state as of the last fetch
+}
+
+impl Bucket {
+    /// Every bucket, in the order the summary lists them.
+    pub const ALL: [Bucket; 6] = [
+        Bucket::NoUpstream,
+        Bucket::NoRemote,
+        Bucket::Dirty,
+        Bucket::Recent,
+        Bucket::Offline,
+        Bucket::Skipped,
+    ];
+
+    /// Label used in the summary line and the repo lists below it.
+    pub fn label(self) -> &'static str {
+        match self {
+            Bucket::NoUpstream => "no upstream",
+            Bucket::NoRemote => "no remote",
+            Bucket::Skipped => "skipped",
+            Bucket::Dirty => "dirty",
+            Bucket::Recent => "recent",
+            Bucket::Offline => "offline",
+        }
+    }
+}
+
+/// Failure categories, so the summary can group e.g. every auth failure together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Auth,     // Credentials rejected or missing, host key not trusted
+    Network,  // Host unreachable, DNS failure, timeout, dropped connection
+    Diverged, // Local and upstream history both moved
+    Conflict, // Local changes in the way of the update
+    Corrupt,  // Repository or object database cannot be read
+    Other,
+}
+
+impl ErrorKind {
+    /// Every kind, in the order the summary lists them.
+    pub const ALL: [ErrorKind; 6] = [
+        ErrorKind::Auth,
+        ErrorKind::Network,
+        ErrorKind::Diverged,
+        ErrorKind::Conflict,
+        ErrorKind::Corrupt,
+        ErrorKind::Other,
+    ];
+
+    /// Label used in the summary's failure lists.
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Auth => "authentication",
+            ErrorKind::Network => "network",
+            ErrorKind::Diverged => "diverged",
+            ErrorKind::Conflict => "conflict",
+            ErrorKind::Corrupt => "corrupt repo",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// Classifies an update error by the I/O and gix errors in its chain, then by
+    /// the wording of transport and git messages (ssh and git only report text).
+    pub fn of(error: &anyhow::Error) -> ErrorKind {
+        use std::io::ErrorKind as Io;
+
+        for cause in error.chain() {
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                match io.kind() {
+                    Io::TimedOut
+                    | Io::ConnectionRefused
+                    | Io::ConnectionReset
+                    | Io::ConnectionAborted
+                    | Io::NotConnected
+                    | Io::HostUnreachable
+                    | Io::NetworkUnreachable => return ErrorKind::Network,
+                    Io::PermissionDenied => return ErrorKind::Auth,
+                    _ => {}
+                }
+            }
+            if cause.is::<gix::open::Error>()
+                || cause.is::<gix::discover::Error>()
+                || cause.is::<gix::object::find::existing::Error>()
+                || cause.is::<gix::refs::file::loose::reference::decode::Error>()
+            {
+                return ErrorKind::Corrupt;
+            }
+        }
+
+        let text = format!("{error:#}").to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|needle| text.contains(needle));
+        if has(&[
+            "authentication",
+            "permission denied",
+            "could not read username",
+            "credentials",
+            "host key verification failed",
+            "401 unauthorized",
+            "403 forbidden",
+        ]) || http_status(&text, &["401", "403"])
+        {
+            ErrorKind::Auth
+        } else if has(&[
+            "timed out",
+            "could not resolve",
+            "failed to resolve",
+            "connection refused",
+            "connection reset",
+            "network is unreachable",
+            "no route to host",
+            "failed to connect",
+            "too many requests",
+        ]) || http_status(&text, &["429"])
+        {
+            ErrorKind::Network
+        } else if has(&["corrupt", "bad object", "not a git repository", "packfile"]) {
+            ErrorKind::Corrupt
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// Whether lowercased `text` reports one of the HTTP status `codes` as such
+/// ("status 403", "http 403", "error: 403"), not just digits in an id or path.
+fn http_status(text: &str, codes: &[&str]) -> bool {
+    let prefixes = ["status ", "status code ", "status: ", "http ", "http/1.1 ", "error: "];
+    codes
+        .iter()
+        .any(|code| prefixes.iter().any(|prefix| text.contains(&format!("{prefix}{code}"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divergence() {
+        let status = |ahead_behind| RepoStatus {
+            ahead_behind,
+            ..Default::default()
+        };
+        assert_eq!(status(None).divergence(), None);
+        assert_eq!(status(Some((0, 0))).divergence(), None);
+        assert_eq!(status(Some((2, 0))).divergence().as_deref(), Some("ahead 2"));
+        assert_eq!(status(Some((1, 3))).divergence().as_deref(), Some("ahead 1, behind 3"));
+    }
+
+    #[test]
+    fn test_error_kind_of_io_errors() {
+        let timeout = std::io::Error::new(std::io::ErrorKind::TimedOut, "slow");
+        let err = anyhow::Error::new(timeout).context("fetch failed");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Network);
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(ErrorKind::of(&denied.into()), ErrorKind::Auth);
+    }
+
+    #[test]
+    fn test_error_kind_of_messages() {
+        let kind = |msg: &str| ErrorKind::of(&anyhow::anyhow!("{msg}"));
+        assert_eq!(kind("git@host: Permission denied (publickey)."), ErrorKind::Auth);
+        assert_eq!(kind("HTTP status 403 Forbidden"), ErrorKind::Auth);
+        assert_eq!(kind("Could not resolve host: example.invalid"), ErrorKind::Network);
+        assert_eq!(kind("HTTP 429 Too Many Requests"), ErrorKind::Network);
+        assert_eq!(kind("The requested URL returned error: 401"), ErrorKind::Auth);
+        assert_eq!(kind("cannot check out /src/app-403/lib"), ErrorKind::Other);
+        assert_eq!(kind("reference points to missing object 94290a1"), ErrorKind::Other);
+        assert_eq!(kind("object 1234 is corrupt"), ErrorKind::Corrupt);
+        assert_eq!(kind("Tag v9 not found on remote"), ErrorKind::Other);
+    }
+}
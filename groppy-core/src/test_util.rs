@@ -0,0 +1,79 @@
+//! Git fixtures shared by the unit tests: repos, bare remotes, clones.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Helper: init a git repo with one commit via git CLI
+pub fn init_repo_with_commit(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    Command::new("git")
+        .args(["init"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    configure_identity(path);
+    fs::write(path.join("README.md"), "# test\n").unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "initial"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+}
+
+// Helper: set a committer identity so commits and reflog writes work without global config
+pub fn configure_identity(path: &Path) {
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.name", "test"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+}
+
+// Helper: init a bare repo
+pub fn init_bare_repo(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    Command::new("git")
+        .args(["init", "--bare"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+}
+
+// Helper: bare remote at <tmp>/remote.git, pushed from <tmp>/work, cloned to <tmp>/clone.
+// Returns (work, clone).
+pub fn init_remote_and_clone(tmp: &Path) -> (PathBuf, PathBuf) {
+    let bare_path = tmp.join("remote.git");
+    let work_path = tmp.join("work");
+    let clone_path = tmp.join("clone");
+    init_bare_repo(&bare_path);
+    init_repo_with_commit(&work_path);
+    git(&work_path, &["remote", "add", "origin", bare_path.to_str().unwrap()]);
+    git(&work_path, &["push", "-u", "origin", "HEAD"]);
+    git(tmp, &["clone", bare_path.to_str().unwrap(), clone_path.to_str().unwrap()]);
+    configure_identity(&clone_path);
+    (work_path, clone_path)
+}
+
+// Helper: commit a new file in `work` and push it to its upstream
+pub fn push_new_file(work: &Path, name: &str) {
+    fs::write(work.join(name), "new content").unwrap();
+    git(work, &["add", "."]);
+    git(work, &["commit", "-m", name]);
+    git(work, &["push"]);
+}
+
+// Helper: run a git command in `dir`, ignoring the result
+pub fn git(dir: &Path, args: &[&str]) {
+    Command::new("git").args(args).current_dir(dir).output().unwrap();
+}
@@ -3,6 +3,7 @@
 //! This tool scans directories for Git repositories and updates them concurrently
 //! using gitoxide (gix) for fetch operations and rayon for parallelism. It mirrors
 //! the functionality of goppy (the Go version) with the same CLI interface and TUI.
+//! The engine lives in the `groppy-core` crate; this binary is its terminal front end.
 //!
 //! # Usage
 //!
@@ -13,16 +14,37 @@
 //! groppy -j 8                 # Use 8 parallel jobs
 //! ```
 
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use crossterm::style::{Color, Stylize};
-use gix::bstr::ByteSlice;
+use groppy_core::config::Strategy;
+use groppy_core::discovery::{self, DEFAULT_EXCLUDES, ScanOptions};
+use groppy_core::forge::{
+    self, AzureDevOps, Bitbucket, BitbucketAuth, CloneProtocol, Forge, GitHub, Gitea, Gitolite,
+};
+use groppy_core::hooks::Hooks;
+use groppy_core::import::{self, ImportFormat};
+use groppy_core::manifest::{self, RepoManifest};
+use groppy_core::netrc::Netrc;
+use groppy_core::plan::{self, PlanAction, PlannedRepo};
+use groppy_core::run::{self, Event, RunControl};
+use groppy_core::state::{self, FetchLog, PullLog, UpdateTimes};
+use groppy_core::update::run_git;
+use groppy_core::{
+    AskCredentials, AskDirty, Bucket, DirtyAction, ErrorKind, Interaction, IpVersion, RepoStatus,
+    Unclean, UpdateOptions, config, gix,
+};
+
+mod report;
+
+use report::{LineTemplate, ReportKind, ReportTarget};
 
 // Catppuccin Mocha color palette constants
 // These define the RGB values used for terminal output styling
@@ -84,27 +106,605 @@ const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦
 /// Command-line interface definition using clap derive macros.
 /// Accepts optional directories, job count, and verbose flag.
 #[derive(Parser)]
-#[command(name = "groppy", about = "Parallel Git repository updater (Rust + gitoxide)", version)]
+#[command(
+    name = "groppy",
+    about = "Parallel Git repository updater (Rust + gitoxide)",
+    version,
+    args_conflicts_with_subcommands = true
+)]
 struct Cli {
-    /// Directories to scan for Git repositories (defaults to current directory)
+    /// Directories to scan for Git repositories, on top of the config's `roots`
+    /// (default: the roots, else the current directory)
     directories: Vec<PathBuf>,
 
-    /// Number of parallel jobs for concurrent repo updates
-    #[arg(short = 'j', long = "jobs", default_value_t = 4)]
-    jobs: usize,
+    /// Scan only the directories given, not the config's `roots` as well
+    #[arg(long = "only-args")]
+    only_args: bool,
+
+    /// Also scan DIR this run, on top of whatever would be scanned without it
+    /// (the roots or `.`, the given directories, or none with a repo list)
+    #[arg(long = "add", value_name = "DIR")]
+    add: Vec<PathBuf>,
+
+    /// Number of parallel jobs for concurrent repo updates (0 or omitted = automatic)
+    #[arg(short = 'j', long = "jobs")]
+    jobs: Option<usize>,
+
+    /// Treat --jobs as a ceiling: start half as many repos at once, add one as
+    /// updates succeed, halve on each network failure (e.g. throttling)
+    #[arg(long = "adaptive-jobs")]
+    adaptive_jobs: bool,
 
     /// Whether to show verbose output including unchanged repos
     #[arg(short = 'v', long = "verbose")]
     verbose: bool,
+
+    /// Print a dimmed line for every repo that was already current or left
+    /// alone, tagged with its category, and count unchanged repos in the summary
+    #[arg(long = "show-skipped")]
+    show_skipped: bool,
+
+    /// Follow symlinked directories while scanning (each target is visited once)
+    #[arg(short = 'L', long = "follow-symlinks")]
+    follow_symlinks: bool,
+
+    /// Scan dot-directories below the roots, e.g. `~/.config/nvim` under `~` (default)
+    #[arg(long = "hidden", overrides_with = "no_hidden")]
+    hidden: bool,
+
+    /// Skip dot-directories below the roots; roots themselves are always scanned
+    #[arg(long = "no-hidden", overrides_with = "hidden")]
+    no_hidden: bool,
+
+    /// Walk the directories even if a cached scan of them looks current
+    /// (scans are cached in `~/.local/state/groppy`)
+    #[arg(long = "rescan")]
+    rescan: bool,
+
+    /// Read repository paths, one per line, from a file (`-` for stdin) instead of scanning
+    #[arg(short = 'f', long = "from-file", value_name = "PATH")]
+    from_file: Option<PathBuf>,
+
+    /// Gitea or Forgejo instance to list an organization's repos from, e.g.
+    /// `https://codeberg.org`; missing ones are cloned, all are updated. Token from
+    /// `GITEA_TOKEN`
+    #[arg(long = "gitea-url", value_name = "URL", requires = "gitea_org")]
+    gitea_url: Option<String>,
+
+    /// File holding the access token for the forge flags (e.g. a systemd
+    /// credential or Kubernetes secret); replaces their environment variables
+    #[arg(long = "token-file", value_name = "PATH")]
+    token_file: Option<PathBuf>,
+
+    /// Organization to list with --gitea-url
+    #[arg(long = "gitea-org", value_name = "ORG", requires = "gitea_url")]
+    gitea_org: Option<String>,
+
+    /// GitHub organization to list repos from; missing ones are cloned, all are
+    /// updated. Token from `GITHUB_TOKEN`, else `GH_TOKEN`
+    #[arg(long = "github-org", value_name = "ORG")]
+    github_org: Option<String>,
+
+    /// Bitbucket Cloud workspace to list repos from; missing ones are cloned, all are
+    /// updated. Auth from `BITBUCKET_USERNAME` + `BITBUCKET_APP_PASSWORD`, or
+    /// `BITBUCKET_TOKEN`
+    #[arg(long = "bitbucket-workspace", value_name = "WORKSPACE")]
+    bitbucket_workspace: Option<String>,
+
+    /// Azure DevOps project to list repos from, as `ORG/PROJECT` or a project URL;
+    /// missing ones are cloned, all are updated. PAT from `AZURE_DEVOPS_PAT`
+    #[arg(long = "azure-project", value_name = "ORG/PROJECT", value_parser = parse_azure_project)]
+    azure_project: Option<(String, String)>,
+
+    /// Gitolite server to list repos from with `ssh USER@HOST info`; missing ones are
+    /// cloned over SSH, all are updated
+    #[arg(long = "gitolite", value_name = "USER@HOST")]
+    gitolite: Option<String>,
+
+    /// Google `repo` manifest (`manifest.xml`) whose projects to clone and update,
+    /// laid out under --clone-into as in `repo sync`
+    #[arg(long = "manifest", value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Move repos under --clone-into that the --manifest no longer lists into DIR
+    /// (same relative paths, never deleted); without it they are only reported
+    #[arg(long = "archive-extraneous", value_name = "DIR", requires = "manifest")]
+    archive_extraneous: Option<PathBuf>,
+
+    /// Directory forge repositories are cloned into, one subdirectory per repo
+    #[arg(long = "clone-into", value_name = "DIR", default_value = ".")]
+    clone_into: PathBuf,
+
+    /// Point `origin` of forge, manifest and config repos at their listed URL when
+    /// it differs (moved or renamed projects) before fetching; otherwise reported
+    #[arg(long = "fix-remotes")]
+    fix_remotes: bool,
+
+    /// Clone forge repositories over `https` or `ssh`
+    #[arg(
+        long = "clone-protocol",
+        value_name = "PROTOCOL",
+        value_parser = parse_clone_protocol,
+        default_value = "https"
+    )]
+    clone_protocol: CloneProtocol,
+
+    /// How many directory levels below each root to search for repositories
+    /// (a config `roots` entry's `depth` or `recursive` wins for its root)
+    #[arg(short = 'd', long = "max-depth", default_value_t = 1)]
+    max_depth: usize,
+
+    /// Additional directory name to skip while scanning (repeatable)
+    #[arg(short = 'x', long = "exclude", value_name = "NAME")]
+    exclude: Vec<String>,
+
+    /// Don't skip the built-in list of vendored/build directories
+    #[arg(long = "no-default-excludes")]
+    no_default_excludes: bool,
+
+    /// Remote to fetch from for repos without a per-repo `remote` in the config
+    #[arg(short = 'r', long = "remote", value_name = "NAME")]
+    remote: Option<String>,
+
+    /// Additional fetch refspec, e.g. `+refs/pull/*/head:refs/remotes/origin/pr/*` (repeatable)
+    #[arg(long = "refspec", value_name = "SPEC")]
+    refspec: Vec<String>,
+
+    /// Run git's checkout, merge, and rebase steps with no repository hooks
+    /// (post-checkout, post-merge, ...), e.g. ones that start heavy tooling
+    #[arg(long = "no-hooks")]
+    no_hooks: bool,
+
+    /// Shell command run in each repo after its update, with GROPPY_REPO,
+    /// GROPPY_BRANCH, GROPPY_OLD_SHA, GROPPY_NEW_SHA and GROPPY_RESULT set
+    #[arg(long = "exec", value_name = "CMD")]
+    exec: Option<String>,
+
+    /// Also fetch `refs/notes/*` (e.g. review metadata); config `fetch_notes` can
+    /// turn it on everywhere or off per repo
+    #[arg(long = "fetch-notes")]
+    fetch_notes: bool,
+
+    /// Only update repos currently on one of these branches, e.g. `main,master` (repeatable)
+    #[arg(long = "only-branch", value_name = "BRANCHES", value_delimiter = ',')]
+    only_branch: Vec<String>,
+
+    /// Ask what to do with each repo that has local changes (stash, diff, skip, abort)
+    #[arg(short = 'a', long = "ask")]
+    ask: bool,
+
+    /// Local changes without --ask: `skip`, `stash` (stash, update, pop), `fail`,
+    /// or `update-anyway` (move the branch, leave index and files alone)
+    #[arg(
+        long = "unclean",
+        value_name = "POLICY",
+        value_parser = parse_unclean,
+        default_value = "fail"
+    )]
+    unclean: Unclean,
+
+    /// Stop starting repos once this many have failed; the rest are skipped
+    /// (0 or omitted = no limit)
+    #[arg(long = "max-errors", value_name = "N")]
+    max_errors: Option<usize>,
+
+    /// Bound the whole run, e.g. `90s`, `15m`, `1h30m`: afterwards no repo starts,
+    /// fetches in flight are cancelled, and the rest are skipped
+    #[arg(long = "deadline", value_name = "DURATION", value_parser = parse_duration)]
+    deadline: Option<Duration>,
+
+    /// Skip repos updated successfully less than this long ago, e.g. `1h`
+    /// (times kept in `~/.local/state/groppy`)
+    #[arg(
+        long = "min-age",
+        alias = "since",
+        value_name = "DURATION",
+        value_parser = parse_duration
+    )]
+    min_age: Option<Duration>,
+
+    /// Accept the default answer to every prompt instead of asking
+    #[arg(short = 'y', long = "yes")]
+    yes: bool,
+
+    /// Fail instead of prompting for anything (credentials, host keys, --ask)
+    #[arg(long = "non-interactive", conflicts_with_all = ["ask", "yes"])]
+    non_interactive: bool,
+
+    /// Plain output for CI: no spinners, colors, or terminal escape sequences; a few
+    /// milestone lines on stderr instead (automatic when stdout is not a terminal)
+    #[arg(long = "no-progress")]
+    no_progress: bool,
+
+    /// Log filter, e.g. `debug` or `groppy=trace`; overrides RUST_LOG. Logs go to
+    /// stderr and replace the spinner
+    #[arg(long = "log-level", value_name = "FILTER")]
+    log_level: Option<String>,
+
+    /// Log line format
+    #[arg(
+        long = "log-format",
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t = LogFormat::Text
+    )]
+    log_format: LogFormat,
+
+    /// Summary layout: the counters line with per-category lists, or repos
+    /// grouped under updated, local changes and failed
+    #[arg(
+        long = "summary",
+        value_name = "STYLE",
+        value_enum,
+        default_value_t = SummaryStyle::Compact
+    )]
+    summary: SummaryStyle,
+
+    /// Machine-readable output on stdout: one tab-separated line per repo, no
+    /// colors or progress. The format of each version never changes.
+    #[arg(
+        long = "porcelain",
+        value_name = "VERSION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1",
+        conflicts_with = "confirm"
+    )]
+    porcelain: Option<PorcelainVersion>,
+
+    /// Start each repo line with a status glyph: ✓ updated, ∅ up to date, ✗ failed,
+    /// ± dirty, · other
+    #[arg(long = "icons")]
+    icons: bool,
+
+    /// Use ASCII glyphs (+ = x ~ -) instead; implies --icons
+    #[arg(long = "no-emoji")]
+    no_emoji: bool,
+
+    /// Under the spinner, list the repo each worker is on and for how long
+    #[arg(long = "show-workers")]
+    show_workers: bool,
+
+    /// Share one SSH connection per host across the run's fetches (OpenSSH
+    /// ControlMaster), instead of a handshake, and any MFA prompt, per repo
+    #[arg(long = "ssh-multiplex")]
+    ssh_multiplex: bool,
+
+    /// Get, store, and reject HTTPS credentials through `git credential`, so git's
+    /// helpers and config (includeIf, per-URL helpers) apply exactly as for git
+    #[arg(long = "git-credential")]
+    git_credential: bool,
+
+    /// HTTP User-Agent for fetches, e.g. for a proxy that filters agents or to
+    /// spot groppy in server logs; config `user_agent` sets it everywhere
+    #[arg(long = "user-agent", value_name = "AGENT")]
+    user_agent: Option<String>,
+
+    /// Don't connect to each remote host before the run to skip the repos of
+    /// hosts that are down (e.g. off the VPN), or go offline when none is up
+    #[arg(long = "no-host-check")]
+    no_host_check: bool,
+
+    /// Fetch nothing: show each repo's local changes and ahead/behind as of its
+    /// last fetch (what a run does by itself when no remote host is reachable)
+    #[arg(long = "offline", conflicts_with = "confirm")]
+    offline: bool,
+
+    /// Fetch from GitHub, GitLab, Bitbucket and Codeberg over `ssh` or `https`
+    /// whatever the remote URL says, e.g. when port 22 is blocked; config unchanged
+    #[arg(long = "prefer", value_name = "PROTOCOL", value_parser = parse_clone_protocol)]
+    prefer: Option<CloneProtocol>,
+
+    /// Connect SSH fetches over IPv4 only, e.g. where IPv6 routes are broken
+    #[arg(short = '4', long = "ipv4", conflicts_with = "ipv6")]
+    ipv4: bool,
+
+    /// Connect SSH fetches over IPv6 only
+    #[arg(short = '6', long = "ipv6")]
+    ipv6: bool,
+
+    /// Fetch only the branch being updated (plus --refspec values), without tags:
+    /// protocol v2 then lists just that ref, much faster on repos with many refs
+    #[arg(long = "single-branch")]
+    single_branch: bool,
+
+    /// Add the new HEAD's short hash, author, and commit date to each updated repo's
+    /// line and to HTML and Markdown reports
+    #[arg(long = "show-head")]
+    show_head: bool,
+
+    /// Signal the terminal when the run finishes: `bel` rings the bell, `notify` also
+    /// sends an OSC 777/OSC 9 desktop notification where the terminal supports it
+    #[arg(
+        long = "bell",
+        value_name = "KIND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "bel"
+    )]
+    bell: Option<Bell>,
+
+    /// Prefix result lines with the time: `clock` is UTC wall-clock time,
+    /// `elapsed` the time since the run started
+    #[arg(
+        long = "timestamps",
+        value_name = "KIND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "clock"
+    )]
+    timestamps: Option<Timestamps>,
+
+    /// Write a report after the run, as `KIND:PATH` with KIND `html`, `csv`, `md`, `json`,
+    /// or `junit` (repeatable)
+    #[arg(long = "report", value_name = "KIND:PATH")]
+    report: Vec<ReportTarget>,
+
+    /// Template for per-repo result lines, e.g. '{status} {path} {ahead}/{behind}';
+    /// fields: status path name message ahead behind files_changed commits old
+    /// new duration error
+    #[arg(long = "format", value_name = "TEMPLATE")]
+    format: Option<LineTemplate>,
+
+    /// Print GitHub Actions `::error`/`::warning` commands for failed repos and
+    /// repos left alone for local changes, after the summary
+    #[arg(long = "github-annotations")]
+    github_annotations: bool,
+
+    /// Write the complete results as JSON after the run, whatever the console
+    /// output mode (same as `--report json:PATH`)
+    #[arg(long = "report-file", value_name = "PATH")]
+    report_file: Option<PathBuf>,
+
+    /// Check every repo first, print what would happen, and ask before updating
+    #[arg(long = "confirm")]
+    confirm: bool,
+
+    /// Fetch every remote, not just the one HEAD updates from, and report each
+    #[arg(long = "all-remotes")]
+    all_remotes: bool,
+
+    /// Set a branch's missing upstream to `<remote>/<branch>` when that ref exists
+    #[arg(long = "set-upstream")]
+    set_upstream: bool,
+
+    /// Initialize and check out submodules that are missing after the update
+    #[arg(long = "init-submodules")]
+    init_submodules: bool,
+
+    /// Only fast-forward to upstream commits signed by an allowed key
+    #[arg(long = "verify-signatures")]
+    verify_signatures: bool,
+
+    /// Config file to load instead of `~/.config/groppy/config.toml`
+    #[arg(short = 'c', long = "config", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands; without one, groppy updates repositories.
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Print another tool's repo list as `[repos]` entries for config.toml
+    Import {
+        /// Tool the list comes from: `vcstool` (.repos YAML) or `mrconfig`
+        #[arg(long = "from", value_name = "TOOL", value_parser = parse_import_format)]
+        from: ImportFormat,
+
+        /// The list to read, e.g. `repos.yaml` or `~/.mrconfig`
+        file: PathBuf,
+
+        /// Directory relative paths are under (default: `.` for vcstool, the
+        /// file's directory for mrconfig)
+        #[arg(long = "into", value_name = "DIR")]
+        into: Option<PathBuf>,
+    },
+
+    /// Time fetch-only runs over the same repos with each fetch setting
+    /// (default, --single-branch, --ssh-multiplex, both) to pick the fastest
+    Bench {
+        /// Directories to scan for Git repositories (default: the current one)
+        directories: Vec<PathBuf>,
+
+        /// Parallel jobs for every run (0 or omitted = automatic)
+        #[arg(short = 'j', long = "jobs")]
+        jobs: Option<usize>,
+
+        /// Timed runs per setting; the best one counts
+        #[arg(long = "rounds", default_value_t = 1)]
+        rounds: usize,
+    },
+
+    /// Print the commits earlier runs pulled as one Markdown digest, a
+    /// section per repo
+    Changelog {
+        /// `last-run` for the latest run that pulled anything, or a
+        /// `YYYY-MM-DD` date (UTC) for every run since then
+        #[arg(long = "since", value_name = "WHEN", value_parser = parse_since)]
+        since: Since,
+    },
+}
+
+/// Where `groppy changelog --since` starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Since {
+    LastRun,   // The latest run that pulled commits
+    Date(u64), // Unix seconds of a UTC midnight
+}
+
+/// Log line format for `--log-format`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum LogFormat {
+    #[default]
+    Text, // Human-readable lines
+    Json, // One JSON object per event, including the repo span
+}
+
+/// Layouts for the end-of-run summary (`--summary`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum SummaryStyle {
+    #[default]
+    Compact, // Counters line, then one line per bucket and failure kind
+    Grouped, // One line per repo under updated, local changes and failed
+}
+
+/// Versions of the `--porcelain` output format.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum PorcelainVersion {
+    V1, // First format; statuses limited to the original five
+    V2, // Every bucket as its own status
+}
+
+/// Glyph sets for `--icons`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Icons {
+    Unicode, // ✓ ∅ ✗ ±
+    Ascii,   // --no-emoji: + = x ~
+}
+
+impl Icons {
+    /// The glyph for a result: updated, up to date, failed, dirty, or any
+    /// other bucket.
+    fn glyph(self, status: &RepoStatus) -> &'static str {
+        let (unicode, ascii) = match (status.outcome(), status.bucket) {
+            ("updated", _) => ("✓", "+"),
+            ("ok", _) => ("∅", "="),
+            ("failed", _) => ("✗", "x"),
+            (_, Some(Bucket::Dirty)) => ("±", "~"),
+            _ => ("·", "-"),
+        };
+        match self {
+            Icons::Unicode => unicode,
+            Icons::Ascii => ascii,
+        }
+    }
+}
+
+/// Attention signals for `--bell`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Bell {
+    Bel,    // BEL only
+    Notify, // BEL plus OSC 777 and OSC 9 notifications
+}
+
+/// Line prefixes for `--timestamps`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Timestamps {
+    Clock,   // [14:02:07], UTC
+    Elapsed, // [+00:01:23] since the run started
+}
+
+/// A single answer to the dirty-repo question.
+#[derive(Debug, PartialEq)]
+enum PromptAnswer {
+    Action(DirtyAction),
+    ShowDiff, // Print the local changes and ask again
+}
+
+/// Asks the user what to do about dirty repositories, one question at a time.
+///
+/// Holds the shared output lock while waiting for an answer so the spinner and
+/// other workers' result lines don't draw over the question. Other workers keep
+/// fetching; only their output waits.
+struct Prompter {
+    output_lock: Arc<Mutex<()>>,
+    input: Mutex<Box<dyn std::io::BufRead + Send>>,
+    control: Arc<RunControl>, // Aborted when the user picks "abort all"
+}
+
+impl AskDirty for Prompter {
+    /// Prompts until a valid action is chosen. End of input means skip.
+    fn ask_dirty(&self, path: &Path) -> DirtyAction {
+        let _lock = self.output_lock.lock().unwrap();
+        let mut input = self.input.lock().unwrap();
+        if self.control.is_aborted() {
+            return DirtyAction::Abort; // Another worker's question already aborted the run
+        }
+        loop {
+            eprint!(
+                "\r\x1b[J  {}: local changes - [s]tash & update, show [d]iff, [n] skip, \
+                 [q] abort all? [n] ",
+                path.display()
+            );
+            let _ = std::io::stderr().flush();
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                return DirtyAction::Skip;
+            }
+            match parse_prompt_answer(&line) {
+                Some(PromptAnswer::Action(action)) => {
+                    if action == DirtyAction::Abort {
+                        self.control.abort();
+                    }
+                    return action;
+                }
+                Some(PromptAnswer::ShowDiff) => show_local_changes(path),
+                None => eprintln!("  please answer s, d, n, or q"),
+            }
+        }
+    }
+}
+
+/// Parses a prompt reply; an empty reply picks the default (skip).
+fn parse_prompt_answer(line: &str) -> Option<PromptAnswer> {
+    match line.trim().to_ascii_lowercase().as_str() {
+        "" | "n" | "skip" => Some(PromptAnswer::Action(DirtyAction::Skip)),
+        "s" | "stash" => Some(PromptAnswer::Action(DirtyAction::Stash)),
+        "q" | "abort" => Some(PromptAnswer::Action(DirtyAction::Abort)),
+        "d" | "diff" => Some(PromptAnswer::ShowDiff),
+        _ => None,
+    }
+}
+
+/// Prints `git status --short` and `git diff HEAD` for a repo to stderr.
+fn show_local_changes(path: &Path) {
+    for args in [&["status", "--short"][..], &["--no-pager", "diff", "HEAD"][..]] {
+        match run_git(path, args) {
+            Ok(out) => eprint!("{out}"),
+            Err(e) => eprintln!("  {e}"),
+        }
+    }
+}
+
+/// Rejects `--from-file -` with `--ask` or `--confirm`: the list uses up
+/// stdin, where their answers are read from. `--yes` answers without reading.
+fn check_stdin_users(cli: &Cli) -> Result<()> {
+    if cli.from_file.as_deref() != Some(Path::new("-")) || cli.yes {
+        return Ok(());
+    }
+    for (set, flag) in [(cli.ask, "--ask"), (cli.confirm, "--confirm")] {
+        if set {
+            anyhow::bail!("--from-file - reads the repo list from stdin, which {flag} needs");
+        }
+    }
+    Ok(())
+}
+
+/// Asks a yes/no question on stderr and reads the answer from stdin.
+///
+/// `--yes` answers yes without asking; `--non-interactive` is an error since the
+/// question cannot be answered.
+fn confirm(question: &str, interaction: Interaction) -> Result<bool> {
+    match interaction {
+        Interaction::AssumeDefaults => Ok(true),
+        Interaction::Fail => {
+            anyhow::bail!("{question} needs an answer but --non-interactive is set")
+        }
+        Interaction::Prompt => {
+            eprint!("{question} [y/N] ");
+            let _ = std::io::stderr().flush();
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            Ok(parse_yes_no(&line))
+        }
+    }
 }
 
-/// Represents the outcome of updating a single Git repository.
-/// Contains all information needed to display the result to the user.
-struct RepoStatus {
-    path: PathBuf,       //  Absolute filesystem path to the repository
-    success: bool,       // Whether the update operation succeeded
-    message: String,     // Human-readable description of what happened
-    files_changed: usize, // Number of files modified by the update
+/// Parses a `[y/N]` reply; anything but yes means no.
+fn parse_yes_no(line: &str) -> bool {
+    matches!(line.trim().to_ascii_lowercase().as_str(), "y" | "yes")
 }
 
 /// Entry point: parses CLI args, discovers repos, runs parallel updates, and prints summary.
@@ -117,662 +717,1824 @@ struct RepoStatus {
 ///   5. Update all repositories in parallel using a rayon thread pool
 ///   6. Print a summary of results
 fn main() -> Result<()> {
+    let run_started = Instant::now(); // --deadline counts from here
     let cli = Cli::parse();
+    if let Some(Command::Import { from, file, into }) = &cli.command {
+        return import_repos(*from, file, into.as_deref());
+    }
+    if let Some(Command::Bench { directories, jobs, rounds }) = &cli.command {
+        return bench(directories, *jobs, *rounds);
+    }
+    if let Some(Command::Changelog { since }) = &cli.command {
+        return changelog(*since);
+    }
+    check_stdin_users(&cli)?;
+    let logging = init_logging(cli.log_level.as_deref(), cli.log_format)?;
+    let config = config::Config::load(cli.config.as_deref())?;
+    let hooks = config.hooks.as_deref().map(Hooks::load).transpose()?.map(Arc::new);
+    let tokens = Arc::new(config.tokens()?);
+    let cli_token = cli.token_file.as_deref().map(config::read_token).transpose()?;
+    let netrc = Arc::new(Netrc::load()?);
+    let rewrites = Arc::new(config.rewrites.clone());
+    for spec in &cli.refspec {
+        gix::refspec::parse(spec.as_str().into(), gix::refspec::parse::Operation::Fetch)
+            .map_err(|e| anyhow::anyhow!("invalid --refspec {spec}: {e}"))?;
+    }
 
-    // Default to current directory if no directories specified
-    let dirs: Vec<PathBuf> = if cli.directories.is_empty() {
-        vec![PathBuf::from(".")]
-    } else {
-        cli.directories.clone()
-    };
+    // The config's roots plus any directories given, or only those with
+    // --only-args; see scan_dirs
+    let forges = forges(&cli, cli_token.as_deref(), &tokens);
+    let listed = cli.from_file.is_some() || !forges.is_empty();
+    let roots: Vec<PathBuf> = config.roots.iter().map(|root| root.path.clone()).collect();
+    let dirs = scan_dirs(&cli.directories, &roots, listed, cli.only_args, &cli.add);
+    let config_repos = !cli.only_args && (!listed || !cli.directories.is_empty());
 
     // Canonicalize paths to absolute form and remove any that don't exist
     let dirs: Vec<PathBuf> = dirs
         .into_iter()
         .filter_map(|d| std::fs::canonicalize(d).ok())
         .collect();
-    let dirs = unique_ordered(dirs); // Remove duplicate directories
+    let dirs = discovery::unique_ordered(dirs); // Remove duplicate directories
 
-    // Discover all git repositories in the provided directories
-    let (repos, scan_warnings) = find_git_repositories(&dirs);
+    let output_lock = Arc::new(Mutex::new(())); // Prevents interleaved output lines
+
+    // Discover all git repositories in the provided directories, with a spinner
+    // showing how many directories have been walked so far
+    let mut exclude: Vec<String> = if cli.no_default_excludes {
+        Vec::new()
+    } else {
+        DEFAULT_EXCLUDES.iter().map(|s| s.to_string()).collect()
+    };
+    exclude.extend(cli.exclude.iter().cloned());
+    let scan_opts = ScanOptions {
+        follow_symlinks: cli.follow_symlinks,
+        hidden: !cli.no_hidden,
+        max_depth: cli.max_depth,
+        exclude,
+        root_depths: config.root_depths(),
+    };
+    let porcelain = cli.porcelain.is_some(); // Machine output only: no spinners, colors, summary
+    let icons = match (cli.icons, cli.no_emoji) {
+        (_, true) => Some(Icons::Ascii),
+        (true, false) => Some(Icons::Unicode),
+        (false, false) => None,
+    };
+    // Spinners would garble porcelain and log lines; --no-progress, or stdout
+    // going to a pipe or file (`groppy | tee log`), wants plain lines
+    let no_progress = cli.no_progress || !std::io::stdout().is_terminal();
+    let progress = !porcelain && !logging && !no_progress;
+    let plain = no_progress && !porcelain; // Milestone lines stand in for the spinners
+    if no_progress {
+        crossterm::style::force_color_output(false);
+    }
+    let taskbar_guard = progress.then(TaskbarGuard::install).transpose()?;
+    let scanned = Arc::new(AtomicUsize::new(0));
+    let stop_scan_spinner = Arc::new(AtomicBool::new(!progress));
+    let scan_spinner_handle = {
+        let stop = stop_scan_spinner.clone();
+        let scanned = scanned.clone();
+        let lock = output_lock.clone();
+        std::thread::spawn(move || run_scan_spinner(stop, scanned, lock))
+    };
+    let state_dir = state::state_dir();
+    let (mut repos, mut scan_warnings) = match state_dir.as_deref() {
+        Some(cache) => discovery::scan_cached(&dirs, &scan_opts, &scanned, cache, cli.rescan),
+        None => discovery::scan_for_repositories(&dirs, &scan_opts, &scanned),
+    };
+    stop_scan_spinner.store(true, Ordering::Release);
+    let _ = scan_spinner_handle.join();
+    if progress {
+        eprint!("\r\x1b[J");
+    }
+    if plain {
+        eprintln!("scanned {} directories", scanned.load(Ordering::Relaxed));
+    }
+
+    // Append explicitly listed repositories, skipping ones discovery already found
+    if let Some(list_path) = &cli.from_file {
+        let (listed, list_warnings) = discovery::load_repo_list(list_path)?;
+        repos = discovery::unique_ordered(repos.into_iter().chain(listed).collect());
+        scan_warnings.extend(list_warnings);
+    }
+    // Then forge repositories, cloning the missing ones first
+    let mut managed = Vec::new(); // Every forge repo on disk, for --archive-extraneous
+    for forge in &forges {
+        let hosted = forge.list().with_context(|| format!("cannot list {}", forge.describe()))?;
+        let interrupt = AtomicBool::new(false);
+        let (root, protocol) = (&cli.clone_into, cli.clone_protocol);
+        let checkouts = forge::clone_missing(&hosted, root, protocol, cli.fix_remotes, &interrupt);
+        if !porcelain {
+            print_checkouts(&checkouts);
+        }
+        managed.extend(checkouts.repos.iter().cloned());
+        repos = discovery::unique_ordered(repos.into_iter().chain(checkouts.repos).collect());
+    }
+    // Repos the manifest no longer lists are reported, or moved aside
+    if cli.manifest.is_some() {
+        let archive_dir = cli.archive_extraneous.as_deref();
+        let (extra, warnings) = manifest::extraneous(&cli.clone_into, &managed, archive_dir);
+        scan_warnings.extend(warnings);
+        match archive_dir {
+            Some(dir) => {
+                for (path, moved) in manifest::archive(&extra, &cli.clone_into, dir) {
+                    let line = match moved {
+                        Ok(to) => format!("  archived {} → {}", path.display(), to.display())
+                            .with(COLOR_SUBTEXT),
+                        Err(e) => format!("  {e:#}").with(COLOR_RED),
+                    };
+                    if !porcelain {
+                        println!("{line}");
+                    }
+                }
+            }
+            None if !porcelain => {
+                for path in &extra {
+                    let line = format!("  not in manifest: {}", path.display());
+                    println!("{}", line.with(COLOR_SUBTEXT));
+                }
+            }
+            None => {}
+        }
+    }
+    // And, whenever the config's roots are scanned, repos the config gives a
+    // `url`, cloning the missing ones: a new machine only needs the config
+    if config_repos {
+        let specs = config.clone_specs();
+        let checkouts = forge::ensure_cloned(&specs, cli.fix_remotes, &AtomicBool::new(false));
+        if !porcelain {
+            print_checkouts(&checkouts);
+        }
+        repos = discovery::unique_ordered(repos.into_iter().chain(checkouts.repos).collect());
+    }
     if cli.verbose {
         for w in &scan_warnings {
             eprintln!("{}", format!("  warning: {w}").with(COLOR_SUBTEXT));
         }
     }
+    let control = Arc::new(RunControl::default()); // Abort, deadline, and pause switches
+    control.stop_after_failures(cli.max_errors.unwrap_or(0));
+    if let Some(deadline) = cli.deadline {
+        let control = control.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(deadline.saturating_sub(run_started.elapsed()));
+            control.interrupt(); // Cancel fetches in flight and skip the rest
+        });
+    }
+    let interaction = if cli.non_interactive {
+        Interaction::Fail
+    } else if cli.yes {
+        Interaction::AssumeDefaults
+    } else {
+        Interaction::Prompt
+    };
+    let ip_version = match (cli.ipv4, cli.ipv6) {
+        (true, _) => Some(IpVersion::V4),
+        (_, true) => Some(IpVersion::V6),
+        _ => None,
+    };
+    let prompter = (cli.ask && interaction == Interaction::Prompt).then(|| {
+        Arc::new(Prompter {
+            output_lock: output_lock.clone(),
+            input: Mutex::new(Box::new(std::io::BufReader::new(std::io::stdin()))),
+            control: control.clone(),
+        }) as Arc<dyn AskDirty>
+    });
+
+    // The status key reader owns stdin for the run (see below); HTTPS logins
+    // that git's helpers and askpass cannot supply are typed through it, once
+    // per host
+    let keys = !porcelain
+        && prompter.is_none()
+        && interaction == Interaction::Prompt
+        && std::io::stdin().is_terminal();
+    let terminal_lines = Arc::new(Mutex::new(TerminalLines::default()));
+    let credentials = (keys && cfg!(unix)).then(|| {
+        Arc::new(CredentialPrompter {
+            output_lock: output_lock.clone(),
+            lines: terminal_lines.clone(),
+            accounts: Mutex::default(),
+        }) as Arc<dyn AskCredentials>
+    });
+    // Without the taskbar guard's handler, a signal would end the run with
+    // echo still off if it came mid-password
+    #[cfg(unix)]
+    if credentials.is_some() && taskbar_guard.is_none() {
+        exit_on_signal(String::new)?;
+    }
+
+    //  Determine actual job count (0 or omitted means pick one automatically)
+    let jobs = match cli.jobs {
+        Some(n) if n > 0 => n,
+        _ => {
+            let cpus = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4);
+            auto_jobs(cpus, repos.len())
+        }
+    };
+
+    // Build a rayon thread pool with the requested number of threads
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()?;
+    if cli.adaptive_jobs {
+        control.adapt_jobs(jobs);
+    }
+
+    let fetch_log = state_dir.as_deref().map(FetchLog::load).unwrap_or_default();
+    let ssh_control = cli.ssh_multiplex.then(SshControlDir::create).transpose()?;
+
+    let config = &config;
+    let fetch_log = &fetch_log;
+    let options_for = |repo_path: &Path| UpdateOptions {
+        remote: config.remote_for(repo_path, cli.remote.as_deref()),
+        refspecs: config.refspecs_for(repo_path, &cli.refspec, cli.fetch_notes),
+        ask: prompter.clone(),
+        ask_credentials: credentials.clone(),
+        tokens: tokens.clone(),
+        netrc: netrc.clone(),
+        git_credential: cli.git_credential,
+        user_agent: cli.user_agent.clone().or_else(|| config.user_agent.clone()),
+        ip_version,
+        prefer: cli.prefer,
+        rewrites: rewrites.clone(),
+        interaction,
+        signing_keys: config.signing_keys_for(repo_path, cli.verify_signatures),
+        only_branches: config.only_branches_for(repo_path, &cli.only_branch),
+        pin: config.pin_for(repo_path),
+        init_submodules: cli.init_submodules,
+        set_upstream: cli.set_upstream,
+        all_remotes: cli.all_remotes,
+        hooks: hooks.clone(),
+        strategy: config.strategy_for(repo_path),
+        clean_check: config.clean_check_for(repo_path),
+        no_hooks: cli.no_hooks,
+        exec: cli.exec.clone(),
+        unclean: cli.unclean,
+        last_fetched: fetch_log.last_fetched(repo_path),
+        min_age: cli.min_age.unwrap_or_default(),
+        show_head: cli.show_head,
+        single_branch: cli.single_branch,
+        ssh_control_dir: ssh_control.as_ref().map(|dir| dir.0.clone()),
+        ..Default::default()
+    };
+
+    // Plan-and-confirm: check every repo without changing anything, then update
+    // only the repos the plan says will move (plus dirty ones --ask or
+    // --unclean can handle)
+    if cli.confirm {
+        let handles_dirty =
+            prompter.is_some() || matches!(cli.unclean, Unclean::Stash | Unclean::UpdateAnyway);
+        let plan = plan_repositories(&pool, &repos, &options_for, &output_lock, progress);
+        print_plan(&plan, cli.verbose);
+        repos = plan
+            .into_iter()
+            .filter(|p| {
+                matches!(
+                    p.action,
+                    PlanAction::FastForward | PlanAction::Integrate | PlanAction::Fetch
+                ) || (p.action == PlanAction::Dirty && handles_dirty)
+            })
+            .map(|p| p.path)
+            .collect();
+        if repos.is_empty() {
+            println!("{}", "Nothing to update".with(COLOR_SUBTEXT));
+            return Ok(());
+        }
+        let question = format!("Proceed with {} repositories?", repos.len());
+        if !confirm(&question, interaction)? {
+            return Ok(());
+        }
+    }
+
+    let mut update_times = state_dir.as_deref().map(UpdateTimes::load).unwrap_or_default();
+    run::slowest_first(&mut repos, &update_times);
+
     let total = repos.len();
     let start = Instant::now(); //  Start timing the entire update process
+    tracing::info!(repos = total, jobs, "updating repositories");
+    if plain {
+        eprintln!("updating {total} repositories with {jobs} jobs");
+    }
 
     // Shared atomic counters for thread-safe progress tracking
     let completed = Arc::new(AtomicUsize::new(0));
     let succeeded = Arc::new(AtomicUsize::new(0));
     let failed = Arc::new(AtomicUsize::new(0));
-    let stop_spinner = Arc::new(AtomicBool::new(false));
-    let output_lock = Arc::new(Mutex::new(())); // Prevents interleaved output lines
+    let stop_spinner = Arc::new(AtomicBool::new(!progress));
+
+    // Status dumps on SIGUSR1, or on Enter when nothing else reads the terminal,
+    // which also takes `p`/`r` to pause and resume dispatching
+    let board = Arc::new(StatusBoard {
+        total,
+        completed: completed.clone(),
+        succeeded: succeeded.clone(),
+        failed: failed.clone(),
+        workers: Mutex::new(vec![None; jobs]),
+        started: start,
+        output_lock: output_lock.clone(),
+        control: control.clone(),
+        progress,
+    });
+    #[cfg(unix)]
+    watch_status_signal(board.clone())?;
+    if keys {
+        let (board, lines) = (board.clone(), terminal_lines.clone());
+        std::thread::spawn(move || watch_status_keys(&board, std::io::stdin().lock(), &lines));
+    }
 
     // Spawn the spinner animation on a dedicated thread
     let spinner_stop = stop_spinner.clone();
-    let spinner_completed = completed.clone();
+    let spinner_counts = (completed.clone(), failed.clone());
     let spinner_total = total;
     let spinner_lock = output_lock.clone();
+    let spinner_board = cli.show_workers.then(|| board.clone());
     let spinner_handle = std::thread::spawn(move || {
-        run_spinner(spinner_stop, spinner_completed, spinner_total, spinner_lock);
+        let label = "Updating repositories...";
+        let counts = spinner_counts;
+        run_spinner(spinner_stop, counts, spinner_total, spinner_lock, label, spinner_board);
     });
 
-    //  Determine actual job count (0 means use all available CPUs)
-    let jobs = if cli.jobs == 0 {
-        std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4)
-    } else {
-        cli.jobs
-    };
-
-    // Build a rayon thread pool with the requested number of threads
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(jobs)
-        .build()?;
-
-    // Process all repositories in parallel within the thread pool scope
-    pool.scope(|s| {
-        for repo_path in &repos {
-            let completed = completed.clone();
-            let succeeded = succeeded.clone();
-            let failed = failed.clone();
-            let output_lock = output_lock.clone();
-            let verbose = cli.verbose;
-
-            s.spawn(move |_| {
-                // Update the repository and record the result
-                let status = update_repository(repo_path);
-
-                // Failures always print; unchanged-success lines respect verbose.
-                // The visibility check is explicit here so failures can never be
-                // accidentally silenced by a change inside format_line.
-                if !status.success || status.files_changed > 0 || verbose {
-                    let _lock = output_lock.lock().unwrap();
-                    eprint!("\r\x1b[K");
-                    println!("{}", format_line(&status));
+    // Process all repositories in parallel, printing and counting each result
+    // as its worker finishes
+    let on_event = |event: Event<'_>| match event {
+        Event::Started { worker, path } => {
+            board.workers.lock().unwrap()[worker] = Some((path.to_path_buf(), Instant::now()));
+        }
+        Event::Finished { worker, status } => {
+            board.workers.lock().unwrap()[worker] = None;
+
+            // Failures always print, and so do repos out of sync with their
+            // upstream; unchanged-success lines respect verbose. The visibility
+            // check is explicit here so failures can never be accidentally
+            // silenced by a change inside format_line.
+            let shown = !status.success || status.files_changed > 0 || cli.verbose;
+            let skipped = cli.show_skipped && skipped_category(status).is_some();
+            if !porcelain && (shown || skipped || status.divergence().is_some()) {
+                let _lock = output_lock.lock().unwrap();
+                if progress {
+                    eprint!("\r\x1b[J");
                 }
+                let line = match (&cli.format, skipped) {
+                    (Some(template), true) => template.render(status).dim().to_string(),
+                    (Some(template), false) => {
+                        template.render(status).with(line_color(status)).to_string()
+                    }
+                    (None, true) => skipped_line(status, icons).dim().to_string(),
+                    (None, false) => format_line(status, icons),
+                };
+                let stamp = cli.timestamps.map(|kind| {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                    timestamp(kind, now.as_secs(), start.elapsed()).with(COLOR_SUBTEXT)
+                });
+                match stamp {
+                    Some(stamp) => println!("{stamp} {line}"),
+                    None => println!("{line}"),
+                }
+            }
 
-                // Atomically update progress counters
-                completed.fetch_add(1, Ordering::Relaxed);
-                if status.success {
-                    succeeded.fetch_add(1, Ordering::Relaxed);
-                } else {
-                    failed.fetch_add(1, Ordering::Relaxed);
+            // Atomically update progress counters
+            completed.fetch_add(1, Ordering::Relaxed);
+            match (status.bucket, status.success) {
+                (Some(_), _) => {} // Counted per bucket in the summary instead
+                (None, true) => _ = succeeded.fetch_add(1, Ordering::Relaxed),
+                (None, false) => _ = failed.fetch_add(1, Ordering::Relaxed),
+            }
+        }
+    };
+    if cli.offline {
+        control.set_offline();
+    } else if !cli.no_host_check {
+        control.check_hosts(&pool, &repos, &options_for);
+        if control.is_offline() && !porcelain {
+            let _lock = output_lock.lock().unwrap();
+            if progress {
+                eprint!("\r\x1b[J");
+            }
+            let banner = "No remote host is reachable - offline: nothing is fetched, \
+                          repos are shown as of their last fetch";
+            println!("{}", banner.with(COLOR_RED).bold());
+        }
+    }
+    // The address family only reaches SSH; say so rather than let HTTPS
+    // fetches stall on the broken one unannounced
+    if ip_version.is_some() && !control.is_offline() {
+        let ignored = run::ip_version_ignored(&pool, &repos, &options_for);
+        if !ignored.is_empty() {
+            let _lock = output_lock.lock().unwrap();
+            if progress {
+                eprint!("\r\x1b[J");
+            }
+            let flag = if cli.ipv4 { "--ipv4" } else { "--ipv6" };
+            let warning = format!(
+                "  warning: {flag} applies to SSH remotes only; {} repos fetch over HTTPS or \
+                 git:// with either address family",
+                ignored.len()
+            );
+            eprintln!("{}", warning.with(COLOR_SUBTEXT));
+            if cli.verbose {
+                for path in &ignored {
+                    eprintln!("{}", format!("    {}", path.display()).with(COLOR_SUBTEXT));
                 }
-            });
+            }
         }
-    });
+    }
+    let results = run::update_all(&pool, &repos, &options_for, &control, &on_event);
+    if let Some(dir) = &state_dir {
+        record_fetches(dir, &results);
+        record_update_times(dir, &mut update_times, &results);
+        record_pulls(dir, &results);
+    }
 
     // Stop the spinner thread and wait for it to finish
     stop_spinner.store(true, Ordering::Release);
     let _ = spinner_handle.join();
 
-    eprint!("\r\x1b[K"); // Clear the final spinner line
-    eprint!("\x1b]9;4;0;0\x07"); // Clear OSC 9;4 terminal progress indicator
+    if progress {
+        eprint!("\r\x1b[J"); // Clear the final spinner line
+        eprint!("{}", taskbar(Taskbar::Clear));
+    }
 
     // Load final counter values for the summary
     let completed = completed.load(Ordering::Relaxed);
     let succeeded = succeeded.load(Ordering::Relaxed);
     let failed_count = failed.load(Ordering::Relaxed);
+    let deadline_hit = control.is_interrupted();
     let elapsed = start.elapsed();
 
-    // Print the summary line in muted gray
+    // Porcelain replaces every human-readable line with one stable line per repo
+    if let Some(version) = cli.porcelain {
+        let lines = match version {
+            PorcelainVersion::V1 => report::porcelain_v1(&results),
+            PorcelainVersion::V2 => report::porcelain_v2(&results),
+        };
+        print!("{lines}");
+    } else {
+        let counts = (completed, succeeded, failed_count);
+        let style = (cli.summary, cli.show_skipped);
+        print_summary(&results, total, counts, jobs, elapsed, style);
+        for (host, repos) in control.unreachable_hosts() {
+            let repos = if repos == 1 { "1 repo".to_string() } else { format!("{repos} repos") };
+            let note = format!("{host} is unreachable - its {repos} skipped");
+            println!("{}", note.with(COLOR_RED));
+        }
+        for host in control.failing_hosts() {
+            let note = format!("credentials for {host} are not working - its other repos skipped");
+            println!("{}", note.with(COLOR_RED));
+        }
+        if let Some(deadline) = cli.deadline.filter(|_| deadline_hit) {
+            let note = format!("deadline of {}s reached - partial results", deadline.as_secs());
+            println!("{}", note.with(COLOR_RED));
+        }
+        if let Some(max) = cli.max_errors.filter(|_| control.hit_error_limit()) {
+            let note = format!("stopped after {failed_count} failures (--max-errors {max})");
+            println!("{}", note.with(COLOR_RED));
+        }
+    }
+
+    if cli.github_annotations {
+        print!("{}", report::github_annotations(&results));
+    }
+
+    let report_file = cli.report_file.clone().map(|path| ReportTarget {
+        kind: ReportKind::Json,
+        path,
+    });
+    for target in cli.report.iter().chain(&report_file) {
+        report::write(target, &results, elapsed)?;
+    }
+
+    if let Some(bell) = cli.bell {
+        let body = format!("{completed} repos, {failed_count} failed");
+        eprint!("{}", attention(bell, &body));
+    }
+
+    // Exit with error code 1 if any repositories failed or the deadline cut the run short
+    if failed_count > 0 || deadline_hit {
+        drop(taskbar_guard); // `exit` skips destructors
+        drop(ssh_control);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// The escape sequences for `--bell`. Terminals ignore OSC sequences they don't
+/// understand; the OSC 9 body never starts with a digit, which would make it an
+/// OSC 9;4 progress update instead.
+fn attention(bell: Bell, body: &str) -> String {
+    match bell {
+        Bell::Bel => "\x07".to_string(),
+        Bell::Notify => format!("\x1b]777;notify;groppy;{body}\x07\x1b]9;groppy: {body}\x07\x07"),
+    }
+}
+
+/// Prints the summary line in muted gray, then the repos in each bucket.
+fn print_summary(
+    results: &[RepoStatus],
+    total: usize,
+    (completed, succeeded, failed_count): (usize, usize, usize),
+    jobs: usize,
+    elapsed: Duration,
+    (style, show_skipped): (SummaryStyle, bool),
+) {
+    let bucketed: Vec<(Bucket, PathBuf)> = results
+        .iter()
+        .filter_map(|status| Some((status.bucket?, status.path.clone())))
+        .collect();
     println!();
+    let received: usize = results.iter().map(|status| status.bytes_received).sum();
+    let current = results.iter().filter(|status| status.outcome() == "ok").count();
+    let current = match show_skipped {
+        true => format!(" | {current} unchanged"),
+        false => String::new(),
+    };
     let summary = format!(
-        "repos: {} total | {} done | {} ok | {} fail | jobs: {} | elapsed: {}s",
+        "repos: {} total | {} done | {} ok | {} fail{}{} | jobs: {} | elapsed: {}s | received: {}",
         total,
         completed,
         succeeded,
         failed_count,
+        current,
+        bucket_counts(&bucketed),
         jobs,
-        elapsed.as_secs()
+        elapsed.as_secs(),
+        report::format_bytes(received)
     );
+    if style == SummaryStyle::Grouped {
+        for (line, color) in grouped_summary(results) {
+            println!("{}", line.with(color));
+        }
+    }
     println!("{}", summary.with(COLOR_SUBTEXT));
+    if style == SummaryStyle::Compact {
+        for line in bucket_lists(&bucketed) {
+            println!("{}", line.with(COLOR_SUBTEXT));
+        }
+    }
+    for line in shared_object_lists(results) {
+        println!("{}", line.with(COLOR_SUBTEXT));
+    }
+    if style == SummaryStyle::Compact {
+        for line in failure_lists(results) {
+            println!("{}", line.with(COLOR_RED));
+        }
+    }
+}
 
-    // Exit with error code 1 if any repositories failed
-    if failed_count > 0 {
-        std::process::exit(1);
+/// Failure lines for `--summary grouped`: `path  kind: reason` per failed
+/// repo, with repos that failed the same way (kind and first message line)
+/// collapsed into one `kind: reason (N): path, path` line.
+fn failure_groups(results: &[RepoStatus]) -> Vec<String> {
+    let mut groups: Vec<((&str, &str), Vec<String>)> = Vec::new();
+    for status in results {
+        let Some(kind) = status.error_kind() else { continue };
+        let key = (kind.label(), status.message.lines().next().unwrap_or_default());
+        let path = status.path.display().to_string();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, paths)) => paths.push(path),
+            None => groups.push((key, vec![path])),
+        }
     }
+    groups
+        .into_iter()
+        .map(|((kind, reason), paths)| match paths.len() {
+            1 => format!("  {}  {kind}: {reason}", paths[0]),
+            n => format!("  {kind}: {reason} ({n}): {}", paths.join(", ")),
+        })
+        .collect()
+}
+
+/// Lines for `--summary grouped`: each updated repo with its commit count,
+/// repos left alone for local changes, each failed repo with its kind and
+/// first message line, the other buckets, then how many were up to date.
+fn grouped_summary(results: &[RepoStatus]) -> Vec<(String, Color)> {
+    let mut lines = Vec::new();
+    let mut group = |title: &str, color: Color, entries: Vec<String>| {
+        if !entries.is_empty() {
+            lines.push((format!("{title} ({}):", entries.len()), color));
+            lines.extend(entries.into_iter().map(|entry| (format!("  {entry}"), color)));
+        }
+    };
+    let updated = results
+        .iter()
+        .filter(|status| status.outcome() == "updated")
+        .map(|status| match status.commit_count() {
+            1 => format!("{}  +1 commit", status.path.display()),
+            n => format!("{}  +{n} commits", status.path.display()),
+        })
+        .collect();
+    group("updated", COLOR_GREEN, updated);
+    let dirty = results
+        .iter()
+        .filter(|status| status.bucket == Some(Bucket::Dirty))
+        .map(|status| status.path.display().to_string())
+        .collect();
+    group("local changes", COLOR_SUBTEXT, dirty);
+    let failed = results.iter().filter(|status| status.error_kind().is_some()).count();
+    if failed > 0 {
+        lines.push((format!("failed ({failed}):"), COLOR_RED));
+        lines.extend(failure_groups(results).into_iter().map(|line| (line, COLOR_RED)));
+    }
+    let bucketed: Vec<(Bucket, PathBuf)> = results
+        .iter()
+        .filter_map(|status| Some((status.bucket?, status.path.clone())))
+        .filter(|(bucket, _)| *bucket != Bucket::Dirty)
+        .collect();
+    lines.extend(bucket_lists(&bucketed).into_iter().map(|line| (line, COLOR_SUBTEXT)));
+    let current = results.iter().filter(|status| status.outcome() == "ok").count();
+    lines.push((format!("up to date: {current}"), COLOR_SUBTEXT));
+    lines
+}
 
+/// One line per failure kind with its count and repos, e.g.
+/// `  network (2): /src/a, /src/b`.
+fn failure_lists(results: &[RepoStatus]) -> Vec<String> {
+    ErrorKind::ALL
+        .iter()
+        .filter_map(|&kind| {
+            let paths: Vec<String> = results
+                .iter()
+                .filter(|status| status.error_kind() == Some(kind))
+                .map(|status| status.path.display().to_string())
+                .collect();
+            (!paths.is_empty())
+                .then(|| format!("  {} ({}): {}", kind.label(), paths.len(), paths.join(", ")))
+        })
+        .collect()
+}
+
+/// Summary-line segment with the count of each non-empty bucket,
+/// e.g. ` | 2 no upstream`.
+fn bucket_counts(bucketed: &[(Bucket, PathBuf)]) -> String {
+    Bucket::ALL
+        .iter()
+        .map(|&bucket| (bucket, bucketed.iter().filter(|(b, _)| *b == bucket).count()))
+        .filter(|&(_, n)| n > 0)
+        .map(|(bucket, n)| format!(" | {n} {}", bucket.label()))
+        .collect()
+}
+
+/// One line per object store other repos borrow from (alternates) with the
+/// repos that depend on it, e.g. `  shared objects /ref/linux.git: /src/a`.
+fn shared_object_lists(results: &[RepoStatus]) -> Vec<String> {
+    let mut stores: std::collections::BTreeMap<&Path, Vec<String>> = Default::default();
+    for status in results {
+        for store in &status.alternates {
+            stores.entry(store).or_default().push(status.path.display().to_string());
+        }
+    }
+    stores
+        .into_iter()
+        .map(|(store, repos)| {
+            let missing = if store.exists() { "" } else { " (missing)" };
+            format!("  shared objects {}{missing}: {}", store.display(), repos.join(", "))
+        })
+        .collect()
+}
+
+/// One line per non-empty bucket listing its repos by path.
+fn bucket_lists(bucketed: &[(Bucket, PathBuf)]) -> Vec<String> {
+    Bucket::ALL
+        .iter()
+        .filter_map(|&bucket| {
+            let paths: Vec<String> = bucketed
+                .iter()
+                .filter(|(b, _)| *b == bucket)
+                .map(|(_, path)| path.display().to_string())
+                .collect();
+            (!paths.is_empty()).then(|| format!("  {}: {}", bucket.label(), paths.join(", ")))
+        })
+        .collect()
+}
+
+/// Installs the `tracing` subscriber writing to stderr, filtered by `--log-level`
+/// or else `RUST_LOG`. Returns false, installing nothing, when neither is set.
+fn init_logging(level: Option<&str>, format: LogFormat) -> Result<bool> {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = match level {
+        Some(directives) => EnvFilter::try_new(directives)
+            .map_err(|e| anyhow::anyhow!("invalid --log-level {directives}: {e}"))?,
+        None => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return Ok(false),
+        },
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_thread_names(true);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+    Ok(true)
+}
+
+/// Remembers when each successfully updated repo was fetched, for `--min-age`.
+/// Failing to save only costs the next run a fetch, so it is just logged.
+fn record_fetches(dir: &Path, results: &[RepoStatus]) {
+    let mut log = FetchLog::default();
+    let now = std::time::SystemTime::now();
+    for status in results.iter().filter(|s| s.success && s.bucket.is_none()) {
+        log.record(&status.path, now);
+    }
+    if let Err(e) = log.save(dir) {
+        tracing::warn!("{e:#}");
+    }
+}
+
+/// Remembers how long each repo that got as far as updating took, so the next
+/// run starts the slowest first. Failures count: a repo that times out is slow.
+fn record_update_times(dir: &Path, times: &mut UpdateTimes, results: &[RepoStatus]) {
+    for status in results.iter().filter(|s| s.bucket.is_none()) {
+        times.record(&status.path, status.duration);
+    }
+    if let Err(e) = times.save(dir) {
+        tracing::warn!("{e:#}");
+    }
+}
+
+/// Remembers the commits each repo got, for `groppy changelog`. Like the fetch
+/// times, failing to save is only logged.
+fn record_pulls(dir: &Path, results: &[RepoStatus]) {
+    let mut log = PullLog::default();
+    let now = std::time::SystemTime::now();
+    for status in results.iter().filter(|s| s.success && !s.commits.is_empty()) {
+        log.record(&status.path, &status.commits, now);
+    }
+    if let Err(e) = log.save(dir) {
+        tracing::warn!("{e:#}");
+    }
+}
+
+/// Parses a `changelog --since` value: `last-run` or `YYYY-MM-DD`.
+fn parse_since(text: &str) -> Result<Since, String> {
+    if text == "last-run" {
+        return Ok(Since::LastRun);
+    }
+    let invalid = || format!("invalid --since {text:?}: expected last-run or YYYY-MM-DD");
+    let mut parts = text.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+    let (Some(Some(year)), Some(Some(month)), Some(Some(day))) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return Err(invalid()),
+    };
+    if !(1970..=9999).contains(&year) || !(1..=days_in_month).contains(&day) {
+        return Err(invalid());
+    }
+    // Howard Hinnant's days-from-civil algorithm, the inverse of format_utc's
+    let (year, month, day) = (i64::from(year), i64::from(month), i64::from(day));
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Ok(Since::Date(u64::try_from(days).unwrap_or(0) * 86_400))
+}
+
+/// Parses an `import --from` format by its label.
+fn parse_import_format(text: &str) -> Result<ImportFormat, String> {
+    ImportFormat::ALL.into_iter().find(|format| format.label() == text).ok_or_else(|| {
+        let labels: Vec<&str> = ImportFormat::ALL.iter().map(|f| f.label()).collect();
+        format!("invalid tool {text:?}: expected one of {}", labels.join(", "))
+    })
+}
+
+fn parse_clone_protocol(text: &str) -> Result<CloneProtocol, String> {
+    CloneProtocol::ALL.into_iter().find(|protocol| protocol.label() == text).ok_or_else(|| {
+        let labels: Vec<&str> = CloneProtocol::ALL.iter().map(|p| p.label()).collect();
+        format!("invalid protocol {text:?}: expected one of {}", labels.join(", "))
+    })
+}
+
+fn parse_unclean(text: &str) -> Result<Unclean, String> {
+    Unclean::ALL.into_iter().find(|policy| policy.label() == text).ok_or_else(|| {
+        let labels: Vec<&str> = Unclean::ALL.iter().map(|policy| policy.label()).collect();
+        format!("invalid policy {text:?}: expected one of {}", labels.join(", "))
+    })
+}
+
+fn parse_azure_project(text: &str) -> Result<(String, String), String> {
+    AzureDevOps::parse_project(text).map_err(|e| e.to_string())
+}
+
+/// Lists freshly cloned repos, origins at another URL, and clones that failed.
+fn print_checkouts(checkouts: &forge::Checkouts) {
+    for path in &checkouts.cloned {
+        println!("{}", format!("  cloned {}", path.display()).with(COLOR_GREEN));
+    }
+    for moved in &checkouts.mismatches {
+        let (path, origin, listed) = (moved.path.display(), &moved.origin, &moved.listed);
+        if moved.fixed {
+            let line = format!("  {path}: origin {origin} → {listed}");
+            println!("{}", line.with(COLOR_SUBTEXT));
+        } else {
+            let line = format!("  {path}: origin is {origin}, listed as {listed} (--fix-remotes)");
+            eprintln!("{}", line.with(COLOR_RED));
+        }
+    }
+    for warning in &checkouts.warnings {
+        eprintln!("{}", format!("  {warning}").with(COLOR_RED));
+    }
+}
+
+/// `groppy import`: prints the list's repos as config entries on stdout and
+/// what was left out on stderr.
+fn import_repos(from: ImportFormat, file: &Path, into: Option<&Path>) -> Result<()> {
+    let text = std::fs::read_to_string(file)
+        .with_context(|| format!("cannot read {}", file.display()))?;
+    let base = match (into, from) {
+        (Some(dir), _) => dir,
+        (None, ImportFormat::Mrconfig) => file.parent().unwrap_or(Path::new("")),
+        (None, ImportFormat::Vcstool) => Path::new(""),
+    };
+    let base = match base.as_os_str().is_empty() {
+        true => std::env::current_dir()?,
+        false => std::path::absolute(base)?,
+    };
+    let imported = import::parse(from, &text, &base)
+        .with_context(|| format!("invalid {} file {}", from.label(), file.display()))?;
+    for warning in &imported.warnings {
+        eprintln!("{}", format!("skipped {warning}").with(COLOR_RED));
+    }
+    print!("{}", import::to_toml(&imported.repos));
+    Ok(())
+}
+
+/// `groppy changelog`: prints the commits recorded in the state dir since
+/// `since` as Markdown on stdout.
+fn changelog(since: Since) -> Result<()> {
+    let dir = state::state_dir().context("no state dir: set XDG_STATE_HOME or HOME")?;
+    let log = PullLog::load(&dir);
+    let cutoff = match since {
+        Since::Date(secs) => secs,
+        Since::LastRun => log.last_run().context("no run has pulled commits yet")?,
+    };
+    print!("{}", report::changelog(&log.since(cutoff), cutoff));
+    Ok(())
+}
+
+/// Fetch settings compared by `groppy bench`: label, --single-branch, --ssh-multiplex.
+const BENCH_VARIANTS: [(&str, bool, bool); 4] = [
+    ("default", false, false),
+    ("--single-branch", true, false),
+    ("--ssh-multiplex", false, true),
+    ("--single-branch --ssh-multiplex", true, true),
+];
+
+/// `groppy bench`: fetches every repo once untimed, so each setting then
+/// measures the same steady state, then times fetch-only runs with each one.
+/// Worktrees and HEADs are never touched.
+fn bench(directories: &[PathBuf], jobs: Option<usize>, rounds: usize) -> Result<()> {
+    let dirs = match directories.is_empty() {
+        true => vec![PathBuf::from(".")],
+        false => directories.to_vec(),
+    };
+    let dirs: Vec<PathBuf> = dirs.iter().filter_map(|d| std::fs::canonicalize(d).ok()).collect();
+    let scanned = AtomicUsize::new(0);
+    let (repos, _) = discovery::scan_for_repositories(&dirs, &ScanOptions::default(), &scanned);
+    if repos.is_empty() {
+        anyhow::bail!("no repositories found");
+    }
+    let cpus = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let jobs = jobs.filter(|&n| n > 0).unwrap_or_else(|| auto_jobs(cpus, repos.len()));
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let ssh_control = SshControlDir::create()?;
+
+    let time_run = |single_branch: bool, multiplex: bool| {
+        let options_for = |_: &Path| UpdateOptions {
+            interaction: Interaction::Fail,
+            strategy: Strategy::FetchOnly,
+            single_branch,
+            ssh_control_dir: multiplex.then(|| ssh_control.0.clone()),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let results = run::update_all(&pool, &repos, &options_for, &RunControl::default(), &|_| {});
+        (started.elapsed(), results.iter().filter(|status| !status.success).count())
+    };
+
+    eprintln!("warming up: fetching {} repositories with {jobs} jobs", repos.len());
+    time_run(false, false);
+    let mut timings = Vec::new();
+    for (label, single_branch, multiplex) in BENCH_VARIANTS {
+        eprintln!("timing {label}");
+        let runs: Vec<(Duration, usize)> =
+            (0..rounds.max(1)).map(|_| time_run(single_branch, multiplex)).collect();
+        timings.push((label, runs.into_iter().min().unwrap_or_default()));
+    }
+    print!("{}", bench_table(&timings));
     Ok(())
 }
 
+/// The `groppy bench` results, one line per setting with the fastest marked.
+fn bench_table(timings: &[(&str, (Duration, usize))]) -> String {
+    let fastest = timings.iter().map(|(_, (took, _))| *took).min().unwrap_or_default();
+    let width = timings.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    timings
+        .iter()
+        .map(|(label, (took, failed))| {
+            let failed = match failed {
+                0 => String::new(),
+                n => format!("  {n} failed"),
+            };
+            let mark = if *took == fastest { "  fastest" } else { "" };
+            format!("{label:<width$}  {:>7.2}s{failed}{mark}\n", took.as_secs_f64())
+        })
+        .collect()
+}
+
+/// The forges named on the command line. Tokens come from `--token-file`
+/// (`cli_token`), else the config's `token_files` for the forge's host, else
+/// the environment.
+fn forges(
+    cli: &Cli,
+    cli_token: Option<&str>,
+    tokens: &BTreeMap<String, String>,
+) -> Vec<Box<dyn Forge>> {
+    let token = |var: &str| std::env::var(var).ok().filter(|token| !token.is_empty());
+    let from_file = |host: &str| cli_token.or(tokens.get(host).map(String::as_str));
+    let from_file = |host: &str| from_file(host).map(str::to_string);
+    let mut forges: Vec<Box<dyn Forge>> = Vec::new();
+    if let (Some(base_url), Some(org)) = (&cli.gitea_url, &cli.gitea_org) {
+        forges.push(Box::new(Gitea {
+            base_url: base_url.clone(),
+            org: org.clone(),
+            token: from_file(url_host(base_url)).or_else(|| token("GITEA_TOKEN")),
+        }));
+    }
+    if let Some(org) = &cli.github_org {
+        forges.push(Box::new(GitHub {
+            org: org.clone(),
+            token: from_file("github.com")
+                .or_else(|| token("GITHUB_TOKEN"))
+                .or_else(|| token("GH_TOKEN")),
+            cache_dir: state::state_dir(),
+        }));
+    }
+    if let Some(workspace) = &cli.bitbucket_workspace {
+        let app_password = token("BITBUCKET_USERNAME").zip(token("BITBUCKET_APP_PASSWORD"));
+        let auth = match (from_file("bitbucket.org"), app_password) {
+            (Some(token), _) => Some(BitbucketAuth::Token(token)),
+            (None, Some((username, password))) => {
+                Some(BitbucketAuth::AppPassword { username, password })
+            }
+            (None, None) => token("BITBUCKET_TOKEN").map(BitbucketAuth::Token),
+        };
+        forges.push(Box::new(Bitbucket {
+            workspace: workspace.clone(),
+            auth,
+        }));
+    }
+    if let Some((organization, project)) = &cli.azure_project {
+        forges.push(Box::new(AzureDevOps {
+            organization: organization.clone(),
+            project: project.clone(),
+            pat: from_file("dev.azure.com")
+                .or_else(|| token("AZURE_DEVOPS_PAT"))
+                .or_else(|| token("AZURE_DEVOPS_EXT_PAT")),
+        }));
+    }
+    if let Some(host) = &cli.gitolite {
+        forges.push(Box::new(Gitolite { host: host.clone() }));
+    }
+    if let Some(path) = &cli.manifest {
+        forges.push(Box::new(RepoManifest { path: path.clone() }));
+    }
+    forges
+}
+
+/// Host part of a URL such as `https://codeberg.org/api`.
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.split(':').next().unwrap_or_default()
+}
+
+/// Parses a duration such as `45`, `90s`, `15m`, `2h` or `1h30m` (bare numbers
+/// are seconds).
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {text:?}: expected e.g. 90s, 15m, 1h30m");
+    if let Ok(secs) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for c in text.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                digits.push(c);
+                continue;
+            }
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = digits.parse().map_err(|_| invalid())?;
+        total = n
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+    match digits.is_empty() && !text.is_empty() {
+        true => Ok(Duration::from_secs(total)),
+        false => Err(invalid()),
+    }
+}
+
+/// Directories to scan. Given directories are added to the config's roots
+/// (duplicates dropped later), or replace them with `--only-args`. With
+/// neither, the roots, else `.`; a repo list or forge alone scans nothing.
+/// `--add` directories (`added`) come on top of whichever applies.
+fn scan_dirs(
+    given: &[PathBuf],
+    roots: &[PathBuf],
+    listed: bool,
+    only_args: bool,
+    added: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut dirs = match (given.is_empty(), only_args) {
+        (false, true) => given.to_vec(),
+        (false, false) => roots.iter().chain(given).cloned().collect(),
+        (true, _) if listed => Vec::new(),
+        (true, false) if !roots.is_empty() => roots.to_vec(),
+        (true, _) => vec![PathBuf::from(".")],
+    };
+    dirs.extend_from_slice(added);
+    dirs
+}
+
+/// Picks a job count for network-bound updates.
+///
+/// Fetches mostly wait on the network, so oversubscribe the CPUs 4x (bounded to
+/// 4..=32 to stay polite to remotes), but never start more threads than repos.
+fn auto_jobs(cpus: usize, repos: usize) -> usize {
+    (cpus * 4).clamp(4, 32).min(repos.max(1))
+}
+
+/// Terminal tab/taskbar progress states, sent as OSC 9;4 escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Taskbar {
+    Clear,
+    Normal(usize), // Percent done
+    Error(usize),  // Percent done, with failures so far
+    Indeterminate, // Busy, total unknown
+}
+
+/// The OSC 9;4 sequence for a taskbar state.
+fn taskbar(state: Taskbar) -> String {
+    let (code, percent) = match state {
+        Taskbar::Clear => (0, 0),
+        Taskbar::Normal(percent) => (1, percent),
+        Taskbar::Error(percent) => (2, percent),
+        Taskbar::Indeterminate => (3, 0),
+    };
+    format!("\x1b]9;4;{code};{percent}\x07")
+}
+
+/// The OSC 2 sequence setting the window/tab title, e.g. `groppy 42/180`.
+fn window_title(title: &str) -> String {
+    format!("\x1b]2;{title}\x07")
+}
+
+/// Saves the terminal's title on its title stack (XTWINOPS), before groppy
+/// overwrites it.
+const TITLE_PUSH: &str = "\x1b[22;0t";
+
+/// Restores the title saved by [`TITLE_PUSH`].
+const TITLE_POP: &str = "\x1b[23;0t";
+
+/// What the guard prints to leave the terminal as it found it.
+fn restore_terminal() -> String {
+    format!("{}{TITLE_POP}", taskbar(Taskbar::Clear))
+}
+
+/// Clears the taskbar progress however the run ends: on drop (returns from
+/// `main`, errors included), from the panic hook, and on SIGINT/SIGTERM, which
+/// otherwise kill the process with the bar still up. The window title, which
+/// shows progress too, is restored the same way.
+struct TaskbarGuard;
+
+impl TaskbarGuard {
+    fn install() -> Result<TaskbarGuard> {
+        eprint!("{TITLE_PUSH}");
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            eprint!("{}", restore_terminal());
+            default_hook(info);
+        }));
+        #[cfg(unix)]
+        exit_on_signal(|| format!("\r\x1b[J{}", restore_terminal()))?;
+        Ok(TaskbarGuard)
+    }
+}
+
+/// Ends the run with exit code 128 + signal on SIGINT/SIGTERM, after turning
+/// terminal echo back on if a password prompt had it off and printing
+/// `restore`.
+#[cfg(unix)]
+fn exit_on_signal(restore: fn() -> String) -> Result<()> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM])?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            if ECHO_OFF.load(Ordering::Acquire) {
+                set_terminal_echo(true);
+            }
+            eprint!("{}", restore());
+            std::process::exit(128 + signal);
+        }
+    });
+    Ok(())
+}
+
+impl Drop for TaskbarGuard {
+    fn drop(&mut self) {
+        eprint!("{}", restore_terminal());
+    }
+}
+
+/// Socket directory for `--ssh-multiplex`, private to this run and removed
+/// when it ends; idle master connections exit on their own soon after.
+struct SshControlDir(PathBuf);
+
+impl SshControlDir {
+    fn create() -> Result<SshControlDir> {
+        let base = std::env::var_os("XDG_RUNTIME_DIR")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let dir = base.join(format!("groppy-ssh-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("cannot create {}", dir.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(SshControlDir(dir))
+    }
+}
+
+impl Drop for SshControlDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 /// Runs the color-cycling spinner animation on a dedicated thread.
 ///
 /// Displays a braille spinner character that cycles through Catppuccin colors,
-/// along with a progress counter showing completed/total repos. Also emits
-/// OSC 9;4 escape sequences for terminal tab progress indicators.
+/// along with `label` and a progress counter showing completed/total repos. Also emits
+/// OSC 9;4 escape sequences for terminal tab progress indicators, in the error
+/// state once anything in `counts` (completed, failed) has failed.
 ///
 /// Runs at ~12.5fps (80ms per frame) until the stop flag is set.
 fn run_spinner(
     stop: Arc<AtomicBool>,
-    completed: Arc<AtomicUsize>,
+    counts: (Arc<AtomicUsize>, Arc<AtomicUsize>),
     total: usize,
     output_lock: Arc<Mutex<()>>,
+    label: &str,
+    board: Option<Arc<StatusBoard>>,
 ) {
-    let mut frame = 0usize;
-    let mut color_idx = 0usize;
+    let (completed, failed) = counts;
     let mut tick = 0usize;
 
     while !stop.load(Ordering::Acquire) {
         let current = completed.load(Ordering::Relaxed);
 
-        let progress_percent = if total > 0 { (current * 100) / total } else { 0 };
-        let spinner_char = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
-        let color = SPINNER_COLORS[color_idx % SPINNER_COLORS.len()];
+        let progress_percent = (current * 100).checked_div(total).unwrap_or(0);
+        let state = match failed.load(Ordering::Relaxed) {
+            0 => Taskbar::Normal(progress_percent),
+            _ => Taskbar::Error(progress_percent),
+        };
 
         {
             let _lock = output_lock.lock().unwrap();
-            eprint!("\x1b]9;4;1;{progress_percent}\x07");
+            eprint!("{}", taskbar(state));
+            eprint!("{}", window_title(&format!("groppy {current}/{total}")));
             eprint!(
-                "\r\x1b[K{} Updating repositories... ({}/{})",
-                spinner_char.to_string().with(color),
+                "\r\x1b[J{} {label} ({}/{})",
+                spinner_glyph(tick),
                 current,
                 total
             );
+            // --show-workers: one line per busy worker below, then back up
+            let lines = board.as_ref().map(|board| board.worker_area()).unwrap_or_default();
+            for line in &lines {
+                eprint!("\n{}", line.as_str().with(COLOR_SUBTEXT));
+            }
+            if !lines.is_empty() {
+                eprint!("\x1b[{}A\r", lines.len());
+            }
         }
         let _ = std::io::stderr().flush();
 
-        frame += 1;
         tick += 1;
-        if tick.is_multiple_of(3) {
-            color_idx += 1;
+        std::thread::sleep(Duration::from_millis(80));
+    }
+}
+
+/// Live view of an update run, shared with the threads that print status dumps.
+struct StatusBoard {
+    total: usize,
+    completed: Arc<AtomicUsize>,
+    succeeded: Arc<AtomicUsize>,
+    failed: Arc<AtomicUsize>,
+    workers: Mutex<Vec<Option<(PathBuf, Instant)>>>, // Repo each pool thread is on, and since when
+    started: Instant,
+    output_lock: Arc<Mutex<()>>,
+    control: Arc<RunControl>, // Paused and resumed from the keyboard
+    progress: bool,           // A spinner line is on stderr and must be cleared first
+}
+
+impl StatusBoard {
+    /// Snapshot lines: counters and queue depth, then each busy worker's repo
+    /// and how long it has been there.
+    fn snapshot(&self) -> Vec<String> {
+        let workers = self.workers.lock().unwrap();
+        let busy = workers.iter().flatten().count();
+        let completed = self.completed.load(Ordering::Relaxed);
+        let paused = if self.control.is_paused() { " | paused" } else { "" };
+        let limit = self.control.jobs_limit().map(|n| format!(" | limit {n}")).unwrap_or_default();
+        let mut lines = vec![format!(
+            "status: {completed}/{} done | {} ok | {} fail | {busy} running | {} queued | \
+             elapsed: {}s{limit}{paused}",
+            self.total,
+            self.succeeded.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+            self.total.saturating_sub(completed + busy),
+            self.started.elapsed().as_secs()
+        )];
+        let width = terminal_width();
+        for (worker, slot) in workers.iter().enumerate() {
+            if let Some((path, since)) = slot {
+                let prefix = format!("  worker {worker}: ");
+                let suffix = format!(" ({}s)", since.elapsed().as_secs());
+                let room = width.saturating_sub(prefix.len() + suffix.len()).max(20);
+                lines.push(format!("{prefix}{}{suffix}", fit_path(path, room)));
+            }
         }
+        lines
+    }
 
-        std::thread::sleep(Duration::from_millis(80));
+    /// Lines for the live area under the spinner (`--show-workers`), e.g.
+    /// `  [3] ~/src/llvm — 02:41`; no more than fit on screen.
+    fn worker_area(&self) -> Vec<String> {
+        let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        self.workers
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter_map(|(worker, slot)| {
+                let (path, since) = slot.as_ref()?;
+                Some(worker_line(worker, path, since.elapsed(), usize::from(cols)))
+            })
+            .take(usize::from(rows).saturating_sub(2))
+            .collect()
+    }
+
+    /// Prints a snapshot to stderr between spinner frames.
+    fn dump(&self) {
+        self.print(&self.snapshot());
+    }
+
+    /// Prints lines to stderr, clearing the spinner line first.
+    fn print(&self, lines: &[String]) {
+        let _lock = self.output_lock.lock().unwrap();
+        if self.progress {
+            eprint!("\r\x1b[J");
+        }
+        for line in lines {
+            eprintln!("{}", line.as_str().with(COLOR_SUBTEXT));
+        }
     }
 }
 
-/// Formats a repository status into a colored string for terminal display.
-///
-/// Always returns a string. Callers decide whether to show it based on
-/// `status.success`, `status.files_changed`, and `verbose`.
-fn format_line(status: &RepoStatus) -> String {
-    let name = status
-        .path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| status.path.display().to_string());
+/// Prints a status dump on every SIGUSR1 (`kill -USR1 <pid>`) until exit.
+#[cfg(unix)]
+fn watch_status_signal(board: Arc<StatusBoard>) -> Result<()> {
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGUSR1])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            board.dump();
+        }
+    });
+    Ok(())
+}
 
-    let color = if !status.success {
-        COLOR_RED
-    } else if status.files_changed > 0 {
-        COLOR_GREEN
-    } else {
-        COLOR_SUBTEXT
-    };
+/// Handles commands entered on the terminal until input ends: Enter (or `s`)
+/// prints a status dump, `p` pauses dispatching new repos, `r` resumes. A line
+/// a [`CredentialPrompter`] is waiting for goes to it instead.
+fn watch_status_keys(
+    board: &StatusBoard,
+    input: impl std::io::BufRead,
+    terminal_lines: &Mutex<TerminalLines>,
+) {
+    for line in input.lines() {
+        if let Ok(line) = &line
+            && let Some(waiting) = terminal_lines.lock().unwrap().waiting.take()
+        {
+            let _ = waiting.send(line.clone());
+            continue;
+        }
+        match line.as_deref().map(str::trim) {
+            Ok("" | "s") => board.dump(),
+            Ok("p") if board.control.set_paused(true) => {
+                board.print(&["paused - repos in flight finish, r + Enter to resume".into()]);
+            }
+            Ok("r") if board.control.set_paused(false) => board.print(&["resumed".into()]),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    board.control.set_paused(false); // Nothing could resume the run any more
+    *terminal_lines.lock().unwrap() = TerminalLines { waiting: None, closed: true };
+}
 
-    format!("  {}: {}", name, status.message)
-        .with(color)
-        .to_string()
+/// Hand-off of terminal lines from [`watch_status_keys`] to a prompt.
+#[derive(Default)]
+struct TerminalLines {
+    waiting: Option<std::sync::mpsc::Sender<String>>, // Gets the next line entered
+    closed: bool,                                     // Input ended; nothing will come
+}
+
+/// Asks for HTTPS usernames and passwords on the terminal, once per host, when
+/// git's credential helpers and askpass have none. The password is typed with
+/// echo off.
+struct CredentialPrompter {
+    output_lock: Arc<Mutex<()>>,
+    lines: Arc<Mutex<TerminalLines>>,
+    accounts: Mutex<HashMap<String, Option<(String, String)>>>, // Answers by host; None: gave up
 }
 
-/// Removes duplicate paths from a Vec while preserving insertion order.
-/// Uses a HashSet for O(1) duplicate detection.
-fn unique_ordered(dirs: Vec<PathBuf>) -> Vec<PathBuf> {
-    let mut seen = std::collections::HashSet::new(); //  Track which paths we've encountered
-    let mut out = Vec::with_capacity(dirs.len());    // Pre-allocate output vector
-    for d in dirs {
-        if seen.insert(d.clone()) {
-            out.push(d); // Only add paths we haven't seen before
+impl AskCredentials for CredentialPrompter {
+    /// An empty username, or the end of input, gives up on the host for the run.
+    fn ask_credentials(&self, host: &str, url: &str) -> Option<(String, String)> {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(account) = accounts.get(host) {
+            return account.clone();
         }
+        let _lock = self.output_lock.lock().unwrap();
+        eprint!("\r\x1b[J  Username for {url} (empty to skip): ");
+        let username = self.next_line().filter(|name| !name.is_empty());
+        let account = username.and_then(|username| {
+            eprint!("  Password for {username}@{host}: ");
+            let password = {
+                let _echo_off = EchoOff::new();
+                self.next_line()
+            };
+            eprintln!();
+            Some((username, password?))
+        });
+        accounts.insert(host.to_string(), account.clone());
+        account
     }
-    out
 }
 
-/// Discovers Git repositories in the given directories.
-///
-///  For each directory:
-///   - If the directory itself is a Git repo, add it and skip subdirectories
-///   - Otherwise, scan immediate subdirectories for Git repos
-///
-/// Returns a deduplicated list of repository paths and a list of warning messages
-/// for any directories that could not be read (e.g. permission denied, unmounted).
-fn find_git_repositories(dirs: &[PathBuf]) -> (Vec<PathBuf>, Vec<String>) {
-    let mut repos = Vec::new();
-    let mut warnings = Vec::new();
-    for d in dirs {
-        let meta = match std::fs::metadata(d) {
-            Ok(m) => m,
-            Err(e) => {
-                warnings.push(format!("cannot stat {}: {e}", d.display()));
-                continue;
+impl CredentialPrompter {
+    /// The next line typed on the terminal, or None once input has ended.
+    fn next_line(&self) -> Option<String> {
+        let _ = std::io::stderr().flush();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        {
+            let mut lines = self.lines.lock().unwrap();
+            if lines.closed {
+                return None;
             }
-        };
-        if !meta.is_dir() {
-            continue; // Skip non-directory paths
+            lines.waiting = Some(sender);
         }
+        receiver.recv().ok().map(|line| line.trim_end_matches('\r').to_string())
+    }
+}
 
-        // Check if this directory is itself a Git repo
-        if is_git_repo(d) {
-            repos.push(d.clone());
-            continue; // Don't recurse into subdirectories
+/// Set while a password prompt has terminal echo off, so that signal exits can
+/// turn it back on.
+static ECHO_OFF: AtomicBool = AtomicBool::new(false);
+
+/// Terminal echo off while it lives: back on when dropped, whether the prompt
+/// returns or panics, and from [`exit_on_signal`] if the run is killed first.
+struct EchoOff;
+
+impl EchoOff {
+    fn new() -> EchoOff {
+        ECHO_OFF.store(true, Ordering::Release);
+        set_terminal_echo(false);
+        EchoOff
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        set_terminal_echo(true);
+        ECHO_OFF.store(false, Ordering::Release);
+    }
+}
+
+/// Turns terminal echo off or back on with `stty`, for typing passwords.
+fn set_terminal_echo(on: bool) {
+    #[cfg(unix)]
+    if let Ok(tty) = std::fs::File::open("/dev/tty") {
+        let mode = if on { "echo" } else { "-echo" };
+        let _ = std::process::Command::new("stty").arg(mode).stdin(tty).status();
+    }
+    #[cfg(not(unix))]
+    let _ = on;
+}
+
+/// Runs the discovery spinner until the stop flag is set.
+///
+/// Shows how many directories the walker has visited so far, since the total
+/// is unknown until the scan finishes.
+fn run_scan_spinner(stop: Arc<AtomicBool>, scanned: Arc<AtomicUsize>, output_lock: Arc<Mutex<()>>) {
+    let mut tick = 0usize;
+
+    while !stop.load(Ordering::Acquire) {
+        let current = scanned.load(Ordering::Relaxed);
+        {
+            let _lock = output_lock.lock().unwrap();
+            eprint!("{}", taskbar(Taskbar::Indeterminate));
+            eprint!(
+                "\r\x1b[J{} Scanning directories... ({current} scanned)",
+                spinner_glyph(tick)
+            );
         }
+        let _ = std::io::stderr().flush();
 
-        // Scan immediate subdirectories for Git repos
-        let entries = match std::fs::read_dir(d) {
-            Ok(e) => e,
-            Err(e) => {
-                warnings.push(format!("cannot read {}: {e}", d.display()));
-                continue;
-            }
-        };
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() && is_git_repo(&path) {
-                repos.push(path); // Found a Git repo in subdirectory
-            }
-        }
+        tick += 1;
+        std::thread::sleep(Duration::from_millis(80));
     }
-    (unique_ordered(repos), warnings)
 }
 
-/// Checks whether a path contains a valid Git repository.
-/// Uses gitoxide's open function which validates the .git structure.
-fn is_git_repo(path: &Path) -> bool {
-    gix::open(path).is_ok()
+/// Returns the colored spinner character for an animation tick.
+/// The frame advances every tick; the color changes every 3 ticks.
+fn spinner_glyph(tick: usize) -> String {
+    let spinner_char = SPINNER_FRAMES[tick % SPINNER_FRAMES.len()];
+    let color = SPINNER_COLORS[(tick / 3) % SPINNER_COLORS.len()];
+    spinner_char.with(color).to_string()
 }
 
-///  Top-level wrapper for repository updates that converts errors into RepoStatus.
-/// Ensures that any error from try_update_repository is caught and reported gracefully.
-fn update_repository(path: &Path) -> RepoStatus {
-    match try_update_repository(path) {
-        Ok(status) => status,
-        Err(e) => RepoStatus {
-            path: path.to_path_buf(),
-            success: false,
-            message: e.to_string(),
-            files_changed: 0,
-        },
-    }
+/// Terminal width in columns; 80 when it cannot be measured (not a terminal).
+fn terminal_width() -> usize {
+    crossterm::terminal::size().map_or(80, |(cols, _)| usize::from(cols))
 }
 
-/// Attempts to update a single Git repository via fetch + fast-forward.
-///
-/// The update process:
-///   1. Open the repository with gitoxide
-///   2. Check for local changes (bail if dirty)
-///   3. Verify we're on a branch (bail if detached HEAD)
-///   4. Fetch from the default remote using gitoxide's connect/prepare/receive pipeline
-///   5. Find the updated remote tracking ref for our branch
-///   6. Fast-forward the local branch ref to the new commit
-///   7. Checkout the updated tree using `git checkout --force HEAD`
-///   8. Count changed files by diffing the old and new tree
-fn try_update_repository(path: &Path) -> Result<RepoStatus> {
-    // Open the repository using gitoxide
-    let repo = gix::open(path)?;
-
-    // Bail early if the working tree has local modifications
-    if repo.is_dirty()? {
-        return Ok(RepoStatus {
-            path: path.to_path_buf(),
-            success: false,
-            message: "Repository has local changes - skipping update".to_string(),
-            files_changed: 0,
-        });
-    }
+/// A repo's directory name for result and plan lines (the whole path for `/`),
+/// middle-truncated to half the terminal width so lines stay readable.
+fn repo_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    truncate_middle(&name, (terminal_width() / 2).max(20))
+}
 
-    // Get the current HEAD reference (must be a branch, not detached)
-    let mut head_ref = match repo.head_ref()? {
-        Some(r) => r,
-        None => {
-            return Ok(RepoStatus {
-                path: path.to_path_buf(),
-                success: false,
-                message: "Detached HEAD state - skipping update".to_string(),
-                files_changed: 0,
-            });
-        }
-    };
+/// Shortens `text` to at most `max` characters by replacing its middle with `…`.
+fn truncate_middle(text: &str, max: usize) -> String {
+    let len = text.chars().count();
+    if len <= max {
+        return text.to_string();
+    }
+    let keep = max.saturating_sub(1); // Room left beside the ellipsis
+    let head: String = text.chars().take(keep / 2).collect();
+    let tail: String = text.chars().skip(len - (keep - keep / 2)).collect();
+    format!("{head}…{tail}")
+}
 
-    let old_id = head_ref.id().detach();
-    let head_name = head_ref.name().as_bstr().to_string();
+/// A `--show-workers` line, fitted to `width` columns.
+fn worker_line(worker: usize, path: &Path, elapsed: Duration, width: usize) -> String {
+    let prefix = format!("  [{worker}] ");
+    let secs = elapsed.as_secs();
+    let suffix = format!(" — {:02}:{:02}", secs / 60, secs % 60);
+    let room = width.saturating_sub(prefix.len() + suffix.chars().count()).max(20);
+    format!("{prefix}{}{suffix}", fit_path(path, room))
+}
 
-    // Resolve the default fetch remote (usually "origin")
-    let remote = repo.find_default_remote(gix::remote::Direction::Fetch);
-    let remote = match remote {
-        Some(Ok(r)) => r,
-        Some(Err(e)) => {
-            return Ok(RepoStatus {
-                path: path.to_path_buf(),
-                success: false,
-                message: format!("Remote error: {e}"),
-                files_changed: 0,
-            });
-        }
-        None => {
-            return Ok(RepoStatus {
-                path: path.to_path_buf(),
-                success: false,
-                message: "No remote configured".to_string(),
-                files_changed: 0,
-            });
-        }
+/// Shows `path` in at most `max` characters for progress lines: the home
+/// directory becomes `~`, then whole middle components give way to `…`
+/// (`~/work/…/service-foo`), and only then characters. Reports and logs keep
+/// full paths.
+fn fit_path(path: &Path, max: usize) -> String {
+    let home = std::env::var_os("HOME").filter(|h| !h.is_empty()).map(PathBuf::from);
+    let text = match home.as_deref().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
     };
+    if text.chars().count() <= max {
+        return text;
+    }
+    let parts: Vec<&str> = text.split('/').collect();
+    let head = parts[..2.min(parts.len())].join("/"); // `~/work` or `/src`
+    let fitting = (1..=parts.len().saturating_sub(3))
+        .map(|tail| format!("{head}/…/{}", parts[parts.len() - tail..].join("/")))
+        .take_while(|candidate| candidate.chars().count() <= max)
+        .last();
+    fitting.unwrap_or_else(|| truncate_middle(&text, max))
+}
 
-    // Fetch from remote using gitoxide's three-step pipeline:
-    // connect → prepare_fetch → receive
-    let outcome = remote
-        .connect(gix::remote::Direction::Fetch)?
-        .prepare_fetch(gix::progress::Discard, Default::default())?
-        .receive(gix::progress::Discard, &AtomicBool::new(false))?;
-
-    //  Find the new commit ID from the fetch outcome's ref mappings
-    let new_id = find_updated_target(&outcome, &head_name);
-
-    let new_id = match new_id {
-        Some(id) => id,
-        None => {
-            // No mapping found means nothing changed for our branch
-            return Ok(RepoStatus {
-                path: path.to_path_buf(),
-                success: true,
-                message: "Already up to date".to_string(),
-                files_changed: 0,
-            });
-        }
+/// Formats a repository status into a colored string for terminal display.
+///
+/// Always returns a string. Callers decide whether to show it based on
+/// `status.success`, `status.files_changed`, and `verbose`.
+fn format_line(status: &RepoStatus, icons: Option<Icons>) -> String {
+    let name = repo_name(&status.path);
+    let color = line_color(status);
+
+    let head = status.head.as_ref().map(|head| format!(" ({})", report::describe_head(head)));
+    let divergence = status.divergence().map(|d| format!(" [{d}]")).unwrap_or_default();
+    let icon = icons.map(|icons| format!("{} ", icons.glyph(status))).unwrap_or_default();
+    format!("  {icon}{}: {}{}{divergence}", name, status.message, head.unwrap_or_default())
+        .with(color)
+        .to_string()
+}
+
+/// `--timestamps` prefix: `[HH:MM:SS]` of `now` (Unix seconds, UTC) for
+/// `clock`, `[+HH:MM:SS]` of `elapsed` for `elapsed`.
+fn timestamp(kind: Timestamps, now: u64, elapsed: Duration) -> String {
+    let (prefix, secs) = match kind {
+        Timestamps::Clock => ("", now % 86_400),
+        Timestamps::Elapsed => ("+", elapsed.as_secs()),
     };
+    format!("[{prefix}{:02}:{:02}:{:02}]", secs / 3600, secs / 60 % 60, secs % 60)
+}
 
-    // Compare old and new commit IDs
-    if new_id == old_id {
-        return Ok(RepoStatus {
-            path: path.to_path_buf(),
-            success: true,
-            message: "Already up to date".to_string(),
-            files_changed: 0,
-        });
+/// Result line color: red for failures, green when files changed, else subtext.
+fn line_color(status: &RepoStatus) -> Color {
+    if !status.success {
+        COLOR_RED
+    } else if status.files_changed > 0 {
+        COLOR_GREEN
+    } else {
+        COLOR_SUBTEXT
     }
+}
 
-    // Fast-forward: update the local branch ref to point at the new commit.
-    // set_target_id uses PreviousValue::MustExistAndMatch internally, so it
-    // fails atomically if the ref moved since we read it.
-    head_ref.set_target_id(new_id, "groppy: fast-forward")?;
-
-    // Walk the diff once: count changed files and apply deletions immediately.
-    // Deletions are unlinked inline rather than collected into a Vec and iterated
-    // again — gix::worktree::state::checkout only writes entries present in the new
-    // index and does not remove files that disappeared.
-    let workdir = repo
-        .workdir()
-        .ok_or_else(|| anyhow::anyhow!("bare repo has no workdir"))?
-        .to_owned();
-
-    let old_tree = repo.find_object(old_id)?.peel_to_tree()?;
-    let new_tree_obj = repo.find_object(new_id)?.peel_to_tree()?;
-    let mut files_changed: usize = 0;
-
-    old_tree
-        .changes()?
-        .options(|o| { o.track_path(); })
-        .for_each_to_obtain_tree(&new_tree_obj, |change| {
-            use gix::object::tree::diff::Change;
-            match change {
-                Change::Deletion { location, .. } => {
-                    let _ = std::fs::remove_file(
-                        workdir.join(gix::path::from_bstr(location).as_ref()),
-                    );
-                    files_changed += 1;
-                }
-                Change::Addition { .. } | Change::Modification { .. } | Change::Rewrite { .. } => {
-                    files_changed += 1;
-                }
-            }
-            Ok::<_, std::convert::Infallible>(gix::object::tree::diff::Action::Continue(()))
-        })?;
-
-    // Build the new index from the target tree and check out all modified/added entries.
-    // Using overwrite_existing mirrors `git checkout --force HEAD`: existing files are
-    // overwritten without complaint.
-    let mut index = repo.index_from_tree(&new_tree_obj.id)?;
-    let mut opts = repo.checkout_options(
-        gix::worktree::stack::state::attributes::Source::WorktreeThenIdMapping,
-    )?;
-    opts.overwrite_existing = true;
-
-    let checkout_result = gix::worktree::state::checkout(
-        &mut index,
-        &workdir,
-        repo.objects.clone().into_arc()?,
-        &gix::progress::Discard,
-        &gix::progress::Discard,
-        &AtomicBool::new(false),
-        opts,
-    );
-
-    if let Err(e) = checkout_result {
-        let msg = match head_ref.set_target_id(old_id, "groppy: revert failed fast-forward") {
-            Ok(_) => format!("Checkout failed: {e}"),
-            Err(revert_err) => format!("Checkout failed: {e}; revert also failed: {revert_err}"),
-        };
-        return Ok(RepoStatus {
-            path: path.to_path_buf(),
-            success: false,
-            message: msg,
-            files_changed: 0,
-        });
+/// Category of a repo that neither changed nor failed, for `--show-skipped`:
+/// its bucket label, or `unchanged`. None for updated and failed repos.
+fn skipped_category(status: &RepoStatus) -> Option<&'static str> {
+    match status.outcome() {
+        "updated" | "failed" => None,
+        "ok" if status.files_changed > 0 => None,
+        "ok" => Some("unchanged"),
+        bucket => Some(bucket),
     }
-
-    // Persist the updated index so subsequent git commands and status checks see it
-    index.write(Default::default())?;
-
-    // Return success with the count of changed files
-    Ok(RepoStatus {
-        path: path.to_path_buf(),
-        success: true,
-        message: if files_changed > 0 {
-            format!("Updated successfully - {files_changed} files changed")
-        } else {
-            "Updated successfully".to_string()
-        },
-        files_changed,
-    })
 }
 
+/// Formats a `--show-skipped` line: the repo's message tagged with its
+/// category. Callers dim it.
+fn skipped_line(status: &RepoStatus, icons: Option<Icons>) -> String {
+    let name = repo_name(&status.path);
+    let category = skipped_category(status).unwrap_or_default();
+    let divergence = status.divergence().map(|d| format!(" [{d}]")).unwrap_or_default();
+    let icon = icons.map(|icons| format!("{} ", icons.glyph(status))).unwrap_or_default();
+    format!("  {icon}{name}: {} ({category}){divergence}", status.message)
+}
 
+/// Checks every repository with `plan::plan_all`, behind a spinner when
+/// `progress` is set.
+/// Results keep the order of `repos`.
+fn plan_repositories(
+    pool: &rayon::ThreadPool,
+    repos: &[PathBuf],
+    options_for: &(dyn Fn(&Path) -> UpdateOptions + Sync),
+    output_lock: &Arc<Mutex<()>>,
+    progress: bool,
+) -> Vec<PlannedRepo> {
+    let checked = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(!progress));
+    let spinner_handle = {
+        let (stop, checked, lock, total) =
+            (stop.clone(), checked.clone(), output_lock.clone(), repos.len());
+        std::thread::spawn(move || {
+            let counts = (checked, Arc::new(AtomicUsize::new(0)));
+            run_spinner(stop, counts, total, lock, "Checking repositories...", None);
+        })
+    };
+    let plan = plan::plan_all(pool, repos, options_for, &|_| {
+        checked.fetch_add(1, Ordering::Relaxed);
+    });
+    stop.store(true, Ordering::Release);
+    let _ = spinner_handle.join();
+    if progress {
+        eprint!("\r\x1b[J{}", taskbar(Taskbar::Clear));
+    }
+    plan
+}
 
-/// Finds the updated commit ID for our branch in the fetch outcome.
-///
-/// Scans the ref mappings from the fetch to find one whose local tracking ref
-/// (e.g., refs/remotes/origin/main) matches our branch name (e.g., refs/heads/main).
-/// Returns the remote commit ID if found, or None if no update was fetched.
-fn find_updated_target(
-    outcome: &gix::remote::fetch::Outcome,
-    head_name: &str,
-) -> Option<gix::ObjectId> {
-    //  Extract just the branch name (e.g., "main" from "refs/heads/main")
-    let branch_short = head_name.strip_prefix("refs/heads/").unwrap_or(head_name);
-    let tracking_suffix = format!("/{branch_short}");
-
-    // Iterate through all ref mappings from the fetch
-    for mapping in &outcome.ref_map.mappings {
-        if let Some(ref local) = mapping.local {
-            let local_bytes: &[u8] = local.as_ref();
-            let local_str = local_bytes.to_str().unwrap_or("");
-            // Match tracking refs that end with our branch name
-            if local_str.ends_with(&tracking_suffix)
-                && let Some(id) = mapping.remote.as_id()
-            {
-                return Some(id.to_owned());
-            }
+/// Prints one line per repository that would not be left alone (every repo
+/// with `verbose`), then the counts per action.
+fn print_plan(plan: &[PlannedRepo], verbose: bool) {
+    for planned in plan {
+        if planned.action == PlanAction::UpToDate && !verbose {
+            continue;
         }
+        let name = repo_name(&planned.path);
+        let color = match planned.action {
+            PlanAction::FastForward | PlanAction::Integrate | PlanAction::Fetch => COLOR_GREEN,
+            PlanAction::Diverged | PlanAction::Error => COLOR_RED,
+            _ => COLOR_SUBTEXT,
+        };
+        let line = if planned.detail.is_empty() {
+            format!("  {name}: {}", planned.action.label())
+        } else {
+            format!("  {name}: {} ({})", planned.action.label(), planned.detail)
+        };
+        println!("{}", line.with(color));
     }
-    None //  No matching ref found
+    println!("\nplan: {}", plan_summary(plan));
 }
 
+/// Counts per action, e.g. `12 fast-forward | 3 up to date`, skipping zeros.
+fn plan_summary(plan: &[PlannedRepo]) -> String {
+    let parts: Vec<String> = PlanAction::ALL
+        .iter()
+        .map(|&action| (action, plan.iter().filter(|p| p.action == action).count()))
+        .filter(|&(_, n)| n > 0)
+        .map(|(action, n)| format!("{n} {}", action.label()))
+        .collect();
+    if parts.is_empty() {
+        "nothing to check".to_string()
+    } else {
+        parts.join(" | ")
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use std::process::Command;
-
-    // Helper: init a git repo with one commit via git CLI
-    fn init_repo_with_commit(path: &Path) {
-        fs::create_dir_all(path).unwrap();
-        Command::new("git")
-            .args(["init"])
-            .current_dir(path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["config", "user.name", "test"])
-            .current_dir(path)
-            .output()
-            .unwrap();
-        fs::write(path.join("README.md"), "# test\n").unwrap();
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["commit", "-m", "initial"])
-            .current_dir(path)
-            .output()
-            .unwrap();
-    }
-
-    // Helper: init a bare repo
-    fn init_bare_repo(path: &Path) {
-        fs::create_dir_all(path).unwrap();
-        Command::new("git")
-            .args(["init", "--bare"])
-            .current_dir(path)
-            .output()
-            .unwrap();
-    }
 
     // ────────────────────────────────────────────────────────────
-    // unique_ordered
+    // StatusBoard
     // ────────────────────────────────────────────────────────────
 
-    #[test]
-    fn test_unique_ordered_no_duplicates() {
-        let input = vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")];
-        let out = unique_ordered(input);
-        assert_eq!(out.len(), 3);
+    fn board(
+        total: usize,
+        completed: usize,
+        workers: Vec<Option<(PathBuf, Instant)>>,
+    ) -> StatusBoard {
+        StatusBoard {
+            total,
+            completed: Arc::new(AtomicUsize::new(completed)),
+            succeeded: Arc::new(AtomicUsize::new(completed)),
+            failed: Arc::new(AtomicUsize::new(0)),
+            workers: Mutex::new(workers),
+            started: Instant::now(),
+            output_lock: Arc::new(Mutex::new(())),
+            control: Arc::new(RunControl::default()),
+            progress: false,
+        }
     }
 
     #[test]
-    fn test_unique_ordered_with_duplicates() {
-        let input = vec![
-            PathBuf::from("a"),
-            PathBuf::from("b"),
-            PathBuf::from("a"),
-            PathBuf::from("c"),
-            PathBuf::from("b"),
-        ];
-        let out = unique_ordered(input);
-        assert_eq!(out, vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")]);
+    fn test_status_snapshot() {
+        let since = Instant::now() - Duration::from_secs(12);
+        let board = board(10, 3, vec![None, Some((PathBuf::from("/src/slow"), since)), None]);
+        let lines = board.snapshot();
+        assert_eq!(
+            lines[0],
+            "status: 3/10 done | 3 ok | 0 fail | 1 running | 6 queued | elapsed: 0s"
+        );
+        assert_eq!(lines[1..], ["  worker 1: /src/slow (12s)".to_string()]);
     }
 
     #[test]
-    fn test_unique_ordered_all_same() {
-        let input = vec![PathBuf::from("x"), PathBuf::from("x"), PathBuf::from("x")];
-        let out = unique_ordered(input);
-        assert_eq!(out.len(), 1);
+    fn test_watch_status_keys_stops_at_end_of_input() {
+        let board = board(1, 0, vec![None]);
+        // Returns once the input is exhausted; dumps go to stderr
+        watch_status_keys(&board, std::io::Cursor::new("\ns\nother\n"), &Mutex::default());
     }
 
     #[test]
-    fn test_unique_ordered_empty() {
-        let out = unique_ordered(vec![]);
-        assert!(out.is_empty());
+    fn test_credential_prompt_takes_lines_from_key_reader() {
+        let lines = Arc::new(Mutex::new(TerminalLines::default()));
+        let prompter = CredentialPrompter {
+            output_lock: Arc::new(Mutex::new(())),
+            lines: lines.clone(),
+            accounts: Mutex::default(),
+        };
+        let board = board(1, 0, vec![None]);
+        let answers = std::thread::scope(|s| {
+            let url = "https://git.example/a";
+            let asking = s.spawn(|| prompter.ask_credentials("git.example", url));
+            while lines.lock().unwrap().waiting.is_none() {
+                std::thread::yield_now();
+            }
+            // The key reader hands the username over, then input ends
+            watch_status_keys(&board, std::io::Cursor::new("ann\n"), &lines);
+            asking.join().unwrap()
+        });
+        assert_eq!(answers, None, "input ended before the password");
+        assert_eq!(prompter.ask_credentials("git.example", "https://git.example/b"), None);
+        assert!(!board.control.is_paused());
     }
 
-    // ────────────────────────────────────────────────────────────
-    // is_git_repo
-    // ────────────────────────────────────────────────────────────
-
     #[test]
-    fn test_is_git_repo_valid() {
-        let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        assert!(is_git_repo(tmp.path()));
+    fn test_echo_off_is_flagged_until_dropped() {
+        let echo_off = EchoOff::new();
+        assert!(ECHO_OFF.load(Ordering::Acquire), "signal exits must restore echo");
+        drop(echo_off);
+        assert!(!ECHO_OFF.load(Ordering::Acquire));
     }
 
     #[test]
-    fn test_is_git_repo_not_a_repo() {
-        let tmp = tempfile::tempdir().unwrap();
-        assert!(!is_git_repo(tmp.path()));
-    }
+    fn test_pause_and_resume_keys() {
+        let board = board(4, 1, vec![None]);
+        watch_status_keys(&board, std::io::Cursor::new("p\n"), &Mutex::default());
+        assert!(!board.control.is_paused(), "end of input must not leave the run paused");
 
-    #[test]
-    fn test_is_git_repo_nonexistent() {
-        assert!(!is_git_repo(Path::new("/nonexistent/path/to/repo")));
+        board.control.set_paused(true);
+        assert!(board.snapshot()[0].ends_with("elapsed: 0s | paused"));
     }
 
     // ────────────────────────────────────────────────────────────
-    // find_git_repositories
+    // parse_duration
     // ────────────────────────────────────────────────────────────
 
     #[test]
-    fn test_find_git_repos_direct_is_repo() {
-        let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        let (repos, _) = find_git_repositories(&[tmp.path().to_path_buf()]);
-        assert_eq!(repos.len(), 1);
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
     }
 
     #[test]
-    fn test_find_git_repos_subdir_repos() {
-        let parent = tempfile::tempdir().unwrap();
-        let repo1 = parent.path().join("repo1");
-        let repo2 = parent.path().join("repo2");
-        let not_repo = parent.path().join("notrepo");
-        fs::create_dir_all(&not_repo).unwrap();
-        init_repo_with_commit(&repo1);
-        init_repo_with_commit(&repo2);
-        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()]);
-        assert_eq!(repos.len(), 2);
+    fn test_parse_duration_rejects_garbage() {
+        for text in ["", "m", "10x", "1h30", "-5s", "1.5h"] {
+            assert!(parse_duration(text).is_err(), "accepted {text:?}");
+        }
     }
 
     #[test]
-    fn test_find_git_repos_empty_dir() {
-        let tmp = tempfile::tempdir().unwrap();
-        let (repos, _) = find_git_repositories(&[tmp.path().to_path_buf()]);
-        assert!(repos.is_empty());
+    fn test_parse_unclean() {
+        assert_eq!(parse_unclean("update-anyway"), Ok(Unclean::UpdateAnyway));
+        assert_eq!(parse_unclean("fail"), Ok(Unclean::Fail));
+        assert!(parse_unclean("ignore").unwrap_err().contains("skip, stash"));
     }
 
+    // ────────────────────────────────────────────────────────────
+    // scan_dirs
+    // ────────────────────────────────────────────────────────────
+
     #[test]
-    fn test_find_git_repos_nonexistent_dir() {
-        let (repos, _) = find_git_repositories(&[PathBuf::from("/nonexistent/path")]);
-        assert!(repos.is_empty());
+    fn test_scan_dirs() {
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let roots = paths(&["/src", "/work"]);
+        let given = paths(&["/tmp/one-off"]);
+        let union = paths(&["/src", "/work", "/tmp/one-off"]);
+        assert_eq!(scan_dirs(&given, &roots, false, false, &[]), union);
+        assert_eq!(scan_dirs(&given, &roots, false, true, &[]), given);
+        assert_eq!(scan_dirs(&[], &roots, false, false, &[]), roots);
+        assert_eq!(scan_dirs(&[], &roots, false, true, &[]), paths(&["."]));
+        assert_eq!(scan_dirs(&[], &[], false, false, &[]), paths(&["."]));
+        assert!(scan_dirs(&[], &roots, true, false, &[]).is_empty());
+        assert_eq!(scan_dirs(&given, &[], true, false, &[]), given);
+
+        let added = paths(&["/tmp/extra"]);
+        assert_eq!(scan_dirs(&[], &[], false, false, &added), paths(&[".", "/tmp/extra"]));
+        let roots_added = paths(&["/src", "/work", "/tmp/extra"]);
+        assert_eq!(scan_dirs(&[], &roots, false, false, &added), roots_added);
+        let given_added = paths(&["/tmp/one-off", "/tmp/extra"]);
+        assert_eq!(scan_dirs(&given, &roots, false, true, &added), given_added);
+        assert_eq!(scan_dirs(&[], &roots, true, false, &added), added);
     }
 
+    // ────────────────────────────────────────────────────────────
+    // auto_jobs
+    // ────────────────────────────────────────────────────────────
+
     #[test]
-    fn test_find_git_repos_file_path() {
-        let tmp = tempfile::tempdir().unwrap();
-        let fpath = tmp.path().join("file.txt");
-        fs::write(&fpath, "hi").unwrap();
-        let (repos, _) = find_git_repositories(&[fpath]);
-        assert!(repos.is_empty());
+    fn test_auto_jobs_scales_with_cpus() {
+        assert_eq!(auto_jobs(2, 100), 8);
+        assert_eq!(auto_jobs(4, 100), 16);
     }
 
     #[test]
-    fn test_find_git_repos_deduplication() {
-        let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        let p = tmp.path().to_path_buf();
-        let (repos, _) = find_git_repositories(&[p.clone(), p]);
-        assert_eq!(repos.len(), 1);
+    fn test_auto_jobs_bounds() {
+        assert_eq!(auto_jobs(1, 100), 4);
+        assert_eq!(auto_jobs(64, 100), 32);
     }
 
     #[test]
-    fn test_find_git_repos_skips_subdir_if_parent_is_repo() {
-        let parent = tempfile::tempdir().unwrap();
-        init_repo_with_commit(parent.path());
-        let nested = parent.path().join("nested");
-        init_repo_with_commit(&nested);
-        let (repos, _) = find_git_repositories(&[parent.path().to_path_buf()]);
-        assert_eq!(repos.len(), 1);
+    fn test_auto_jobs_capped_by_repo_count() {
+        assert_eq!(auto_jobs(8, 3), 3);
+        assert_eq!(auto_jobs(8, 0), 1);
     }
 
     // ────────────────────────────────────────────────────────────
@@ -786,8 +2548,9 @@ mod tests {
             success: true,
             message: "Updated - 5 files changed".to_string(),
             files_changed: 5,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(line.contains("myrepo"));
     }
 
@@ -799,8 +2562,9 @@ mod tests {
             success: true,
             message: "Already up to date".to_string(),
             files_changed: 0,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(line.contains("myrepo"));
     }
 
@@ -811,8 +2575,9 @@ mod tests {
             success: true,
             message: "Already up to date".to_string(),
             files_changed: 0,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(line.contains("Already up to date"));
     }
 
@@ -823,8 +2588,9 @@ mod tests {
             success: false,
             message: "open repo: error".to_string(),
             files_changed: 0,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(line.contains("open repo: error"));
     }
 
@@ -835,11 +2601,62 @@ mod tests {
             success: false,
             message: "error".to_string(),
             files_changed: 0,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(line.contains("myrepo"));
     }
 
+    #[test]
+    fn test_format_line_shows_ahead_behind() {
+        let status = RepoStatus {
+            path: PathBuf::from("/home/user/myrepo"),
+            success: true,
+            message: "Already up to date".to_string(),
+            ahead_behind: Some((2, 0)),
+            ..Default::default()
+        };
+        assert!(format_line(&status, None).contains("Already up to date [ahead 2]"));
+    }
+
+    #[test]
+    fn test_format_line_shows_head() {
+        let status = RepoStatus {
+            path: PathBuf::from("/home/user/myrepo"),
+            success: true,
+            message: "Updated - 1 files changed".to_string(),
+            head: Some(groppy_core::HeadCommit {
+                id: gix::ObjectId::empty_tree(gix::hash::Kind::Sha1),
+                author: "Ann".to_string(),
+                seconds: 0,
+            }),
+            ..Default::default()
+        };
+        let line = format_line(&status, None);
+        assert!(line.contains("changed (4b825dc by Ann, 1970-01-01 00:00:00 UTC)"), "{line}");
+    }
+
+    #[test]
+    fn test_format_line_icons() {
+        let mut status = RepoStatus {
+            path: PathBuf::from("/home/user/myrepo"),
+            success: true,
+            message: "Already up to date".to_string(),
+            ..Default::default()
+        };
+        assert!(format_line(&status, Some(Icons::Unicode)).contains("  ∅ myrepo: Already"));
+        assert!(format_line(&status, Some(Icons::Ascii)).contains("  = myrepo: Already"));
+        status.bucket = Some(Bucket::Dirty);
+        assert_eq!(Icons::Unicode.glyph(&status), "±");
+        status.bucket = None;
+        status.success = false;
+        assert_eq!(Icons::Ascii.glyph(&status), "x");
+        status.success = true;
+        status.old_id = Some(gix::ObjectId::null(gix::hash::Kind::Sha1));
+        status.new_id = Some(gix::ObjectId::empty_tree(gix::hash::Kind::Sha1));
+        assert_eq!(Icons::Unicode.glyph(&status), "✓");
+    }
+
     #[test]
     fn test_format_line_root_path_fallback() {
         let status = RepoStatus {
@@ -847,207 +2664,470 @@ mod tests {
             success: false,
             message: "error".to_string(),
             files_changed: 0,
+            ..Default::default()
         };
-        let line = format_line(&status);
+        let line = format_line(&status, None);
         assert!(!line.is_empty());
     }
 
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("service-foo", 20), "service-foo");
+        assert_eq!(truncate_middle("abcdefghij", 7), "abc…hij");
+        assert_eq!(truncate_middle("abcdefghij", 6), "ab…hij");
+    }
+
+    #[test]
+    fn test_fit_path_drops_middle_components() {
+        let path = Path::new("/srv/work/team/platform/backend/service-foo");
+        assert_eq!(fit_path(path, 100), "/srv/work/team/platform/backend/service-foo");
+        assert_eq!(fit_path(path, 30), "/srv/…/backend/service-foo");
+        assert_eq!(fit_path(path, 25), "/srv/…/service-foo");
+        assert_eq!(fit_path(path, 18), "/srv/…/service-foo");
+        assert_eq!(fit_path(path, 10), "/srv…e-foo");
+    }
+
+    #[test]
+    fn test_worker_line() {
+        let path = Path::new("/srv/work/team/platform/backend/llvm");
+        let took = Duration::from_secs(161);
+        let full = "  [3] /srv/work/team/platform/backend/llvm — 02:41";
+        assert_eq!(worker_line(3, path, took, 80), full);
+        assert_eq!(worker_line(3, path, took, 40), "  [3] /srv/…/backend/llvm — 02:41");
+    }
+
     // ────────────────────────────────────────────────────────────
-    //  update_repository
+    // dirty-repo prompt
     // ────────────────────────────────────────────────────────────
 
+    fn scripted_prompter(answers: &str) -> Arc<Prompter> {
+        Arc::new(Prompter {
+            output_lock: Arc::new(Mutex::new(())),
+            input: Mutex::new(Box::new(std::io::Cursor::new(answers.to_string().into_bytes()))),
+            control: Arc::new(RunControl::default()),
+        })
+    }
+
+    #[test]
+    fn test_parse_prompt_answer() {
+        assert_eq!(parse_prompt_answer("\n"), Some(PromptAnswer::Action(DirtyAction::Skip)));
+        assert_eq!(parse_prompt_answer("S\n"), Some(PromptAnswer::Action(DirtyAction::Stash)));
+        assert_eq!(parse_prompt_answer("q"), Some(PromptAnswer::Action(DirtyAction::Abort)));
+        assert_eq!(parse_prompt_answer("d"), Some(PromptAnswer::ShowDiff));
+        assert_eq!(parse_prompt_answer("maybe"), None);
+    }
+
     #[test]
-    fn test_update_repository_not_a_repo() {
+    fn test_prompter_reasks_until_valid() {
         let tmp = tempfile::tempdir().unwrap();
-        let st = update_repository(tmp.path());
-        assert!(!st.success);
+        let prompter = scripted_prompter("what\ns\n");
+        assert_eq!(prompter.ask_dirty(tmp.path()), DirtyAction::Stash);
     }
 
     #[test]
-    fn test_update_repository_no_remote() {
+    fn test_prompter_eof_skips() {
         let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        let st = update_repository(tmp.path());
-        assert!(!st.success);
-        assert!(
-            st.message.contains("No remote") || st.message.contains("remote"),
-            "unexpected message: {}",
-            st.message
-        );
+        assert_eq!(scripted_prompter("").ask_dirty(tmp.path()), DirtyAction::Skip);
     }
 
     #[test]
-    fn test_update_repository_dirty_repo() {
+    fn test_prompter_abort_sets_flag() {
         let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        // Stage a new file
-        fs::write(tmp.path().join("dirty.txt"), "dirty").unwrap();
-        Command::new("git")
-            .args(["add", "dirty.txt"])
-            .current_dir(tmp.path())
-            .output()
-            .unwrap();
+        let prompter = scripted_prompter("q\n");
+        assert_eq!(prompter.ask_dirty(tmp.path()), DirtyAction::Abort);
+        assert!(prompter.control.is_aborted());
+        assert_eq!(prompter.ask_dirty(tmp.path()), DirtyAction::Abort);
+    }
 
-        let st = update_repository(tmp.path());
-        assert!(!st.success);
-        assert!(st.message.contains("local changes"), "unexpected: {}", st.message);
+    #[test]
+    fn test_bucket_summary() {
+        let bucketed = vec![
+            (Bucket::NoUpstream, PathBuf::from("/src/a")),
+            (Bucket::NoUpstream, PathBuf::from("/src/b")),
+        ];
+        assert_eq!(bucket_counts(&bucketed), " | 2 no upstream");
+        assert_eq!(bucket_lists(&bucketed), vec!["  no upstream: /src/a, /src/b"]);
+        assert_eq!(bucket_counts(&[]), "");
+        assert!(bucket_lists(&[]).is_empty());
+        let skipped = [(Bucket::Skipped, PathBuf::from("/src/c"))];
+        assert_eq!(bucket_counts(&skipped), " | 1 skipped");
     }
 
+    // ────────────────────────────────────────────────────────────
+    // failure_lists
+    // ────────────────────────────────────────────────────────────
+
     #[test]
-    fn test_update_repository_detached_head() {
-        let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        // Detach HEAD
-        let output = Command::new("git")
-            .args(["rev-parse", "HEAD"])
-            .current_dir(tmp.path())
-            .output()
-            .unwrap();
-        let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        Command::new("git")
-            .args(["checkout", &hash])
-            .current_dir(tmp.path())
-            .output()
-            .unwrap();
+    fn test_failure_lists() {
+        let failed = |path: &str, error| RepoStatus {
+            path: PathBuf::from(path),
+            error,
+            ..Default::default()
+        };
+        let results = vec![
+            failed("/src/a", Some(ErrorKind::Network)),
+            failed("/src/b", None),
+            failed("/src/c", Some(ErrorKind::Network)),
+            RepoStatus {
+                success: true,
+                error: Some(ErrorKind::Auth),
+                ..failed("/src/d", None)
+            },
+        ];
+        assert_eq!(
+            failure_lists(&results),
+            vec!["  network (2): /src/a, /src/c", "  other (1): /src/b"]
+        );
+    }
 
-        let st = update_repository(tmp.path());
-        assert!(!st.success);
-        assert!(st.message.contains("Detached HEAD"), "unexpected: {}", st.message);
+    #[test]
+    fn test_skipped_line() {
+        let status = |message: &str| RepoStatus {
+            path: PathBuf::from("/src/api"),
+            success: true,
+            message: message.to_string(),
+            ..Default::default()
+        };
+        let current = status("Already up to date");
+        assert_eq!(skipped_line(&current, None), "  api: Already up to date (unchanged)");
+        let no_upstream = RepoStatus {
+            bucket: Some(Bucket::NoUpstream),
+            ..status("No upstream configured")
+        };
+        assert_eq!(skipped_category(&no_upstream), Some("no upstream"));
+        let changed = RepoStatus {
+            files_changed: 3,
+            ..status("Fast-forwarded")
+        };
+        assert_eq!(skipped_category(&changed), None);
+        let failed = RepoStatus {
+            success: false,
+            ..status("Fetch failed")
+        };
+        assert_eq!(skipped_category(&failed), None);
     }
 
     #[test]
-    fn test_update_repository_already_up_to_date() {
-        let tmp = tempfile::tempdir().unwrap();
-        let bare_path = tmp.path().join("remote.git");
-        let work_path = tmp.path().join("work");
-        let clone_path = tmp.path().join("clone");
-
-        // Create bare remote, push from work, clone
-        init_bare_repo(&bare_path);
-        init_repo_with_commit(&work_path);
-        Command::new("git")
-            .args(["remote", "add", "origin", bare_path.to_str().unwrap()])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["push", "-u", "origin", "HEAD"])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["clone", bare_path.to_str().unwrap(), clone_path.to_str().unwrap()])
-            .output()
-            .unwrap();
+    fn test_failure_groups() {
+        let failed = |path: &str, message: &str| RepoStatus {
+            path: PathBuf::from(path),
+            message: message.to_string(),
+            error: Some(ErrorKind::Auth),
+            ..Default::default()
+        };
+        let results = vec![
+            failed("/src/a", "Fetch failed: authentication failed\nhint"),
+            failed("/src/b", "Fetch failed: host key not trusted"),
+            failed("/src/c", "Fetch failed: authentication failed"),
+            RepoStatus {
+                error: Some(ErrorKind::Network),
+                ..failed("/src/d", "Fetch failed: authentication failed")
+            },
+        ];
+        assert_eq!(
+            failure_groups(&results),
+            vec![
+                "  authentication: Fetch failed: authentication failed (2): /src/a, /src/c",
+                "  /src/b  authentication: Fetch failed: host key not trusted",
+                "  /src/d  network: Fetch failed: authentication failed",
+            ]
+        );
+    }
 
-        let st = update_repository(&clone_path);
-        assert!(st.success, "expected success, got: {}", st.message);
-        assert_eq!(st.message, "Already up to date");
+    #[test]
+    fn test_grouped_summary() {
+        let status = |path: &str| RepoStatus {
+            path: PathBuf::from(path),
+            success: true,
+            ..Default::default()
+        };
+        let commit = || groppy_core::PulledCommit {
+            id: gix::ObjectId::null(gix::hash::Kind::Sha1),
+            summary: String::new(),
+        };
+        let results = vec![
+            RepoStatus {
+                old_id: Some(gix::ObjectId::null(gix::hash::Kind::Sha1)),
+                new_id: Some(gix::ObjectId::empty_tree(gix::hash::Kind::Sha1)),
+                commits: vec![commit(), commit()],
+                ..status("/src/a")
+            },
+            RepoStatus {
+                success: false,
+                message: "Fetch failed: timed out\nretry later".to_string(),
+                error: Some(ErrorKind::Network),
+                ..status("/src/b")
+            },
+            RepoStatus {
+                bucket: Some(Bucket::Dirty),
+                ..status("/src/c")
+            },
+            RepoStatus {
+                bucket: Some(Bucket::NoUpstream),
+                ..status("/src/d")
+            },
+            status("/src/e"),
+            status("/src/f"),
+        ];
+        let lines: Vec<String> = grouped_summary(&results)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "updated (1):",
+                "  /src/a  +2 commits",
+                "local changes (1):",
+                "  /src/c",
+                "failed (1):",
+                "  /src/b  network: Fetch failed: timed out",
+                "  no upstream: /src/d",
+                "up to date: 2",
+            ]
+        );
     }
 
     #[test]
-    fn test_update_repository_with_changes() {
+    fn test_shared_object_lists() {
         let tmp = tempfile::tempdir().unwrap();
-        let bare_path = tmp.path().join("remote.git");
-        let work_path = tmp.path().join("work");
-        let clone_path = tmp.path().join("clone");
-
-        init_bare_repo(&bare_path);
-        init_repo_with_commit(&work_path);
-        Command::new("git")
-            .args(["remote", "add", "origin", bare_path.to_str().unwrap()])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["push", "-u", "origin", "HEAD"])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["clone", bare_path.to_str().unwrap(), clone_path.to_str().unwrap()])
-            .output()
-            .unwrap();
+        let borrowing = |path: &str, stores: &[&Path]| RepoStatus {
+            path: PathBuf::from(path),
+            alternates: stores.iter().map(|store| store.to_path_buf()).collect(),
+            ..Default::default()
+        };
+        let gone = Path::new("/nonexistent/ref.git");
+        let results = vec![
+            borrowing("/src/a", &[tmp.path()]),
+            borrowing("/src/b", &[]),
+            borrowing("/src/c", &[tmp.path(), gone]),
+        ];
+        assert_eq!(
+            shared_object_lists(&results),
+            vec![
+                "  shared objects /nonexistent/ref.git (missing): /src/c".to_string(),
+                format!("  shared objects {}: /src/a, /src/c", tmp.path().display()),
+            ]
+        );
+    }
 
-        // Add new commit and push from work
-        fs::write(work_path.join("new.txt"), "new content").unwrap();
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["commit", "-m", "add new file"])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
-        Command::new("git")
-            .args(["push"])
-            .current_dir(&work_path)
-            .output()
-            .unwrap();
+    // ────────────────────────────────────────────────────────────
+    // --confirm plan
+    // ────────────────────────────────────────────────────────────
+
+    fn planned(action: PlanAction) -> PlannedRepo {
+        PlannedRepo {
+            path: PathBuf::from("/r"),
+            action,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_yes_no() {
+        assert!(parse_yes_no("y\n"));
+        assert!(parse_yes_no(" YES "));
+        assert!(!parse_yes_no("\n"));
+        assert!(!parse_yes_no("n"));
+        assert!(!parse_yes_no("sure"));
+    }
+
+    #[test]
+    fn test_confirm_without_prompting() {
+        assert!(confirm("Proceed?", Interaction::AssumeDefaults).unwrap());
+        assert!(confirm("Proceed?", Interaction::Fail).is_err());
+    }
 
-        let st = update_repository(&clone_path);
-        assert!(st.success, "expected success, got: {}", st.message);
-        assert_eq!(st.files_changed, 1, "expected 1 file changed, got {}", st.files_changed);
+    #[test]
+    fn test_plan_summary_skips_zero_counts() {
+        let plan = vec![
+            planned(PlanAction::UpToDate),
+            planned(PlanAction::FastForward),
+            planned(PlanAction::FastForward),
+            planned(PlanAction::Dirty),
+        ];
+        assert_eq!(plan_summary(&plan), "2 fast-forward | 1 up to date | 1 dirty");
+        assert_eq!(plan_summary(&[]), "nothing to check");
     }
 
     // ────────────────────────────────────────────────────────────
-    // is_dirty (via gix::Repository::is_dirty)
+    // init_logging / --log-format
     // ────────────────────────────────────────────────────────────
 
     #[test]
-    fn test_is_dirty_clean() {
-        let tmp = tempfile::tempdir().unwrap();
-        let path = tmp.path().canonicalize().unwrap();
-        init_repo_with_commit(&path);
-        let repo = gix::open(&path).unwrap();
-        assert!(!repo.is_dirty().unwrap());
+    fn test_init_logging_rejects_bad_filter() {
+        // Fails before installing anything, so it is safe to run alongside other tests
+        let err = init_logging(Some("groppy=loud"), LogFormat::Text).unwrap_err();
+        assert!(err.to_string().starts_with("invalid --log-level groppy=loud"), "got: {err}");
     }
 
     #[test]
-    fn test_is_dirty_staged_file() {
-        let tmp = tempfile::tempdir().unwrap();
-        let path = tmp.path().canonicalize().unwrap();
-        init_repo_with_commit(&path);
-        fs::write(path.join("staged.txt"), "staged").unwrap();
-        Command::new("git")
-            .args(["add", "staged.txt"])
-            .current_dir(&path)
-            .output()
+    fn test_bell_parses_and_signals() {
+        let bell = |args: &[&str]| Cli::try_parse_from(args).unwrap().bell;
+        assert_eq!(bell(&["groppy"]), None);
+        assert_eq!(bell(&["groppy", "--bell"]), Some(Bell::Bel));
+        assert_eq!(bell(&["groppy", "--bell=notify"]), Some(Bell::Notify));
+        assert_eq!(attention(Bell::Bel, "3 repos, 0 failed"), "\x07");
+        let notify = attention(Bell::Notify, "3 repos, 0 failed");
+        assert!(notify.starts_with("\x1b]777;notify;groppy;3 repos, 0 failed\x07"));
+        assert!(notify.contains("\x1b]9;groppy: 3 repos"));
+        assert!(notify.ends_with('\x07'));
+    }
+
+    #[test]
+    fn test_stdin_list_rejects_prompts() {
+        let check = |args: &[&str]| {
+            let cli = Cli::try_parse_from(["groppy"].iter().chain(args)).unwrap();
+            check_stdin_users(&cli)
+        };
+        assert!(check(&["-f", "-", "--ask"]).is_err());
+        assert!(check(&["-f", "-", "--confirm"]).is_err());
+        assert!(check(&["-f", "-", "--confirm", "--yes"]).is_ok());
+        assert!(check(&["-f", "repos.txt", "--ask"]).is_ok());
+        assert!(check(&["-f", "-"]).is_ok());
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let elapsed = Duration::from_secs(83);
+        assert_eq!(timestamp(Timestamps::Clock, 1_792_065_599, elapsed), "[11:59:59]");
+        assert_eq!(timestamp(Timestamps::Elapsed, 1_792_065_599, elapsed), "[+00:01:23]");
+        let long = Duration::from_secs(26 * 3600);
+        assert_eq!(timestamp(Timestamps::Elapsed, 0, long), "[+26:00:00]");
+        let cli = Cli::try_parse_from(["groppy", "--timestamps"]).unwrap();
+        assert_eq!(cli.timestamps, Some(Timestamps::Clock));
+        let cli = Cli::try_parse_from(["groppy", "--timestamps=elapsed"]).unwrap();
+        assert_eq!(cli.timestamps, Some(Timestamps::Elapsed));
+    }
+
+    #[test]
+    fn test_format_template_parses() {
+        let cli = Cli::try_parse_from(["groppy", "--format", "{status} {path}"]).unwrap();
+        assert!(cli.format.is_some());
+        assert!(Cli::try_parse_from(["groppy", "--format", "{nope}"]).is_err());
+    }
+
+    #[test]
+    fn test_log_format_parses() {
+        let cli = Cli::try_parse_from(["groppy", "--log-format", "json", "--log-level", "debug"])
             .unwrap();
-        let repo = gix::open(&path).unwrap();
-        assert!(repo.is_dirty().unwrap());
+        assert_eq!(cli.log_format, LogFormat::Json);
+        assert_eq!(cli.log_level.as_deref(), Some("debug"));
+        assert_eq!(Cli::try_parse_from(["groppy"]).unwrap().log_format, LogFormat::Text);
+        assert!(Cli::try_parse_from(["groppy", "--log-format", "xml"]).is_err());
     }
 
     #[test]
-    fn test_is_dirty_unstaged_modification() {
-        let tmp = tempfile::tempdir().unwrap();
-        let path = tmp.path().canonicalize().unwrap();
-        init_repo_with_commit(&path);
-        fs::write(path.join("README.md"), "modified content\n").unwrap();
-        let repo = gix::open(&path).unwrap();
-        assert!(repo.is_dirty().unwrap());
+    fn test_import_subcommand() {
+        let cli = Cli::try_parse_from(["groppy", "import", "--from", "mrconfig", "x"]).unwrap();
+        let Some(Command::Import { from, file, into }) = cli.command else {
+            panic!("expected import");
+        };
+        assert_eq!((from, file, into), (ImportFormat::Mrconfig, PathBuf::from("x"), None));
+        assert!(Cli::try_parse_from(["groppy", "import", "--from", "repo", "x"]).is_err());
+        assert!(Cli::try_parse_from(["groppy", "src"]).unwrap().command.is_none());
     }
 
     #[test]
-    fn test_update_repository_unstaged_modification() {
-        let tmp = tempfile::tempdir().unwrap();
-        init_repo_with_commit(tmp.path());
-        fs::write(tmp.path().join("README.md"), "modified content\n").unwrap();
+    fn test_bench_subcommand() {
+        let cli = Cli::try_parse_from(["groppy", "bench", "-j", "4", "src"]).unwrap();
+        let Some(Command::Bench { directories, jobs, rounds }) = cli.command else {
+            panic!("expected bench");
+        };
+        assert_eq!((directories, jobs, rounds), (vec![PathBuf::from("src")], Some(4), 1));
+    }
 
-        let st = update_repository(tmp.path());
-        assert!(!st.success);
-        assert!(st.message.contains("local changes"), "unexpected: {}", st.message);
+    #[test]
+    fn test_changelog_subcommand() {
+        let since = |text| {
+            let cli = Cli::try_parse_from(["groppy", "changelog", "--since", text]).ok()?;
+            let Some(Command::Changelog { since }) = cli.command else {
+                panic!("expected changelog");
+            };
+            Some(since)
+        };
+        assert_eq!(since("last-run"), Some(Since::LastRun));
+        assert_eq!(since("1970-01-02"), Some(Since::Date(86_400)));
+        assert_eq!(since("2024-02-29"), Some(Since::Date(1_709_164_800)));
+        assert_eq!(since("2023-02-29"), None);
+        assert_eq!(since("2026-13-01"), None);
+        assert_eq!(since("yesterday"), None);
+        assert!(Cli::try_parse_from(["groppy", "changelog"]).is_err());
     }
 
-    // ────────────────────────────────────────────────────────────
-    // find_updated_target (needs a fetch outcome, tested via integration)
-    // ────────────────────────────────────────────────────────────
+    #[test]
+    fn test_bench_table_marks_fastest() {
+        let timings = [
+            ("default", (Duration::from_millis(2500), 0)),
+            ("--single-branch", (Duration::from_millis(1250), 2)),
+        ];
+        assert_eq!(
+            bench_table(&timings),
+            "default             2.50s\n--single-branch     1.25s  2 failed  fastest\n"
+        );
+    }
+
+    #[test]
+    fn test_gitea_flags_come_together() {
+        assert!(Cli::try_parse_from(["groppy", "--gitea-url", "https://codeberg.org"]).is_err());
+        let cli = Cli::try_parse_from([
+            "groppy",
+            "--gitea-url",
+            "https://codeberg.org",
+            "--gitea-org",
+            "forgejo",
+            "--clone-protocol",
+            "ssh",
+        ])
+        .unwrap();
+        assert_eq!(cli.clone_protocol, CloneProtocol::Ssh);
+        let listed = |cli: &Cli| forges(cli, None, &BTreeMap::new());
+        assert_eq!(listed(&cli).len(), 1);
+        let cli = Cli::try_parse_from(["groppy", "--bitbucket-workspace", "team"]).unwrap();
+        assert_eq!(listed(&cli)[0].describe(), "bitbucket team");
+        let cli = Cli::try_parse_from(["groppy", "--github-org", "rust-lang"]).unwrap();
+        assert_eq!(listed(&cli)[0].describe(), "github rust-lang");
+        let url = "https://dev.azure.com/contoso/Fabrikam";
+        let cli = Cli::try_parse_from(["groppy", "--azure-project", url]).unwrap();
+        assert_eq!(listed(&cli)[0].describe(), "azure devops contoso/Fabrikam");
+        assert!(Cli::try_parse_from(["groppy", "--azure-project", "contoso"]).is_err());
+        let cli = Cli::try_parse_from(["groppy", "--gitolite", "git@git.example.com"]).unwrap();
+        assert_eq!(listed(&cli)[0].describe(), "gitolite git@git.example.com");
+        let cli = Cli::try_parse_from(["groppy", "--manifest", "default.xml"]).unwrap();
+        assert_eq!(listed(&cli)[0].describe(), "manifest default.xml");
+        assert!(Cli::try_parse_from(["groppy", "--archive-extraneous", "attic"]).is_err());
+        assert!(parse_clone_protocol("ftp").is_err());
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("https://codeberg.org"), "codeberg.org");
+        assert_eq!(url_host("https://ann@git.example:3000/api/v1"), "git.example");
+        assert_eq!(url_host("gitea.local/"), "gitea.local");
+        let cli = Cli::try_parse_from(["groppy", "--token-file", "/run/secrets/gitea"]).unwrap();
+        assert_eq!(cli.token_file, Some(PathBuf::from("/run/secrets/gitea")));
+    }
 
-    // find_updated_target is tested implicitly through update_repository_with_changes
-    // Direct unit testing requires constructing gix::remote::fetch::Outcome which
-    //    has non-public fields — covered via the integration tests above.
+    #[test]
+    fn test_hidden_flags_last_one_wins() {
+        assert!(!Cli::try_parse_from(["groppy"]).unwrap().no_hidden);
+        assert!(Cli::try_parse_from(["groppy", "--hidden", "--no-hidden"]).unwrap().no_hidden);
+        assert!(!Cli::try_parse_from(["groppy", "--no-hidden", "--hidden"]).unwrap().no_hidden);
+    }
+
+    #[test]
+    fn test_taskbar_sequences() {
+        assert_eq!(taskbar(Taskbar::Clear), "\x1b]9;4;0;0\x07");
+        assert_eq!(taskbar(Taskbar::Normal(40)), "\x1b]9;4;1;40\x07");
+        assert_eq!(taskbar(Taskbar::Error(75)), "\x1b]9;4;2;75\x07");
+        assert_eq!(taskbar(Taskbar::Indeterminate), "\x1b]9;4;3;0\x07");
+    }
+
+    #[test]
+    fn test_window_title_sequences() {
+        assert_eq!(window_title("groppy 42/180"), "\x1b]2;groppy 42/180\x07");
+        assert!(restore_terminal().ends_with("\x1b[23;0t"));
+    }
 
     // ────────────────────────────────────────────────────────────
     // run_spinner (trivial animation loop — no logic to test)
@@ -0,0 +1,1110 @@
+//! Run reports written after the summary by `--report <kind>:<path>`
+//! (`html`, `csv`, `md`, `json`, `junit`) and `--report-file <path>` (JSON),
+//! plus the per-repo console formats: `--porcelain`, `--format` templates, and
+//! GitHub Actions annotations, and the `groppy changelog` digest.
+//!
+//! Each report covers every repository of the run, sorted by path, and is
+//! written in one go once all updates have finished.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use groppy_core::state::Pull;
+use groppy_core::{Bucket, HeadCommit, RepoStatus, gix};
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportKind {
+    Html,  // Standalone page with a sortable table
+    Csv,   // One row per repo for spreadsheets
+    Md,    // Markdown summary for PRs, wikis, and chat
+    Json,  // Every detail of the run, for archives and scripts
+    Junit, // JUnit XML, one test case per repo, for CI test views
+}
+
+/// Report kinds accepted by `--report`, for error messages.
+const KINDS: &str = "html, csv, md, json, junit";
+
+/// A `--report` argument: what to write and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportTarget {
+    pub kind: ReportKind,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportTarget {
+    type Err = String;
+
+    /// Parses `KIND:PATH`, e.g. `html:/srv/www/sync.html`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected KIND:PATH, got `{s}`"))?;
+        let kind = match kind {
+            "html" => ReportKind::Html,
+            "csv" => ReportKind::Csv,
+            "md" => ReportKind::Md,
+            "json" => ReportKind::Json,
+            "junit" => ReportKind::Junit,
+            other => return Err(format!("unknown report kind `{other}` (expected {KINDS})")),
+        };
+        if path.is_empty() {
+            return Err("report path is empty".to_string());
+        }
+        Ok(ReportTarget {
+            kind,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// Renders `results` in the target's format and writes the file.
+pub fn write(target: &ReportTarget, results: &[RepoStatus], elapsed: Duration) -> Result<()> {
+    let text = match target.kind {
+        ReportKind::Html => html(results, elapsed, generated_at()),
+        ReportKind::Csv => csv(results),
+        ReportKind::Md => markdown(results, elapsed, generated_at()),
+        ReportKind::Json => json(results, elapsed, generated_at()),
+        ReportKind::Junit => junit(results, elapsed),
+    };
+    std::fs::write(&target.path, text)
+        .with_context(|| format!("cannot write report {}", target.path.display()))
+}
+
+/// Current UTC time as `YYYY-MM-DD HH:MM:SS UTC`.
+fn generated_at() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_utc(secs)
+}
+
+/// Formats Unix seconds as a UTC timestamp (proleptic Gregorian calendar).
+fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// `--show-head` detail: `abc1234 by Alice, 2026-10-14 09:30:00 UTC`.
+pub fn describe_head(head: &HeadCommit) -> String {
+    let when = format_utc(u64::try_from(head.seconds).unwrap_or(0));
+    format!("{} by {}, {when}", head.id.to_hex_with_len(7), head.author)
+}
+
+/// Counts of each outcome, e.g. `12 updated, 3 ok, 1 failed`, in first-seen order.
+fn outcome_counts(results: &[RepoStatus]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for status in results {
+        match counts.iter_mut().find(|(outcome, _)| *outcome == status.outcome()) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((status.outcome(), 1)),
+        }
+    }
+    let parts: Vec<String> = counts.iter().map(|(outcome, n)| format!("{n} {outcome}")).collect();
+    parts.join(", ")
+}
+
+/// Human-readable byte count with binary units, e.g. `512 B` or `3.4 MiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Total pack bytes received across all repos.
+fn total_received(results: &[RepoStatus]) -> String {
+    format_bytes(results.iter().map(|status| status.bytes_received).sum())
+}
+
+/// Short hex of an optional commit ID, or an empty string.
+fn short_id(id: Option<gix::ObjectId>) -> String {
+    id.map(|id| id.to_hex_with_len(7).to_string()).unwrap_or_default()
+}
+
+// ────────────────────────────────────────────────────────────
+// HTML
+// ────────────────────────────────────────────────────────────
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;background:#1e1e2e;\
+color:#cdd6f4}table{border-collapse:collapse;width:100%}th,td{padding:.4rem .6rem;\
+border-bottom:1px solid #45475a;text-align:left;vertical-align:top}th{cursor:pointer;\
+background:#313244}code{font-size:.9em}.updated{color:#a6e3a1}.failed{color:#f38ba8}\
+.muted{color:#6c7086}details ul{margin:.3rem 0;padding-left:1.2rem}";
+
+const HTML_SCRIPT: &str = "document.querySelectorAll('th').forEach((th,i)=>th.onclick=()=>{\
+const tb=th.closest('table').tBodies[0],asc=th.dataset.asc!=='1';th.dataset.asc=asc?'1':'0';\
+[...tb.rows].sort((a,b)=>{const x=a.cells[i].dataset.sort??a.cells[i].innerText,\
+y=b.cells[i].dataset.sort??b.cells[i].innerText,n=parseFloat(x)-parseFloat(y);\
+return (isNaN(n)?x.localeCompare(y):n)*(asc?1:-1)}).forEach(r=>tb.appendChild(r))});";
+
+/// Standalone HTML page: run summary and a table sortable by clicking headers.
+fn html(results: &[RepoStatus], elapsed: Duration, generated: String) -> String {
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>groppy report</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>groppy report</h1>\n\
+         <p class=\"muted\">{} | {} repos: {} | elapsed {}s | received {}</p>\n\
+         <table>\n<thead><tr><th>Repository</th><th>Status</th><th>Change</th>\
+         <th>Commits</th><th>Files</th><th>Time (s)</th><th>Message</th></tr></thead>\n<tbody>\n",
+        escape(&generated),
+        results.len(),
+        escape(&outcome_counts(results)),
+        elapsed.as_secs(),
+        total_received(results)
+    );
+    for status in results {
+        html_row(&mut out, status);
+    }
+    let _ = write!(out, "</tbody>\n</table>\n<script>{HTML_SCRIPT}</script>\n</body>\n</html>\n");
+    out
+}
+
+/// One table row; pulled commits sit in a collapsed `<details>` list.
+fn html_row(out: &mut String, status: &RepoStatus) {
+    let change = match (status.old_id, status.new_id) {
+        (Some(_), Some(_)) if status.outcome() == "updated" => {
+            format!("<code>{}..{}</code>", short_id(status.old_id), short_id(status.new_id))
+        }
+        _ => String::new(),
+    };
+    let change = match &status.head {
+        Some(head) => {
+            let when = format_utc(u64::try_from(head.seconds).unwrap_or(0));
+            let by = format!("{}, {when}", head.author);
+            format!("{change}<br><span class=\"muted\">{}</span>", escape(&by))
+        }
+        None => change,
+    };
+    let commits = if status.commits.is_empty() {
+        String::new()
+    } else {
+        let items: String = status
+            .commits
+            .iter()
+            .map(|c| {
+                let id = c.id.to_hex_with_len(7).to_string();
+                format!("<li><code>{id}</code> {}</li>", escape(&c.summary))
+            })
+            .collect();
+        let more = match status.commits_omitted {
+            0 => String::new(),
+            n => format!("<li class=\"muted\">{n} older commits not listed</li>"),
+        };
+        let count = status.commit_count();
+        format!("<details><summary>{count}</summary><ul>{items}{more}</ul></details>")
+    };
+    let _ = writeln!(
+        out,
+        "<tr><td>{}</td><td class=\"{}\">{}</td><td>{change}</td>\
+         <td data-sort=\"{}\">{commits}</td><td>{}</td><td>{:.1}</td><td>{}</td></tr>",
+        escape(&status.path.display().to_string()),
+        status.outcome().replace(' ', "-"),
+        status.outcome(),
+        status.commit_count(),
+        status.files_changed,
+        status.duration.as_secs_f64(),
+        escape(&status.message).replace('\n', "<br>")
+    );
+}
+
+// ────────────────────────────────────────────────────────────
+// CSV
+// ────────────────────────────────────────────────────────────
+
+/// RFC 4180 CSV with a header row. IDs are full hex; `error` is the message of
+/// failed repos and empty otherwise.
+fn csv(results: &[RepoStatus]) -> String {
+    let mut out = String::from("path,status,old_oid,new_oid,commits,files,duration_s,error\r\n");
+    for status in results {
+        let error = if status.outcome() == "failed" { status.message.as_str() } else { "" };
+        let fields = [
+            status.path.display().to_string(),
+            status.outcome().to_string(),
+            status.old_id.map(|id| id.to_string()).unwrap_or_default(),
+            status.new_id.map(|id| id.to_string()).unwrap_or_default(),
+            status.commit_count().to_string(),
+            status.files_changed.to_string(),
+            format!("{:.3}", status.duration.as_secs_f64()),
+            error.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Quotes a CSV field when it contains a comma, quote, or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// ────────────────────────────────────────────────────────────
+// Markdown
+// ────────────────────────────────────────────────────────────
+
+/// Markdown summary: counts, a collapsible commit list per updated repo, a
+/// failure table, and one line per bucket. Renders on GitHub, GitLab, and most
+/// wikis; `<details>` degrades to plain text elsewhere.
+fn markdown(results: &[RepoStatus], elapsed: Duration, generated: String) -> String {
+    let mut out = format!(
+        "## groppy sync - {generated}\n\n{} repos: {} | elapsed {}s | received {}\n",
+        results.len(),
+        outcome_counts(results),
+        elapsed.as_secs(),
+        total_received(results)
+    );
+
+    let updated: Vec<&RepoStatus> = results.iter().filter(|s| s.outcome() == "updated").collect();
+    if !updated.is_empty() {
+        out.push_str("\n### Updated\n");
+    }
+    for status in updated {
+        let head = match &status.head {
+            Some(head) => format!(", now {}", escape(&describe_head(head))),
+            None => String::new(),
+        };
+        let _ = write!(
+            out,
+            "\n<details><summary><code>{}</code> - {} commits, {} files{head}</summary>\n\n",
+            escape(&status.path.display().to_string()),
+            status.commit_count(),
+            status.files_changed
+        );
+        for commit in &status.commits {
+            let id = commit.id.to_hex_with_len(7).to_string();
+            let _ = writeln!(out, "- `{id}` {}", escape(&commit.summary));
+        }
+        if status.commits_omitted > 0 {
+            let _ = writeln!(out, "- {} older commits not listed", status.commits_omitted);
+        }
+        out.push_str("\n</details>\n");
+    }
+
+    let failed: Vec<&RepoStatus> = results.iter().filter(|s| s.outcome() == "failed").collect();
+    if !failed.is_empty() {
+        out.push_str("\n### Failed\n\n| Repository | Error |\n| --- | --- |\n");
+    }
+    for status in failed {
+        let _ = writeln!(
+            out,
+            "| `{}` | {} |",
+            status.path.display(),
+            escape(&status.message).replace('|', "\\|").replace('\n', "<br>")
+        );
+    }
+
+    let mut others: Vec<(&str, Vec<String>)> = Vec::new();
+    for status in results.iter().filter(|s| s.bucket.is_some()) {
+        let path = format!("`{}`", status.path.display());
+        match others.iter_mut().find(|(outcome, _)| *outcome == status.outcome()) {
+            Some((_, paths)) => paths.push(path),
+            None => others.push((status.outcome(), vec![path])),
+        }
+    }
+    if !others.is_empty() {
+        out.push_str("\n### Not updated\n\n");
+    }
+    for (outcome, paths) in others {
+        let _ = writeln!(out, "- {outcome}: {}", paths.join(", "));
+    }
+    out
+}
+
+// ────────────────────────────────────────────────────────────
+// Changelog
+// ────────────────────────────────────────────────────────────
+
+/// `groppy changelog`: the commits pulled since `since` (Unix seconds) as
+/// Markdown, one section per repo sorted by path with the latest run's
+/// commits first.
+pub fn changelog(pulls: &[&Pull], since: u64) -> String {
+    let mut by_repo: BTreeMap<&Path, Vec<&Pull>> = BTreeMap::new();
+    for pull in pulls {
+        by_repo.entry(&pull.repo).or_default().push(pull);
+    }
+    let mut out = format!("## groppy changelog - since {}\n\n", format_utc(since));
+    if by_repo.is_empty() {
+        out.push_str("No commits pulled.\n");
+        return out;
+    }
+    let _ = writeln!(out, "{} commits in {} repos", pulls.len(), by_repo.len());
+    for (repo, mut pulls) in by_repo {
+        pulls.sort_by_key(|pull| std::cmp::Reverse(pull.at)); // Stable: keeps newest first
+        let _ = writeln!(
+            out,
+            "\n### `{}` - {} commits\n",
+            escape(&repo.display().to_string()),
+            pulls.len()
+        );
+        for pull in pulls {
+            let id = pull.id.get(..7).unwrap_or(&pull.id);
+            let _ = writeln!(out, "- `{id}` {}", escape(&pull.summary));
+        }
+    }
+    out
+}
+
+// ────────────────────────────────────────────────────────────
+// JSON
+// ────────────────────────────────────────────────────────────
+
+/// The whole run as one JSON document: when and how long, a `summary` of
+/// counts per outcome, and one object per repo with everything `RepoStatus`
+/// carries. IDs are full hex; fields with nothing to say are `null` or empty.
+fn json(results: &[RepoStatus], elapsed: Duration, generated: String) -> String {
+    let mut outcomes: BTreeMap<&str, usize> = BTreeMap::new();
+    for status in results {
+        *outcomes.entry(status.outcome()).or_default() += 1;
+    }
+    let repos: Vec<serde_json::Value> = results.iter().map(json_repo).collect();
+    let document = serde_json::json!({
+        "generated": generated,
+        "elapsed_s": elapsed.as_secs_f64(),
+        "summary": {
+            "total": results.len(),
+            "outcomes": outcomes,
+            "bytes_received": results.iter().map(|status| status.bytes_received).sum::<usize>(),
+        },
+        "repos": repos,
+    });
+    let mut out = serde_json::to_string_pretty(&document).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+/// One repo of the JSON report.
+fn json_repo(status: &RepoStatus) -> serde_json::Value {
+    let id = |id: Option<gix::ObjectId>| id.map(|id| id.to_string());
+    let commits: Vec<serde_json::Value> = status
+        .commits
+        .iter()
+        .map(|commit| serde_json::json!({ "id": commit.id.to_string(), "summary": commit.summary }))
+        .collect();
+    let head = status.head.as_ref().map(|head| {
+        serde_json::json!({
+            "id": head.id.to_string(),
+            "author": head.author,
+            "time": format_utc(u64::try_from(head.seconds).unwrap_or(0)),
+        })
+    });
+    let alternates: Vec<String> =
+        status.alternates.iter().map(|path| path.display().to_string()).collect();
+    serde_json::json!({
+        "path": status.path.display().to_string(),
+        "status": status.outcome(),
+        "message": status.message,
+        "error": status.error_kind().map(|kind| kind.label()),
+        "old_oid": id(status.old_id),
+        "new_oid": id(status.new_id),
+        "commits": commits,
+        "commits_omitted": status.commits_omitted,
+        "files_changed": status.files_changed,
+        "duration_s": status.duration.as_secs_f64(),
+        "bytes_received": status.bytes_received,
+        "ahead": status.ahead_behind.map(|(ahead, _)| ahead),
+        "behind": status.ahead_behind.map(|(_, behind)| behind),
+        "head": head,
+        "alternates": alternates,
+    })
+}
+
+// ────────────────────────────────────────────────────────────
+// JUnit
+// ────────────────────────────────────────────────────────────
+
+/// JUnit XML as read by Jenkins and GitLab: one suite, one test case per repo
+/// named by its path. Failed repos carry a `<failure>` with the error kind and
+/// full message; bucketed repos are `<skipped>` with their label.
+fn junit(results: &[RepoStatus], elapsed: Duration) -> String {
+    let failures = results.iter().filter(|s| s.outcome() == "failed").count();
+    let skipped = results.iter().filter(|s| s.bucket.is_some()).count();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"groppy\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" \
+         time=\"{:.3}\">",
+        results.len(),
+        elapsed.as_secs_f64()
+    );
+    let _ = writeln!(
+        out,
+        "  <testsuite name=\"groppy\" tests=\"{}\" failures=\"{failures}\" errors=\"0\" \
+         skipped=\"{skipped}\" time=\"{:.3}\">",
+        results.len(),
+        elapsed.as_secs_f64()
+    );
+    for status in results {
+        let _ = write!(
+            out,
+            "    <testcase classname=\"groppy\" name=\"{}\" time=\"{:.3}\"",
+            xml_text(&status.path.display().to_string()),
+            status.duration.as_secs_f64()
+        );
+        match (status.error_kind(), status.bucket) {
+            (Some(kind), _) => {
+                let first = status.message.lines().next().unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    ">\n      <failure type=\"{}\" message=\"{}\">{}</failure>\n    </testcase>",
+                    kind.label(),
+                    xml_text(first),
+                    xml_text(&status.message)
+                );
+            }
+            (None, Some(bucket)) => {
+                let _ = writeln!(
+                    out,
+                    ">\n      <skipped message=\"{}: {}\"/>\n    </testcase>",
+                    bucket.label(),
+                    xml_text(&status.message)
+                );
+            }
+            (None, None) => out.push_str("/>\n"),
+        }
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// Escapes text for XML and drops the control characters XML 1.0 forbids.
+fn xml_text(text: &str) -> String {
+    let allowed: String =
+        text.chars().filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r')).collect();
+    escape(&allowed)
+}
+
+// ────────────────────────────────────────────────────────────
+// Porcelain
+// ────────────────────────────────────────────────────────────
+
+/// `--porcelain=v1`: one line per repo, fields separated by tabs, in this fixed
+/// order: status, path, old oid, new oid, commits, files, message. Status is
+/// `updated`, `ok`, `failed`, `no-upstream`, or `no-remote`; empty fields stay
+/// empty; paths are quoted like git's `core.quotePath`; tabs and line breaks in
+/// the message become spaces. Never change this format; add a new version.
+pub fn porcelain_v1(results: &[RepoStatus]) -> String {
+    porcelain(results, v1_status)
+}
+
+/// `--porcelain=v2`: the v1 fields, with every bucket label as its own status
+/// (`skipped`, `dirty`, ...). Never change this format; add a new version.
+pub fn porcelain_v2(results: &[RepoStatus]) -> String {
+    porcelain(results, |status| status.outcome())
+}
+
+/// The v1 status: buckets added after v1 shipped show as what v1 printed for
+/// those repos before they existed.
+fn v1_status(status: &RepoStatus) -> &'static str {
+    match status.bucket {
+        Some(Bucket::Skipped) => "failed", // Aborted repos failed before --max-errors
+        Some(Bucket::Dirty) => "failed",   // Local changes failed the update before --unclean
+        Some(Bucket::Recent | Bucket::Offline) => "ok", // Up to date as far as v1 can tell
+        _ => status.outcome(),
+    }
+}
+
+/// Porcelain lines with the status word from `outcome`.
+fn porcelain(results: &[RepoStatus], outcome: fn(&RepoStatus) -> &'static str) -> String {
+    let mut out = String::new();
+    for status in results {
+        let message: String = status
+            .message
+            .chars()
+            .map(|c| if matches!(c, '\t' | '\n' | '\r') { ' ' } else { c })
+            .collect();
+        let _ = writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            outcome(status).replace(' ', "-"),
+            quote_path(&status.path),
+            status.old_id.map(|id| id.to_string()).unwrap_or_default(),
+            status.new_id.map(|id| id.to_string()).unwrap_or_default(),
+            status.commit_count(),
+            status.files_changed,
+            message
+        );
+    }
+    out
+}
+
+/// A path as git prints it with `core.quotePath` on: as is when plain ASCII,
+/// else in double quotes with C escapes for `"`, `\`, and control characters
+/// and octal escapes for every other byte outside printable ASCII.
+fn quote_path(path: &Path) -> String {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    if bytes.iter().all(|&b| (0x20..0x7f).contains(&b) && b != b'"' && b != b'\\') {
+        return path.display().to_string();
+    }
+    let mut out = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\t' => out.push_str("\\t"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            0x0b => out.push_str("\\v"),
+            0x0c => out.push_str("\\f"),
+            0x20..0x7f => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{byte:03o}");
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+// ────────────────────────────────────────────────────────────
+// Line templates
+// ────────────────────────────────────────────────────────────
+
+/// A `{name}` placeholder of a `--format` template.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Status,       // Outcome word: updated, ok, failed, or a bucket label
+    Path,         // Full repo path
+    Name,         // Last path component
+    Message,      // Result message
+    Ahead,        // Commits HEAD has that its upstream lacks, "" when unknown
+    Behind,       // Commits the upstream has that HEAD lacks, "" when unknown
+    FilesChanged, // Files the update changed
+    Commits,      // Commits HEAD moved over
+    Old,          // Short HEAD before, "" when unknown
+    New,          // Short HEAD after, "" when it did not move
+    Duration,     // Seconds spent, e.g. 1.25
+    Error,        // Failure kind, "" unless failed
+}
+
+impl Field {
+    const ALL: [Field; 12] = [
+        Field::Status,
+        Field::Path,
+        Field::Name,
+        Field::Message,
+        Field::Ahead,
+        Field::Behind,
+        Field::FilesChanged,
+        Field::Commits,
+        Field::Old,
+        Field::New,
+        Field::Duration,
+        Field::Error,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Field::Status => "status",
+            Field::Path => "path",
+            Field::Name => "name",
+            Field::Message => "message",
+            Field::Ahead => "ahead",
+            Field::Behind => "behind",
+            Field::FilesChanged => "files_changed",
+            Field::Commits => "commits",
+            Field::Old => "old",
+            Field::New => "new",
+            Field::Duration => "duration",
+            Field::Error => "error",
+        }
+    }
+
+    fn value(self, status: &RepoStatus) -> String {
+        match self {
+            Field::Status => status.outcome().to_string(),
+            Field::Path => status.path.display().to_string(),
+            Field::Name => status
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Field::Message => status.message.clone(),
+            Field::Ahead => status.ahead_behind.map(|(n, _)| n.to_string()).unwrap_or_default(),
+            Field::Behind => status.ahead_behind.map(|(_, n)| n.to_string()).unwrap_or_default(),
+            Field::FilesChanged => status.files_changed.to_string(),
+            Field::Commits => status.commit_count().to_string(),
+            Field::Old => short_id(status.old_id),
+            Field::New => short_id(status.new_id.filter(|&id| Some(id) != status.old_id)),
+            Field::Duration => format!("{:.2}", status.duration.as_secs_f64()),
+            Field::Error => status.error_kind().map(|kind| kind.label()).unwrap_or_default().into(),
+        }
+    }
+}
+
+/// A `--format` template for per-repo lines, e.g. `{status} {path}`; `{{` and
+/// `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineTemplate {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+impl FromStr for LineTemplate {
+    type Err = String;
+
+    /// Parses a template, rejecting unknown or unclosed placeholders.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed `{{{name}`")),
+                        }
+                    }
+                    let field = Field::ALL.into_iter().find(|field| field.label() == name);
+                    let Some(field) = field else {
+                        let known: Vec<&str> = Field::ALL.iter().map(|f| f.label()).collect();
+                        let known = known.join(", ");
+                        return Err(format!("unknown field `{{{name}}}` (known: {known})"));
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(field));
+                }
+                '}' => return Err("unmatched `}` (write `}}` for a literal brace)".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(LineTemplate { pieces })
+    }
+}
+
+impl LineTemplate {
+    /// The template filled in for one repo, uncolored.
+    pub fn render(&self, status: &RepoStatus) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.clone(),
+                Piece::Field(field) => field.value(status),
+            })
+            .collect()
+    }
+}
+
+// ────────────────────────────────────────────────────────────
+// GitHub Actions
+// ────────────────────────────────────────────────────────────
+
+/// `--github-annotations`: an `::error` workflow command per failed repo and
+/// a `::warning` per repo left alone for local changes, titled with the path,
+/// so they show up in the Actions run summary.
+pub fn github_annotations(results: &[RepoStatus]) -> String {
+    let mut out = String::new();
+    for status in results {
+        let (level, tag) = match (status.error_kind(), status.bucket) {
+            (Some(kind), _) => ("error", kind.label()),
+            (None, Some(Bucket::Dirty)) => ("warning", Bucket::Dirty.label()),
+            _ => continue,
+        };
+        let title = format!("groppy: {}", status.path.display());
+        let _ = writeln!(
+            out,
+            "::{level} title={}::{tag}: {}",
+            workflow_escape(&title, true),
+            workflow_escape(&status.message, false)
+        );
+    }
+    out
+}
+
+/// Escapes a workflow command message, or a property value when `property`
+/// is set (those also end at `:` and `,`).
+fn workflow_escape(text: &str, property: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            '\r' => out.push_str("%0D"),
+            '\n' => out.push_str("%0A"),
+            ':' if property => out.push_str("%3A"),
+            ',' if property => out.push_str("%2C"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes text for HTML element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use groppy_core::PulledCommit;
+
+    fn id(hex_digit: char) -> gix::ObjectId {
+        gix::ObjectId::from_hex(hex_digit.to_string().repeat(40).as_bytes()).unwrap()
+    }
+
+    fn updated(path: &str) -> RepoStatus {
+        RepoStatus {
+            path: PathBuf::from(path),
+            success: true,
+            message: "Updated successfully - 1 files changed".to_string(),
+            files_changed: 1,
+            old_id: Some(id('1')),
+            new_id: Some(id('2')),
+            commits: vec![PulledCommit {
+                id: id('2'),
+                summary: "Fix <script> escaping & more".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn failed(path: &str) -> RepoStatus {
+        RepoStatus {
+            path: PathBuf::from(path),
+            message: "Diverged from upstream - skipping update".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_report_target() {
+        let target: ReportTarget = "html:/tmp/out.html".parse().unwrap();
+        assert_eq!(target.kind, ReportKind::Html);
+        assert_eq!(target.path, PathBuf::from("/tmp/out.html"));
+        let target: ReportTarget = "json:run.json".parse().unwrap();
+        assert_eq!(target.kind, ReportKind::Json);
+        assert!("pdf:/tmp/out.pdf".parse::<ReportTarget>().is_err());
+        assert!("html:".parse::<ReportTarget>().is_err());
+        assert!("out.html".parse::<ReportTarget>().is_err());
+    }
+
+    #[test]
+    fn test_changelog_groups_by_repo() {
+        let pull = |at, repo: &str, digit: &str, summary: &str| Pull {
+            at,
+            repo: PathBuf::from(repo),
+            id: digit.repeat(40),
+            summary: summary.to_string(),
+        };
+        let pulls = [
+            pull(100, "/src/lib", "1", "Add <api>"),
+            pull(100, "/src/app", "2", "Fix parser"),
+            pull(200, "/src/lib", "3", "Bump version"),
+            pull(200, "/src/lib", "4", "Update docs"),
+        ];
+        let pulls: Vec<&Pull> = pulls.iter().collect();
+        assert_eq!(
+            changelog(&pulls, 0),
+            "## groppy changelog - since 1970-01-01 00:00:00 UTC\n\n\
+             4 commits in 2 repos\n\
+             \n### `/src/app` - 1 commits\n\n\
+             - `2222222` Fix parser\n\
+             \n### `/src/lib` - 3 commits\n\n\
+             - `3333333` Bump version\n\
+             - `4444444` Update docs\n\
+             - `1111111` Add &lt;api&gt;\n"
+        );
+        assert!(changelog(&[], 86_400).ends_with("No commits pulled.\n"));
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_utc(1_792_065_599), "2026-10-15 11:59:59 UTC");
+    }
+
+    #[test]
+    fn test_outcome_counts() {
+        let results = vec![updated("/a"), failed("/b"), updated("/c")];
+        assert_eq!(outcome_counts(&results), "2 updated, 1 failed");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(3 << 40), "3.0 TiB");
+        assert_eq!(format_bytes(2048 << 40), "2048.0 TiB");
+    }
+
+    #[test]
+    fn test_html_escapes_and_lists_commits() {
+        let results = vec![updated("/src/a&b"), failed("/src/c")];
+        let page = html(&results, Duration::from_secs(3), "now".to_string());
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<td>/src/a&amp;b</td>"));
+        assert!(page.contains("<code>1111111..2222222</code>"));
+        assert!(page.contains("Fix &lt;script&gt; escaping &amp; more"));
+        assert!(page.contains("<td class=\"failed\">failed</td>"));
+        assert!(!page.contains("<script> escaping"));
+    }
+
+    #[test]
+    fn test_csv_rows() {
+        let mut fail = failed("/src/c");
+        fail.message = "git fetch failed: \"denied\", retry".to_string();
+        let text = csv(&[updated("/src/a"), fail]);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(lines[0], "path,status,old_oid,new_oid,commits,files,duration_s,error");
+        assert_eq!(
+            lines[1],
+            format!("/src/a,updated,{},{},1,1,0.000,", "1".repeat(40), "2".repeat(40))
+        );
+        assert_eq!(lines[2], r#"/src/c,failed,,,0,0,0.000,"git fetch failed: ""denied"", retry""#);
+        assert_eq!(lines[3], "");
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+
+    #[test]
+    fn test_markdown_sections() {
+        let mut no_remote = failed("/src/d");
+        no_remote.success = true;
+        no_remote.bucket = Some(groppy_core::Bucket::NoRemote);
+        let mut fail = failed("/src/c");
+        fail.message = "a | b".to_string();
+        let text = markdown(&[updated("/src/a"), fail, no_remote], Duration::ZERO, "now".into());
+        let header = "## groppy sync - now\n\n3 repos: 1 updated, 1 failed, 1 no remote";
+        assert!(text.starts_with(header), "got: {text}");
+        assert!(text.contains("<summary><code>/src/a</code> - 1 commits, 1 files</summary>"));
+        assert!(text.contains("- `2222222` Fix &lt;script&gt; escaping &amp; more\n"));
+        assert!(text.contains("| `/src/c` | a \\| b |"));
+        assert!(text.contains("### Not updated\n\n- no remote: `/src/d`\n"));
+    }
+
+    #[test]
+    fn test_reports_show_head() {
+        let mut status = updated("/src/a");
+        status.head = Some(HeadCommit {
+            id: id('2'),
+            author: "Ann <Lee>".to_string(),
+            seconds: 1_792_065_599,
+        });
+        assert_eq!(
+            describe_head(status.head.as_ref().unwrap()),
+            "2222222 by Ann <Lee>, 2026-10-15 11:59:59 UTC"
+        );
+        let page = html(std::slice::from_ref(&status), Duration::ZERO, "now".into());
+        assert!(page.contains("<span class=\"muted\">Ann &lt;Lee&gt;, 2026-10-15 11:59:59 UTC"));
+        let text = markdown(&[status], Duration::ZERO, "now".into());
+        assert!(text.contains("1 files, now 2222222 by Ann &lt;Lee&gt;, 2026-10-15"), "{text}");
+    }
+
+    #[test]
+    fn test_markdown_omits_empty_sections() {
+        let text = markdown(&[], Duration::ZERO, "now".into());
+        assert!(!text.contains("###"));
+        assert!(text.contains("| elapsed 0s | received 0 B\n"), "got: {text}");
+    }
+
+    #[test]
+    fn test_json_document() {
+        let mut fail = failed("/src/c");
+        fail.error = Some(groppy_core::ErrorKind::Diverged);
+        let text = json(&[updated("/src/a"), fail], Duration::from_millis(1500), "now".into());
+        let doc: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(doc["generated"], "now");
+        assert_eq!(doc["elapsed_s"], 1.5);
+        assert_eq!(doc["summary"]["total"], 2);
+        assert_eq!(doc["summary"]["outcomes"]["updated"], 1);
+        assert_eq!(doc["summary"]["outcomes"]["failed"], 1);
+        let repo = &doc["repos"][0];
+        assert_eq!(repo["path"], "/src/a");
+        assert_eq!(repo["old_oid"], "1".repeat(40));
+        assert_eq!(repo["commits"][0]["summary"], "Fix <script> escaping & more");
+        assert_eq!(repo["error"], serde_json::Value::Null);
+        assert_eq!(doc["repos"][1]["error"], "diverged");
+        assert_eq!(doc["repos"][1]["new_oid"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_junit_cases() {
+        let mut fail = failed("/src/c");
+        fail.error = Some(groppy_core::ErrorKind::Diverged);
+        fail.message.push_str("\u{1b}[0m\nsee log");
+        let mut no_remote = failed("/src/d");
+        no_remote.success = true;
+        no_remote.bucket = Some(groppy_core::Bucket::NoRemote);
+        let text = junit(&[updated("/src/a&b"), fail, no_remote], Duration::from_secs(2));
+        assert!(text.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites"));
+        assert!(text.contains("tests=\"3\" failures=\"1\" skipped=\"1\" time=\"2.000\""));
+        let passed = "<testcase classname=\"groppy\" name=\"/src/a&amp;b\" time=\"0.000\"/>";
+        assert!(text.contains(passed));
+        assert!(text.contains(
+            "<failure type=\"diverged\" message=\"Diverged from upstream - skipping update[0m\">"
+        ));
+        assert!(text.contains("skipping update[0m\nsee log</failure>"));
+        assert!(text.contains("<skipped message=\"no remote: Diverged from upstream"));
+        assert!(text.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn test_line_template() {
+        let template: LineTemplate =
+            "{status} {name} {ahead}/{behind} {files_changed} {old}..{new} {{x}}".parse().unwrap();
+        let mut status = updated("/src/api");
+        status.ahead_behind = Some((0, 2));
+        assert_eq!(template.render(&status), "updated api 0/2 1 1111111..2222222 {x}");
+        let fail = failed("/src/c");
+        let template: LineTemplate = "{path}: {error} {new}|".parse().unwrap();
+        assert_eq!(template.render(&fail), "/src/c: other |");
+        assert!("{bogus}".parse::<LineTemplate>().unwrap_err().contains("unknown field `{bogus}`"));
+        assert!("{path".parse::<LineTemplate>().is_err());
+        assert!("a } b".parse::<LineTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let mut fail = failed("/src/a,b");
+        fail.error = Some(groppy_core::ErrorKind::Network);
+        fail.message = "Fetch failed: 100% lost\nretry".to_string();
+        let mut dirty = failed("/src/d");
+        dirty.success = true;
+        dirty.message = "Local changes - skipping update".to_string();
+        dirty.bucket = Some(Bucket::Dirty);
+        let mut no_remote = failed("/src/e");
+        no_remote.success = true;
+        no_remote.bucket = Some(Bucket::NoRemote);
+        let text = github_annotations(&[fail, updated("/src/c"), dirty, no_remote]);
+        assert_eq!(
+            text,
+            "::error title=groppy%3A /src/a%2Cb::network: Fetch failed: 100%25 lost%0Aretry\n\
+             ::warning title=groppy%3A /src/d::dirty: Local changes - skipping update\n"
+        );
+    }
+
+    #[test]
+    fn test_porcelain_v1_lines() {
+        let mut no_upstream = failed("/src/b");
+        no_upstream.bucket = Some(groppy_core::Bucket::NoUpstream);
+        let mut fail = failed("/src/c");
+        fail.message = "first\tline\nsecond".to_string();
+        let text = porcelain_v1(&[updated("/src/a"), no_upstream, fail]);
+        let lines: Vec<&str> = text.lines().collect();
+        let (old, new) = ("1".repeat(40), "2".repeat(40));
+        let message = "Updated successfully - 1 files changed";
+        assert_eq!(lines[0], format!("updated\t/src/a\t{old}\t{new}\t1\t1\t{message}"));
+        assert!(lines[1].starts_with("no-upstream\t/src/b\t\t\t0\t0\t"));
+        assert_eq!(lines[2], "failed\t/src/c\t\t\t0\t0\tfirst line second");
+        assert!(lines.iter().all(|l| l.split('\t').count() == 7));
+    }
+
+    #[test]
+    fn test_porcelain_versions() {
+        let mut skipped = failed("/src/d");
+        skipped.bucket = Some(Bucket::Skipped);
+        let mut dirty = failed("/src/e");
+        dirty.success = true;
+        dirty.bucket = Some(Bucket::Dirty);
+        let mut recent = updated("/src/f");
+        recent.new_id = None;
+        recent.bucket = Some(Bucket::Recent);
+        let results = [skipped, dirty, recent];
+        let v1: Vec<String> = porcelain_v1(&results).lines().map(String::from).collect();
+        let v2: Vec<String> = porcelain_v2(&results).lines().map(String::from).collect();
+        assert!(v1[0].starts_with("failed\t/src/d\t"));
+        assert!(v2[0].starts_with("skipped\t/src/d\t"));
+        assert!(v1[1].starts_with("failed\t/src/e\t"));
+        assert!(v2[1].starts_with("dirty\t/src/e\t"));
+        assert!(v1[2].starts_with("ok\t/src/f\t"));
+        assert!(v2[2].starts_with("recent\t/src/f\t"));
+    }
+
+    #[test]
+    fn test_porcelain_quotes_paths() {
+        assert_eq!(quote_path(Path::new("/src/plain repo")), "/src/plain repo");
+        assert_eq!(quote_path(Path::new("/src/a\tb\nc")), r#""/src/a\tb\nc""#);
+        assert_eq!(quote_path(Path::new("/src/\"q\"\\x")), r#""/src/\"q\"\\x""#);
+        assert_eq!(quote_path(Path::new("/src/caf\u{e9}")), r#""/src/caf\303\251""#);
+        let text = porcelain_v1(&[failed("/src/two\tfields")]);
+        assert!(text.starts_with("failed\t\"/src/two\\tfields\"\t"));
+        assert_eq!(text.trim_end().split('\t').count(), 7);
+    }
+
+    #[test]
+    fn test_write_creates_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let target = ReportTarget {
+            kind: ReportKind::Html,
+            path: tmp.path().join("report.html"),
+        };
+        write(&target, &[updated("/a")], Duration::ZERO).unwrap();
+        let page = std::fs::read_to_string(&target.path).unwrap();
+        assert!(page.contains("1 repos: 1 updated"));
+    }
+}